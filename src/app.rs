@@ -8,15 +8,39 @@ use std::{
     path::Path,
 };
 
+use crate::detectability::DetectabilityAnalysis;
+use crate::editor::selection_ui;
+use crate::estimation::{ProfileData, SourceEstimation};
+use crate::export::{
+    bodies_obj, bodies_stl, bodies_vtk, grid_vtk, profile_csv, save_file, save_text,
+    ExportMetadata, GridFormat,
+};
+use crate::figure::{ColourBar, Figure, FigureSettings, Mark, Panel, Series};
 use crate::gravity_objects::{
-    Cuboid, DataType, GravityCalc, GravityModelObject, GravityObject, InputUI, Sphere,
+    Cuboid, DataType, GravityCalc, GravityModelObject, GravityObject, Sphere,
+};
+use crate::import::MeshImport;
+use crate::map::{colour_bar, GridData, MapView};
+use crate::model::{Model, SelectMode};
+use crate::noise::NoiseModel;
+use crate::outliner::Outliner;
+use crate::plot::{
+    is_convex, ComponentLayout, Footprint, PlotGroup, PlotView, Section, SectionSlice, Snap,
 };
-use crate::model::Model;
-use crate::plot::PlotView;
+use crate::processing::{self, Processing};
+use crate::profile::{PolylineProfile, Profiles};
+use crate::sensitivity::{Layout, Sensitivity, Survey};
+use crate::sensor::GradiometerSensor;
+use crate::template::TemplateLibrary;
+use crate::timeline::TimelineDialog;
+use crate::transform::TransformDialog;
+use crate::uncertainty::{ObservedProfile, Uncertainty};
+use crate::view3d::{Scene, Surface, View3D};
+use crate::workspace::{Misfit, Workspace};
 
 use egui::{
-    plot::{Legend, Line, LineStyle, LinkedAxisGroup, Plot, PlotPoints, PlotUi, Points, Polygon},
-    Align2, Color32, Context, Key, Pos2, Sense, Stroke, Style, Ui, Vec2, Visuals,
+    plot::{Legend, Line, LineStyle, Plot, PlotPoints, PlotUi, Points, Polygon, VLine},
+    Align2, Color32, Context, Key, Pos2, Style, Ui, Visuals,
 };
 use itertools::izip;
 use ndarray::{s, Array1, Array2, Axis};
@@ -56,10 +80,29 @@ pub struct GravityBuilderApp {
     model: Model,
     data_params: DataParameters,
     #[serde(skip)]
-    plot_group: [LinkedAxisGroup; 2],
+    plot_group: PlotGroup,
     plot_view: PlotView,
     plot_range: [f64; 2],
     add_object: AddObject,
+    component_layout: ComponentLayout,
+    sensor: GradiometerSensor,
+    sensor_view: PlotView,
+    detectability: DetectabilityAnalysis,
+    map_view: MapView,
+    view3d: View3D,
+    snap: Snap,
+    transform: TransformDialog,
+    outliner: Outliner,
+    mesh_import: MeshImport,
+    templates: TemplateLibrary,
+    figure: FigureSettings,
+    profiles: Profiles,
+    section: SectionSlice,
+    timeline: TimelineDialog,
+    sources: SourceEstimation,
+    sensitivity: Sensitivity,
+    uncertainty: Uncertainty,
+    workspace: Workspace,
 }
 
 // pub struct Points {
@@ -68,8 +111,11 @@ pub struct GravityBuilderApp {
 // }
 
 #[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct DataParameters {
     data_type: DataType,
+    /// Components shown side by side in the "Components" window.
+    data_types: Vec<DataType>,
     x_start: f64,
     x_end: f64,
     x_n: usize,
@@ -83,12 +129,15 @@ pub struct DataParameters {
     z: f64,
     grid_x_n: usize,
     grid_y_n: usize,
+    noise: NoiseModel,
+    processing: Processing,
 }
 
 impl Default for DataParameters {
     fn default() -> Self {
         Self {
             data_type: DataType::Gz,
+            data_types: vec![],
             x_start: -10.,
             x_end: 10.,
             x_n: 200,
@@ -102,6 +151,8 @@ impl Default for DataParameters {
             y_gradient: 0.,
             grid_x_n: 50,
             grid_y_n: 50,
+            noise: NoiseModel::default(),
+            processing: Processing::default(),
         }
     }
 }
@@ -145,20 +196,56 @@ impl DataParameters {
         points * 1.0001
     }
 
+    /// Model background at the `points_xy` stations, laid out as the grid.
+    pub fn background_grid(
+        &self,
+        model: &Model,
+        data_type: &DataType,
+        points: &Array2<f64>,
+    ) -> Array2<f64> {
+        let background = model.background.calculate(data_type, points);
+        GridData::from_points(points, &background, self.grid_x_n, self.grid_y_n).values
+    }
+
+    pub fn export_metadata(&self, model: &Model) -> ExportMetadata {
+        ExportMetadata {
+            model_name: model.name.to_string(),
+            component: self.data_type.label().to_string(),
+            units: self.data_type.units().to_string(),
+            height: self.z,
+            gradient: [self.x_gradient, self.y_gradient],
+            noise_seed: self.noise.enabled.then_some(self.noise.seed),
+        }
+    }
+
     pub fn ui(&mut self, ui: &mut Ui) {
         egui::ComboBox::from_label("Component")
-            .selected_text(format!("{:?}", self.data_type))
+            .selected_text(self.data_type.label())
             .show_ui(ui, |ui| {
-                ui.selectable_value(&mut self.data_type, DataType::Gx, "gx");
-                ui.selectable_value(&mut self.data_type, DataType::Gy, "gy");
-                ui.selectable_value(&mut self.data_type, DataType::Gz, "gz");
-                ui.selectable_value(&mut self.data_type, DataType::Gxx, "gxx");
-                ui.selectable_value(&mut self.data_type, DataType::Gxy, "gxy");
-                ui.selectable_value(&mut self.data_type, DataType::Gxz, "gxz");
-                ui.selectable_value(&mut self.data_type, DataType::Gyy, "gyy");
-                ui.selectable_value(&mut self.data_type, DataType::Gyz, "gyz");
-                ui.selectable_value(&mut self.data_type, DataType::Gzz, "gzz");
+                for data_type in DataType::ALL {
+                    ui.selectable_value(&mut self.data_type, data_type, data_type.label());
+                }
             });
+        ui.collapsing("Compare components", |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for data_type in DataType::ALL {
+                    let mut checked = self.data_types.contains(&data_type);
+                    if ui.checkbox(&mut checked, data_type.label()).changed() {
+                        if checked {
+                            self.data_types.push(data_type);
+                        } else {
+                            self.data_types.retain(|dt| *dt != data_type);
+                        }
+                    }
+                }
+            });
+            if ui.button("Gradiometer set (gz, gzz, gxz, gyz)").clicked() {
+                self.data_types = vec![DataType::Gz, DataType::Gzz, DataType::Gxz, DataType::Gyz];
+            }
+            if ui.button("Clear").clicked() {
+                self.data_types.clear();
+            }
+        });
         ui.collapsing("x", |ui| {
             ui.label("start");
             ui.add(egui::Slider::new(&mut self.x_start, -50.0..=0.).text("m"));
@@ -192,6 +279,12 @@ impl DataParameters {
             ui.label("y n measurements");
             ui.add(egui::Slider::new(&mut self.grid_y_n, 1..=50));
         });
+        ui.collapsing("noise", |ui| {
+            self.noise.ui(ui);
+        });
+        ui.collapsing("processing", |ui| {
+            self.processing.ui(ui);
+        });
     }
 }
 
@@ -200,13 +293,29 @@ impl Default for GravityBuilderApp {
         Self {
             model: Model::default(),
             data_params: DataParameters::default(),
-            plot_group: [
-                LinkedAxisGroup::new(true, false),
-                LinkedAxisGroup::new(true, false),
-            ],
+            plot_group: PlotGroup::default(),
             plot_view: PlotView::XZ,
             plot_range: [-10., 10.],
             add_object: AddObject::default(),
+            component_layout: ComponentLayout::ProfilesXZ,
+            sensor: GradiometerSensor::default(),
+            sensor_view: PlotView::XZ,
+            detectability: DetectabilityAnalysis::default(),
+            map_view: MapView::default(),
+            view3d: View3D::default(),
+            snap: Snap::default(),
+            transform: TransformDialog::default(),
+            outliner: Outliner::default(),
+            mesh_import: MeshImport::default(),
+            templates: TemplateLibrary::default(),
+            figure: FigureSettings::default(),
+            profiles: Profiles::default(),
+            section: SectionSlice::default(),
+            timeline: TimelineDialog::default(),
+            sources: SourceEstimation::default(),
+            sensitivity: Sensitivity::default(),
+            uncertainty: Uncertainty::default(),
+            workspace: Workspace::default(),
         }
     }
 }
@@ -243,8 +352,30 @@ impl eframe::App for GravityBuilderApp {
             plot_view,
             plot_range,
             plot_group,
+            component_layout,
+            sensor,
+            sensor_view,
+            detectability,
+            map_view,
+            view3d,
+            snap,
+            transform,
+            outliner,
+            mesh_import,
+            templates,
+            figure,
+            profiles,
+            section,
+            timeline,
+            sources,
+            sensitivity,
+            uncertainty,
+            workspace,
         } = self;
 
+        timeline.update(ctx, model);
+        uncertainty.update(ctx);
+
         // Examples of how to create different panels and windows.
         // Pick whichever suits you.
         // Tip: a good default choice is to just keep the `CentralPanel`.
@@ -278,6 +409,13 @@ impl eframe::App for GravityBuilderApp {
                             }
                         }
                     }
+                    if ui.button("Load as new model").clicked() {
+                        if let Some(path) = rfd::FileDialog::new().pick_file() {
+                            if let Ok(loaded_model) = read_model_from_file(path) {
+                                workspace.add(model, loaded_model);
+                            }
+                        }
+                    }
                 });
             });
         });
@@ -343,6 +481,10 @@ impl eframe::App for GravityBuilderApp {
                         plot_group,
                         &mut PlotView::XZ,
                         self.plot_range,
+                        snap,
+                        section,
+                        sources,
+                        workspace,
                     );
                 });
                 egui::Window::new("YZ View").show(ctx, |ui| {
@@ -354,6 +496,10 @@ impl eframe::App for GravityBuilderApp {
                         plot_group,
                         &mut PlotView::YZ,
                         self.plot_range,
+                        snap,
+                        section,
+                        sources,
+                        workspace,
                     );
                 });
 
@@ -361,36 +507,231 @@ impl eframe::App for GravityBuilderApp {
                 self.plot_range[1] = max_x.max(max_y);
             });
 
-            egui::Window::new("XY View").show(ctx, |ui| {
-                plot_xy(ctx, ui, model, data_params);
-                let gradient = colorous::VIRIDIS;
-                ui.horizontal_wrapped(|ui| {
-                    for i in 1..=10 {
-                        let (rect, _response) =
-                            ui.allocate_at_least(Vec2 { x: 32., y: 16. }, Sense::hover());
-                        let colour = gradient.eval_continuous(1. / i as f64);
-
-                        ui.painter().rect(
-                            rect,
-                            0.,
-                            Color32::from_rgba_premultiplied(colour.r, colour.g, colour.b, 100),
-                            Stroke::new(2., Color32::TRANSPARENT),
+            if !data_params.data_types.is_empty() {
+                egui::Window::new("Components").show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.radio_value(component_layout, ComponentLayout::ProfilesXZ, "x profile");
+                        ui.radio_value(component_layout, ComponentLayout::ProfilesYZ, "y profile");
+                        ui.radio_value(
+                            component_layout,
+                            ComponentLayout::TensorGrid,
+                            "Tensor grid",
                         );
+                    });
+                    match component_layout {
+                        ComponentLayout::ProfilesXZ => plot_component_profiles(
+                            ctx,
+                            ui,
+                            model,
+                            data_params,
+                            plot_group,
+                            &PlotView::XZ,
+                        ),
+                        ComponentLayout::ProfilesYZ => plot_component_profiles(
+                            ctx,
+                            ui,
+                            model,
+                            data_params,
+                            plot_group,
+                            &PlotView::YZ,
+                        ),
+                        ComponentLayout::TensorGrid => {
+                            plot_tensor_grid(ui, model, data_params, plot_group)
+                        }
                     }
                 });
+            }
+
+            if sensor.enabled {
+                egui::Window::new("Gradiometer").show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.radio_value(sensor_view, PlotView::XZ, "x profile");
+                        ui.radio_value(sensor_view, PlotView::YZ, "y profile");
+                    });
+                    plot_sensor(ctx, ui, model, data_params, sensor, plot_group, sensor_view);
+                });
+            }
+
+            if detectability.open {
+                let mut open = detectability.open;
+                egui::Window::new("Detectability")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        detectability_ui(ui, detectability, data_params);
+                    });
+                detectability.open = open;
+            }
+
+            egui::Window::new("XY View").show(ctx, |ui| {
+                plot_xy(
+                    ctx,
+                    ui,
+                    model,
+                    data_params,
+                    plot_group,
+                    map_view,
+                    profiles,
+                    snap,
+                    sources,
+                );
+                ui.collapsing("Map style", |ui| {
+                    map_view.style.ui(ui);
+                });
+                ui.collapsing("Profiles", |ui| {
+                    profiles.ui(ui);
+                });
             });
 
+            for (i, profile) in profiles.profiles.iter_mut().enumerate() {
+                if profile.open {
+                    let mut open = profile.open;
+                    egui::Window::new(format!("Profile {}", profile.name))
+                        .id(egui::Id::new(("polyline_profile", i)))
+                        .open(&mut open)
+                        .show(ctx, |ui| {
+                            plot_polyline(
+                                ctx,
+                                ui,
+                                model,
+                                data_params,
+                                plot_group,
+                                profile,
+                                i,
+                                workspace,
+                            );
+                        });
+                    profile.open = open;
+                }
+            }
+
+            if view3d.open {
+                let mut open = view3d.open;
+                egui::Window::new("3D View")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        plot_3d(ctx, ui, model, data_params, map_view, view3d);
+                    });
+                view3d.open = open;
+            }
+
+            if outliner.open {
+                let mut open = outliner.open;
+                egui::Window::new("Outliner")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        outliner.ui(ui, model);
+                    });
+                outliner.open = open;
+            }
+
+            if transform.open {
+                let mut open = transform.open;
+                egui::Window::new("Transform")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        transform.ui(ui, model);
+                    });
+                transform.open = open;
+            }
+
+            if sources.open {
+                let mut open = sources.open;
+                egui::Window::new("Source estimation")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        estimation_ui(ui, model, data_params, sources);
+                    });
+                sources.open = open;
+            }
+
+            if sensitivity.open {
+                let mut open = sensitivity.open;
+                egui::Window::new("Sensitivity")
+                    .open(&mut open)
+                    .vscroll(true)
+                    .show(ctx, |ui| {
+                        sensitivity_ui(ctx, ui, model, data_params, sensitivity);
+                    });
+                sensitivity.open = open;
+            }
+
+            if uncertainty.open {
+                let mut open = uncertainty.open;
+                egui::Window::new("Uncertainty (MCMC)")
+                    .open(&mut open)
+                    .vscroll(true)
+                    .show(ctx, |ui| {
+                        uncertainty_ui(ui, model, data_params, uncertainty);
+                    });
+                uncertainty.open = open;
+            }
+
+            if workspace.open {
+                let mut open = workspace.open;
+                egui::Window::new("Model comparison")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        workspace_ui(ui, model, data_params, workspace);
+                    });
+                workspace.open = open;
+            }
+
+            if timeline.open {
+                let mut open = timeline.open;
+                egui::Window::new("Timeline")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        timeline_ui(ui, model, data_params, timeline);
+                    });
+                timeline.open = open;
+            }
+
             egui::Window::new("Settings").show(ctx, |ui| {
                 egui::CollapsingHeader::new("Data").show(ui, |ui| {
                     data_params.ui(ui);
                 });
 
+                egui::CollapsingHeader::new("Cross-section").show(ui, |ui| {
+                    section.ui(ui);
+                });
+
+                egui::CollapsingHeader::new("Export").show(ui, |ui| {
+                    export_ui(ui, model, data_params, map_view, section, figure);
+                });
+
+                egui::CollapsingHeader::new("Tools").show(ui, |ui| {
+                    ui.checkbox(&mut detectability.open, "Detectability / survey design");
+                    ui.checkbox(&mut view3d.open, "3D view");
+                    ui.checkbox(&mut outliner.open, "Outliner");
+                    ui.checkbox(&mut timeline.open, "Timeline (4D)");
+                    ui.checkbox(&mut sources.open, "Source estimation");
+                    ui.checkbox(&mut sensitivity.open, "Sensitivity / resolution");
+                    ui.checkbox(&mut uncertainty.open, "Uncertainty (MCMC)");
+                    ui.checkbox(&mut workspace.open, "Model comparison");
+                });
+
+                egui::CollapsingHeader::new("Editing").show(ui, |ui| {
+                    ui.label("Hold M to move, L to resize and P to rotate the selection.");
+                    ui.label("Hold B and drag to box select: shift adds, ctrl subtracts, both intersect.");
+                    snap.ui(ui);
+                    ui.checkbox(&mut transform.open, "Transform dialog");
+                });
+
+                egui::CollapsingHeader::new("Gradiometer").show(ui, |ui| {
+                    ui.checkbox(&mut sensor.enabled, "Show finite-baseline sensor");
+                    sensor.ui(ui);
+                });
+
                 egui::CollapsingHeader::new("Model").show(ui, |ui| {
                     ui.horizontal(|ui| {
                         ui.label("Name: ");
                         ui.text_edit_singleline(&mut model.name);
                     });
 
+                    egui::CollapsingHeader::new("Parameter ranges").show(ui, |ui| {
+                        model.ranges.ui(ui);
+                    });
+
                     egui::CollapsingHeader::new("Create Object").show(ui, |ui| {
                         ui.radio_value(
                             &mut add_object.object_type,
@@ -418,6 +759,7 @@ impl eframe::App for GravityBuilderApp {
                                 GravityObject::Sphere(_) => GravityObject::Sphere(Sphere {
                                     ..Default::default()
                                 }),
+                                GravityObject::Polyhedron(_) => add_object.object_type.clone(),
                             };
                             model.add_object(GravityModelObject {
                                 object,
@@ -425,25 +767,38 @@ impl eframe::App for GravityBuilderApp {
                                 id: model.object_counter,
                                 colour: add_object.colour,
                                 is_selected: true,
+                                is_hidden: false,
+                                is_locked: false,
                             });
                         }
                     });
+
+                    egui::CollapsingHeader::new("Import Mesh").show(ui, |ui| {
+                        mesh_import.ui(ui, model);
+                    });
+
+                    egui::CollapsingHeader::new("Templates").show(ui, |ui| {
+                        templates.ui(ui, model);
+                    });
+
+                    egui::CollapsingHeader::new("Background").show(ui, |ui| {
+                        model.background.ui(ui);
+                    });
                 });
             });
 
-            if model.number_objects_selected() == 1 {
-                for (_, object) in model.objects.iter_mut() {
-                    match object {
-                        Some(obj) => {
-                            if obj.is_selected {
-                                egui::Window::new("Selected Object").show(ctx, |ui| {
-                                    obj.ui(ui);
-                                });
-                            }
-                        }
-                        None => {}
-                    }
-                }
+            let n_selected = model.number_objects_selected();
+            if n_selected > 0 {
+                let title = if n_selected == 1 {
+                    "Selected Object"
+                } else {
+                    "Selected Objects"
+                };
+                egui::Window::new(title)
+                    .id(egui::Id::new("selected_objects"))
+                    .show(ctx, |ui| {
+                        selection_ui(ui, model);
+                    });
             }
         });
     }
@@ -461,11 +816,478 @@ fn read_model_from_file<P: AsRef<Path>>(path: P) -> Result<Model, Box<dyn Error>
     Ok(u)
 }
 
-fn plot_xy(ctx: &Context, ui: &mut Ui, model: &mut Model, data_params: &mut DataParameters) {
-    let edit_mode = ctx.input().key_down(Key::M) || ctx.input().key_down(Key::L);
+/// Buttons to save the profiles and the XY grid of the current component, the model
+/// bodies as meshes and the plots as figures.
+fn export_ui(
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    map_view: &MapView,
+    section: &SectionSlice,
+    figure: &mut FigureSettings,
+) {
+    let metadata = data_params.export_metadata(model);
+    let mut result = None;
+    ui.horizontal(|ui| {
+        ui.label("Profiles (CSV): ");
+        for (label, points, stream) in [
+            ("x", data_params.points_xz(), 0),
+            ("y", data_params.points_yz(), 1),
+        ] {
+            if ui.button(label).clicked() {
+                let csv = profile_csv(
+                    &points,
+                    &profile_columns(model, data_params, &points, stream),
+                    &metadata,
+                );
+                let file_name = format!("{}_{label}_profile.csv", metadata.file_stem());
+                result = Some(save_text(&file_name, &csv));
+            }
+        }
+    });
+    let grid_data = || {
+        let points = data_params.points_xy();
+        let data = if data_params.noise.enabled {
+            data_params
+                .noise
+                .apply(model, &data_params.data_type, &points, 2)
+                .noisy
+        } else {
+            model.calculate(&data_params.data_type, &points)
+        };
+        (points, data)
+    };
+    ui.horizontal_wrapped(|ui| {
+        ui.label("XY grid: ");
+        for format in GridFormat::ALL {
+            if ui.button(format.label()).clicked() {
+                let (points, data) = grid_data();
+                let grid = data_params.processing.map_grid(
+                    GridData::from_points(
+                        &points,
+                        &data,
+                        data_params.grid_x_n,
+                        data_params.grid_y_n,
+                    ),
+                    &data_params.background_grid(model, &data_params.data_type, &points),
+                );
+                let file_name = format!("{}_grid.{}", metadata.file_stem(), format.extension());
+                result = Some(save_text(&file_name, &format.write(&grid, &metadata)));
+            }
+        }
+        if ui
+            .button("VTK")
+            .on_hover_text("Structured grid at the observation surface")
+            .clicked()
+        {
+            let (points, data) = grid_data();
+            let vtk = grid_vtk(
+                &points,
+                &data,
+                data_params.grid_x_n,
+                data_params.grid_y_n,
+                &metadata,
+            );
+            result = Some(save_text(
+                &format!("{}_grid.vtk", metadata.file_stem()),
+                &vtk,
+            ));
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Bodies: ");
+        let stem: String = model
+            .name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        if ui.button("VTK").clicked() {
+            result = Some(save_text(&format!("{stem}_bodies.vtk"), &bodies_vtk(model)));
+        }
+        if ui.button("OBJ").clicked() {
+            result = Some(save_text(&format!("{stem}_bodies.obj"), &bodies_obj(model)));
+        }
+        if ui.button("STL").clicked() {
+            result = Some(save_text(&format!("{stem}_bodies.stl"), &bodies_stl(model)));
+        }
+    });
+    ui.separator();
+    figure.ui(ui);
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Figures: ");
+        for (label, name) in [
+            ("x profile", "x_profile"),
+            ("y profile", "y_profile"),
+            ("XY map", "map"),
+        ] {
+            let build = || match name {
+                "x_profile" => profile_figure(model, data_params, PlotView::XZ, section),
+                "y_profile" => profile_figure(model, data_params, PlotView::YZ, section),
+                _ => map_figure(model, data_params, map_view),
+            };
+            ui.menu_button(label, |ui| {
+                let file_name = format!("{}_{name}", metadata.file_stem());
+                if ui.button("SVG").clicked() {
+                    let svg = build().svg(figure);
+                    result = Some(save_text(&format!("{file_name}.svg"), &svg));
+                    ui.close_menu();
+                }
+                if ui.button("PNG").clicked() {
+                    result = Some(
+                        build()
+                            .png(figure)
+                            .and_then(|png| save_file(&format!("{file_name}.png"), &png)),
+                    );
+                    ui.close_menu();
+                }
+            });
+        }
+    });
+
+    let id = egui::Id::new("export_status");
+    match result {
+        Some(Ok(true)) => {
+            ui.data().insert_temp(id, "Saved".to_string());
+        }
+        Some(Ok(false)) => {
+            ui.data().remove::<String>(id);
+        }
+        Some(Err(err)) => {
+            ui.data().insert_temp(id, format!("Export failed: {err}"));
+        }
+        None => {}
+    }
+    if let Some(status) = ui.data().get_temp::<String>(id) {
+        ui.label(status);
+    }
+}
+
+/// Per-object, combined and, when noise is enabled, noisy values along a profile.
+fn profile_columns(
+    model: &Model,
+    data_params: &DataParameters,
+    points: &Array2<f64>,
+    stream: u64,
+) -> Vec<(String, Array1<f64>)> {
+    let data_type = &data_params.data_type;
+    let mut columns: Vec<(String, Array1<f64>)> = model
+        .ordered_ids()
+        .iter()
+        .filter_map(|id| model.objects.get(id))
+        .flatten()
+        .map(|obj| {
+            (
+                format!("{}: {}", obj.id, obj.name),
+                obj.object.as_calc().calculate(data_type, points),
+            )
+        })
+        .collect();
+    let background = model.background.calculate(data_type, points);
+    if model.background.is_active() {
+        columns.push(("background".to_string(), background.clone()));
+    }
+    let mut observed = model.calculate(data_type, points);
+    columns.push(("combined".to_string(), observed.clone()));
+    if data_params.noise.enabled {
+        let noisy = data_params.noise.apply(model, data_type, points, stream);
+        observed = noisy.noisy.clone();
+        columns.push(("noisy".to_string(), noisy.noisy));
+    }
+    let distances: Vec<f64> = points
+        .rows()
+        .into_iter()
+        .map(|p| (p[0] - points[[0, 0]]).hypot(p[1] - points[[0, 1]]))
+        .collect();
+    if let Some(processed) = data_params
+        .processing
+        .profile(&distances, &observed, &background)
+    {
+        columns.push(("processed".to_string(), processed));
+    }
+    columns
+}
+
+/// Visible objects in display order.
+fn visible_objects(model: &Model) -> Vec<&GravityModelObject> {
+    model
+        .ordered_ids()
+        .iter()
+        .filter_map(|id| model.objects.get(id))
+        .flatten()
+        .filter(|obj| !obj.is_hidden)
+        .collect()
+}
+
+/// Vertices of an outline, with circles as 100 sided polygons.
+fn footprint_points(footprint: Footprint) -> Vec<[f64; 2]> {
+    match footprint {
+        Footprint::Polygon(vertices) => vertices,
+        Footprint::Circle { centre, radius } => (0..100)
+            .map(|i| {
+                let t = TAU * i as f64 / 100.;
+                [centre[0] + radius * t.cos(), centre[1] + radius * t.sin()]
+            })
+            .collect(),
+    }
+}
+
+/// Outlines of the visible objects in a model view, as drawn by `PlotView::footprint`.
+fn footprint_series(model: &Model, plot_view: PlotView) -> Vec<Series> {
+    visible_objects(model)
+        .into_iter()
+        .map(|obj| {
+            Series::new(
+                Some(format!("{}: {}", obj.id, obj.name)),
+                obj.colour,
+                Mark::Polygon,
+                footprint_points(plot_view.footprint(&obj.object)),
+            )
+        })
+        .collect()
+}
+
+/// Outlines of the visible objects cut by the section slab at `plane`, with the bodies
+/// outside the slab faded and dashed.
+fn section_series(
+    model: &Model,
+    section: &SectionSlice,
+    plot_view: PlotView,
+    plane: f64,
+) -> Vec<Series> {
+    visible_objects(model)
+        .into_iter()
+        .flat_map(|obj| {
+            let name = format!("{}: {}", obj.id, obj.name);
+            match section.section(&obj.object, &plot_view, plane) {
+                Section::Inside(footprint) => vec![Series::new(
+                    Some(name),
+                    obj.colour,
+                    Mark::Polygon,
+                    footprint_points(footprint),
+                )],
+                // One legend entry for all the pieces.
+                Section::Cut(loops) => loops
+                    .into_iter()
+                    .enumerate()
+                    .map(|(k, outline)| {
+                        let name = (k == 0).then(|| name.clone());
+                        Series::new(name, obj.colour, Mark::Polygon, outline)
+                    })
+                    .collect(),
+                Section::Outside(_, _) if section.hide_outside => vec![],
+                Section::Outside(footprint, distance) => {
+                    let mut points = footprint_points(footprint);
+                    points.extend(points.first().copied());
+                    vec![Series::new(
+                        Some(format!("{name} ({distance:.1} m off section)")),
+                        obj.colour.linear_multiply(section.fade(distance)),
+                        Mark::DashedLine,
+                        points,
+                    )]
+                }
+            }
+        })
+        .collect()
+}
+
+/// Profile data above the model cross-section, as shown in the XZ and YZ plots.
+fn profile_figure(
+    model: &Model,
+    data_params: &DataParameters,
+    plot_view: PlotView,
+    section: &SectionSlice,
+) -> Figure {
+    let (points, idx, stream, axis) = match plot_view {
+        PlotView::XZ => (data_params.points_xz(), 0, 0, "x"),
+        _ => (data_params.points_yz(), 1, 1, "y"),
+    };
+    let data_type = &data_params.data_type;
+    let pos = points.index_axis(Axis(1), idx);
+    let trace = |values: &Array1<f64>| -> Vec<[f64; 2]> {
+        pos.iter()
+            .zip(values.iter())
+            .map(|(p, v)| [*p, *v])
+            .collect()
+    };
+
+    let mut data_series: Vec<Series> = visible_objects(model)
+        .into_iter()
+        .map(|obj| {
+            Series::new(
+                Some(format!("{}: {}", obj.id, obj.name)),
+                obj.colour,
+                Mark::Line,
+                trace(&obj.object.as_calc().calculate(data_type, &points)),
+            )
+        })
+        .collect();
+    let background = model.background.calculate(data_type, &points);
+    if model.background.is_active() {
+        data_series.push(Series::new(
+            Some("Background".to_string()),
+            Color32::GRAY,
+            Mark::DashedLine,
+            trace(&background),
+        ));
+    }
+    let mut observed = model.calculate(data_type, &points);
+    data_series.push(Series::new(
+        Some("Combined".to_string()),
+        Color32::BLACK,
+        Mark::DashedLine,
+        trace(&observed),
+    ));
+    if data_params.noise.enabled {
+        let noisy = data_params.noise.apply(model, data_type, &points, stream);
+        data_series.push(Series::new(
+            Some(format!("Noisy (SNR {:.1})", noisy.snr)),
+            Color32::BLACK,
+            Mark::Points,
+            trace(&noisy.noisy),
+        ));
+        observed = noisy.noisy;
+    }
+    if let Some(processed) = data_params
+        .processing
+        .profile(&pos.to_vec(), &observed, &background)
+    {
+        data_series.push(Series::new(
+            Some("Processed".to_string()),
+            processing::COLOUR,
+            Mark::Line,
+            trace(&processed),
+        ));
+    }
+
+    let mut model_series = if section.enabled {
+        let [i, j] = plot_view.axes();
+        section_series(model, section, plot_view, points[[0, 3 - i - j]])
+    } else {
+        footprint_series(model, plot_view)
+    };
+    model_series.push(Series::new(
+        Some("Points".to_string()),
+        Color32::BLACK,
+        Mark::Points,
+        trace(&points.index_axis(Axis(1), 2).to_owned()),
+    ));
+
+    Figure {
+        title: format!("{}: {} along {axis}", model.name, data_type.label()),
+        panels: vec![
+            Panel {
+                title: "Data".to_string(),
+                x_label: format!("{axis} (m)"),
+                y_label: format!("{} ({})", data_type.label(), data_type.units()),
+                series: data_series,
+                ..Default::default()
+            },
+            Panel {
+                title: "Model".to_string(),
+                x_label: format!("{axis} (m)"),
+                y_label: "z (m)".to_string(),
+                series: model_series,
+                equal_aspect: true,
+                ..Default::default()
+            },
+        ],
+    }
+}
+
+/// The XY map with its contours, profile lines, body outlines and colour bar.
+fn map_figure(model: &Model, data_params: &DataParameters, map_view: &MapView) -> Figure {
+    let data_type = &data_params.data_type;
+    let points = data_params.points_xy();
+    let data = if data_params.noise.enabled {
+        data_params.noise.apply(model, data_type, &points, 2).noisy
+    } else {
+        model.calculate(data_type, &points)
+    };
+    let grid = data_params.processing.map_grid(
+        GridData::from_points(&points, &data, data_params.grid_x_n, data_params.grid_y_n),
+        &data_params.background_grid(model, data_type, &points),
+    );
+    let style = &map_view.style;
+    let range = style.colour_range(&grid.values);
+
+    let mut series = vec![];
+    if style.contours {
+        for level in style.contour_levels(range) {
+            for segment in grid.contour_segments(level) {
+                series.push(Series::new(
+                    None,
+                    Color32::from_black_alpha(160),
+                    Mark::Line,
+                    segment.to_vec(),
+                ));
+            }
+        }
+    }
+    series.extend(footprint_series(model, PlotView::XY));
+    series.push(Series::new(
+        Some("x profile".to_string()),
+        Color32::BLACK,
+        Mark::Line,
+        vec![
+            [data_params.x_start, data_params.x_y],
+            [data_params.x_end, data_params.x_y],
+        ],
+    ));
+    series.push(Series::new(
+        Some("y profile".to_string()),
+        Color32::BLACK,
+        Mark::DashedLine,
+        vec![
+            [data_params.y_x, data_params.y_start],
+            [data_params.y_x, data_params.y_end],
+        ],
+    ));
+
+    let mut label = format!("{} ({})", data_type.label(), data_type.units());
+    if data_params.processing.is_map_processed() {
+        label.push_str(", processed");
+    }
+    Figure {
+        title: format!("{}: {}", model.name, data_type.label()),
+        panels: vec![Panel {
+            title: format!("Map at z = {} m", data_params.z),
+            x_label: "x (m)".to_string(),
+            y_label: "y (m)".to_string(),
+            series,
+            image: Some((style.raster(&grid, range), grid.bounds())),
+            colour_bar: Some(ColourBar {
+                colormap: style.colormap,
+                range,
+                label,
+            }),
+            equal_aspect: true,
+        }],
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn plot_xy(
+    ctx: &Context,
+    ui: &mut Ui,
+    model: &mut Model,
+    data_params: &mut DataParameters,
+    plot_group: &PlotGroup,
+    map_view: &mut MapView,
+    profiles: &mut Profiles,
+    snap: &Snap,
+    sources: &SourceEstimation,
+) {
+    let edit_mode = ctx.input().key_down(Key::M)
+        || ctx.input().key_down(Key::L)
+        || ctx.input().key_down(Key::P)
+        || ctx.input().key_down(Key::B)
+        || ctx.input().key_down(Key::D);
     let data_points = data_params.points_xy();
+    let mut colour_range = [0., 1.];
     let plot = Plot::new("xy")
         .view_aspect(1.0)
+        .link_axis(plot_group.axes_xy.clone())
+        .link_cursor(plot_group.cursors_xy.clone())
         // .include_x(-10.)
         // .include_x(10.)
         // .include_y(-10.)
@@ -477,13 +1299,11 @@ fn plot_xy(ctx: &Context, ui: &mut Ui, model: &mut Model, data_params: &mut Data
         .allow_drag(if edit_mode { false } else { true });
     // .legend(Legend::default());
 
-    let mut data_total: Array1<f64> = Array1::zeros(data_points.len_of(Axis(0)));
-
-    plot.show(ui, |plot_ui| {
+    let hover = plot.show(ui, |plot_ui| {
         for (_, object) in model.objects.iter() {
             match object {
-                Some(obj) => {
-                    let data = match &obj.object {
+                Some(obj) if !obj.is_hidden => {
+                    match &obj.object {
                         GravityObject::Cuboid(cuboid) => {
                             let edge_lines = cuboid.edge_lines_xy();
                             for edge in edge_lines {
@@ -511,7 +1331,6 @@ fn plot_xy(ctx: &Context, ui: &mut Ui, model: &mut Model, data_params: &mut Data
                                     .color(obj.colour)
                                     .highlight(obj.is_selected),
                             );
-                            cuboid.calculate(&data_params.data_type, &data_points)
                         }
                         GravityObject::Sphere(sphere) => {
                             let polygon = Polygon::new(PlotPoints::from_parametric_callback(
@@ -530,16 +1349,40 @@ fn plot_xy(ctx: &Context, ui: &mut Ui, model: &mut Model, data_params: &mut Data
                                     .color(obj.colour)
                                     .highlight(obj.is_selected),
                             );
-                            sphere.calculate(&data_params.data_type, &data_points)
+                        }
+                        GravityObject::Polyhedron(polyhedron) => {
+                            for edge in polyhedron.edge_lines(PlotView::XY.axes()) {
+                                plot_ui.line(
+                                    edge.name(format!("{}: {}", obj.id, obj.name.to_string()))
+                                        .color(obj.colour)
+                                        .highlight(obj.is_selected),
+                                );
+                            }
                         }
                     };
-                    data_total = data_total + &data;
                 }
-                None => {}
+                _ => {}
             };
         }
-        let min = data_total.min().unwrap();
-        let max = data_total.max().unwrap();
+        let data_total = if data_params.noise.enabled {
+            data_params
+                .noise
+                .apply(model, &data_params.data_type, &data_points, 2)
+                .noisy
+        } else {
+            model.calculate(&data_params.data_type, &data_points)
+        };
+        let grid = data_params.processing.map_grid(
+            GridData::from_points(
+                &data_points,
+                &data_total,
+                data_params.grid_x_n,
+                data_params.grid_y_n,
+            ),
+            &data_params.background_grid(model, &data_params.data_type, &data_points),
+        );
+        colour_range = map_view.style.colour_range(&grid.values);
+        map_view.show(ctx, plot_ui, &grid, colour_range);
 
         let line = Line::new(vec![
             [data_params.x_start, data_params.x_y],
@@ -553,40 +1396,20 @@ fn plot_xy(ctx: &Context, ui: &mut Ui, model: &mut Model, data_params: &mut Data
         ]);
         plot_ui.line(line.name("y").color(line_colour(ctx)).highlight(true));
 
-        let gradient = colorous::VIRIDIS;
-        for (x, y, val) in izip!(
-            data_points.index_axis(Axis(1), 0),
-            data_points.index_axis(Axis(1), 1),
-            data_total.clone(),
-        ) {
-            let val_norm = normalize_range(val, *min, *max);
-            // println!(
-            //     "{},{},{},{}, {}",
-            //     val,
-            //     min,
-            //     max,
-            //     val_norm,
-            //     ((1. - val_norm) * 255.) as u8
-            // );
-            let colour = gradient.eval_continuous(val_norm);
+        profiles.show(ctx, plot_ui);
+        sources.show(plot_ui, [0, 1]);
+        // Clicks add profile vertices while drawing.
+        let clicked =
+            plot_ui.plot_hovered() && plot_ui.plot_clicked() && !ctx.input().key_down(Key::D);
 
-            plot_ui.points(
-                Points::new([*x, *y])
-                    .radius(5.)
-                    .name(format!("{val:.2}"))
-                    // .color(Color32::from_rgb(colour.r, colour.g, colour.b)),
-                    .color(Color32::from_rgba_premultiplied(
-                        colour.r, colour.g, colour.b, 100,
-                    )),
-            );
-        }
         let mut view = PlotView::XY;
-        if plot_ui.plot_hovered() && plot_ui.plot_clicked() && !ctx.input().modifiers.shift {
+        if clicked && !ctx.input().modifiers.shift {
             model.deselect_all();
             model.select_by_click(plot_ui, &mut view);
-        } else if plot_ui.plot_hovered() && plot_ui.plot_clicked() && ctx.input().modifiers.shift {
+        } else if clicked && ctx.input().modifiers.shift {
             model.select_by_click(plot_ui, &mut view);
         }
+        box_select(ctx, plot_ui, model, &view);
         if plot_ui.plot_hovered() && ctx.input().key_pressed(Key::C) && ctx.input().modifiers.ctrl {
             model.copy_selected();
         }
@@ -594,25 +1417,265 @@ fn plot_xy(ctx: &Context, ui: &mut Ui, model: &mut Model, data_params: &mut Data
             model.delete_objects();
         }
         if plot_ui.plot_hovered() && ctx.input().key_down(Key::M) {
-            model.translate_selected(plot_ui, &mut view);
+            model.translate_selected(plot_ui, &mut view, snap);
         }
         if plot_ui.plot_hovered()
             && ctx.input().key_down(Key::L)
             && model.number_objects_selected() == 1
         {
-            model.scale_selected(plot_ui, &mut view);
+            model.scale_selected(plot_ui, &mut view, snap);
+        }
+        if plot_ui.plot_hovered() && ctx.input().key_down(Key::P) {
+            model.rotate_selected(plot_ui, &mut view, snap);
         }
+        plot_ui
+            .pointer_coordinate()
+            .and_then(|p| grid.value_at(p.x, p.y).map(|value| [p.x, p.y, value]))
     });
+    let units = data_params.data_type.units();
+    let processed = if data_params.processing.is_map_processed() {
+        ", processed"
+    } else {
+        ""
+    };
+    colour_bar(
+        ui,
+        &map_view.style.colormap,
+        colour_range,
+        &format!("{} ({units}){processed}", data_params.data_type.label()),
+        PLOT_WIDTH,
+    );
+    match hover.inner {
+        Some([x, y, value]) => ui.label(format!("x {x:.2} m, y {y:.2} m: {value:.3} {units}")),
+        None => ui.label(""),
+    };
+}
+
+/// Data and section plots of a profile drawn on the XY map, against distance along the
+/// line. Bodies are projected onto the vertical section under the line and dotted lines
+/// mark the bends.
+#[allow(clippy::too_many_arguments)]
+fn plot_polyline(
+    ctx: &Context,
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    plot_group: &mut PlotGroup,
+    profile: &PolylineProfile,
+    index: usize,
+    workspace: &Workspace,
+) {
+    let data_type = &data_params.data_type;
+    let (points, distances) = profile.points(
+        data_params.z,
+        [data_params.x_gradient, data_params.y_gradient],
+    );
+    let trace = |values: &Array1<f64>| -> Vec<[f64; 2]> {
+        distances
+            .iter()
+            .zip(values.iter())
+            .map(|(d, v)| [*d, *v])
+            .collect()
+    };
+    let vertex_distances = profile.vertex_distances();
+    let bends = &vertex_distances[1..vertex_distances.len() - 1];
+    let show_bends = |plot_ui: &mut PlotUi| {
+        for distance in bends {
+            plot_ui.vline(
+                VLine::new(*distance)
+                    .color(Color32::GRAY)
+                    .style(LineStyle::dotted_dense()),
+            );
+        }
+    };
+    let (axes, cursors) = plot_group.polyline(index).clone();
+
+    Plot::new(("polyline_data", index))
+        .view_aspect(2.0)
+        .link_axis(axes.clone())
+        .link_cursor(cursors.clone())
+        .width(PLOT_WIDTH)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            for obj in visible_objects(model) {
+                let data = obj.object.as_calc().calculate(data_type, &points);
+                plot_ui.line(
+                    Line::new(trace(&data))
+                        .name(format!("{}: {}", obj.id, obj.name))
+                        .color(obj.colour)
+                        .highlight(obj.is_selected),
+                );
+            }
+            let background = model.background.calculate(data_type, &points);
+            if model.background.is_active() {
+                plot_ui.line(
+                    Line::new(trace(&background))
+                        .name("Background")
+                        .color(Color32::GRAY)
+                        .style(LineStyle::dotted_loose()),
+                );
+            }
+            let mut observed = model.calculate(data_type, &points);
+            let (name, colour) = workspace.combined_style(model, line_colour(ctx));
+            plot_ui.line(
+                Line::new(trace(&observed))
+                    .name(name)
+                    .color(colour)
+                    .style(LineStyle::dashed_loose()),
+            );
+            workspace.show_overlays(plot_ui, model, data_type, &points, trace);
+            if data_params.noise.enabled {
+                // Streams 0 to 2 are the x and y profiles and the XY grid.
+                let noisy = data_params
+                    .noise
+                    .apply(model, data_type, &points, 3 + index as u64);
+                plot_ui.points(
+                    Points::new(trace(&noisy.noisy))
+                        .name(format!("Noisy (SNR {:.1})", noisy.snr))
+                        .color(line_colour(ctx))
+                        .radius(1.5),
+                );
+                observed = noisy.noisy;
+            }
+            if let Some(processed) =
+                data_params
+                    .processing
+                    .profile(&distances, &observed, &background)
+            {
+                plot_ui.line(
+                    Line::new(trace(&processed))
+                        .name("Processed")
+                        .color(processing::COLOUR),
+                );
+            }
+            show_bends(plot_ui);
+        });
+
+    Plot::new(("polyline_section", index))
+        .view_aspect(2.0)
+        .data_aspect(1.0)
+        .link_axis(axes)
+        .link_cursor(cursors)
+        .width(PLOT_WIDTH)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.points(
+                Points::new(trace(&points.column(2).to_owned()))
+                    .name("Points")
+                    .color(line_colour(ctx)),
+            );
+            for obj in visible_objects(model) {
+                let centre = obj.object.as_calc().centre();
+                let (_, offset) = profile.project([centre[0], centre[1]]);
+                plot_ui.polygon(
+                    Polygon::new(PlotPoints::new(footprint_points(
+                        profile.footprint(&obj.object),
+                    )))
+                    .name(format!("{}: {} ({offset:.1} m off line)", obj.id, obj.name))
+                    .color(obj.colour)
+                    .highlight(obj.is_selected),
+                );
+            }
+            show_bends(plot_ui);
+        });
+    ui.label("Distance along the profile (m). Bodies are projected onto the section.");
 }
 
+fn plot_3d(
+    ctx: &Context,
+    ui: &mut Ui,
+    model: &mut Model,
+    data_params: &DataParameters,
+    map_view: &MapView,
+    view3d: &mut View3D,
+) {
+    let surface = if view3d.show_surface {
+        let points = data_params.points_xy();
+        let data = if data_params.noise.enabled {
+            data_params
+                .noise
+                .apply(model, &data_params.data_type, &points, 2)
+                .noisy
+        } else {
+            model.calculate(&data_params.data_type, &points)
+        };
+        let grid =
+            GridData::from_points(&points, &data, data_params.grid_x_n, data_params.grid_y_n);
+        let range = map_view.style.colour_range(&grid.values);
+        Some(Surface {
+            points,
+            grid,
+            range,
+            colormap: map_view.style.colormap,
+        })
+    } else {
+        None
+    };
+    let scene = Scene {
+        points: vec![data_params.points_xz(), data_params.points_yz()],
+        surface,
+    };
+    view3d.show(ctx, ui, model, &scene);
+}
+
+/// Rubber band selection, drag with B held. Shift adds to the selection, ctrl subtracts
+/// and shift + ctrl intersects.
+fn box_select(ctx: &Context, plot_ui: &mut PlotUi, model: &mut Model, plot_view: &PlotView) {
+    let id = egui::Id::new(("box_select", *plot_view));
+    let corners: Option<[[f64; 2]; 2]> = ctx.data().get_temp(id);
+    let pointer = plot_ui.pointer_coordinate().map(|p| [p.x, p.y]);
+    let (pressed, down) = {
+        let input = ctx.input();
+        (
+            input.pointer.primary_pressed(),
+            input.pointer.primary_down(),
+        )
+    };
+    match (corners, pointer) {
+        (None, Some(pointer)) => {
+            if pressed && plot_ui.plot_hovered() && ctx.input().key_down(Key::B) {
+                ctx.data().insert_temp(id, [pointer, pointer]);
+            }
+        }
+        (Some([start, end]), pointer) if down => {
+            let end = pointer.unwrap_or(end);
+            ctx.data().insert_temp(id, [start, end]);
+            let outline = vec![start, [end[0], start[1]], end, [start[0], end[1]]];
+            plot_ui.polygon(
+                Polygon::new(PlotPoints::from(outline))
+                    .color(line_colour(ctx))
+                    .fill_alpha(0.1)
+                    .style(LineStyle::Dashed { length: 5. }),
+            );
+        }
+        (Some(corners), _) => {
+            ctx.data().remove::<[[f64; 2]; 2]>(id);
+            let modifiers = ctx.input().modifiers;
+            let mode = match (modifiers.shift, modifiers.command) {
+                (true, true) => SelectMode::Intersect,
+                (true, false) => SelectMode::Add,
+                (false, true) => SelectMode::Subtract,
+                (false, false) => SelectMode::Replace,
+            };
+            model.select_in_box(plot_view, corners, mode);
+        }
+        (None, None) => {}
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn plot(
     ctx: &Context,
     ui: &mut Ui,
     model: &mut Model,
     data_params: &mut DataParameters,
-    plot_group: &mut [LinkedAxisGroup; 2],
+    plot_group: &mut PlotGroup,
     plot_view: &mut PlotView,
     plot_range: [f64; 2],
+    snap: &Snap,
+    section: &SectionSlice,
+    sources: &SourceEstimation,
+    workspace: &Workspace,
 ) -> [f64; 2] {
     // The central panel the region left after adding TopPanel's and SidePanel's
     let data_points = match plot_view {
@@ -631,15 +1694,16 @@ fn plot(
         PlotView::XZ => ("data_xz", "model_xz"),
         PlotView::YZ => ("data_yz", "model_yz"),
     };
-    let group = match plot_view {
+    let (group, cursor_group) = match plot_view {
         PlotView::XY => todo!(),
-        PlotView::XZ => &plot_group[0],
-        PlotView::YZ => &plot_group[1],
+        PlotView::XZ => (&plot_group.axes[0], &plot_group.cursors[0]),
+        PlotView::YZ => (&plot_group.axes[1], &plot_group.cursors[1]),
     };
     // Associate colour with a cuboid. Also give cuboid a name id.
     let data_plot = Plot::new(data_plot_name)
         .view_aspect(2.0)
         .link_axis(group.clone())
+        .link_cursor(cursor_group.clone())
         .include_x(-5.)
         .include_x(5.)
         .include_y(plot_range[0])
@@ -651,12 +1715,14 @@ fn plot(
 
     let edit_mode = ctx.input().key_down(Key::M)
         || ctx.input().key_down(Key::L)
-        || ctx.input().key_down(Key::P);
+        || ctx.input().key_down(Key::P)
+        || ctx.input().key_down(Key::B);
 
     let model_plot = Plot::new(model_plot_name)
         .view_aspect(2.0)
         .data_aspect(1.0)
         .link_axis(group.clone())
+        .link_cursor(cursor_group.clone())
         .include_x(-5.)
         .include_x(5.)
         .include_y(2.)
@@ -682,35 +1748,113 @@ fn plot(
                             GravityObject::Sphere(sphere) => {
                                 sphere.calculate(&data_params.data_type, &data_points)
                             }
+                            GravityObject::Polyhedron(polyhedron) => {
+                                polyhedron.calculate(&data_params.data_type, &data_points)
+                            }
                         };
                         let data_2d: Vec<_> = pos
                             .into_iter()
                             .zip(data.iter())
                             .map(|(p, val)| [*p, *val])
                             .collect();
-                        let line = Line::new(data_2d);
-                        plot_ui.line(
-                            line.name(format!("{}: {}", obj.id, obj.name.to_string()))
-                                .color(obj.colour)
-                                .highlight(obj.is_selected),
-                        );
+                        if !obj.is_hidden {
+                            let line = Line::new(data_2d);
+                            plot_ui.line(
+                                line.name(format!("{}: {}", obj.id, obj.name.to_string()))
+                                    .color(obj.colour)
+                                    .highlight(obj.is_selected),
+                            );
+                        }
                         data_total = &data_total + &data;
                     }
                     None => {}
                 };
             }
+            let background = model
+                .background
+                .calculate(&data_params.data_type, &data_points);
+            if model.background.is_active() {
+                let data_2d: Vec<_> = pos
+                    .into_iter()
+                    .zip(background.iter())
+                    .map(|(p, val)| [*p, *val])
+                    .collect();
+                plot_ui.line(
+                    Line::new(data_2d)
+                        .name("Background")
+                        .color(Color32::GRAY)
+                        .style(LineStyle::dotted_loose()),
+                );
+                data_total = &data_total + &background;
+            }
+            if !data_params.data_type.is_linear() {
+                data_total = model.calculate(&data_params.data_type, &data_points);
+            }
             let data_2d: Vec<_> = pos
                 .into_iter()
                 .zip(data_total.iter())
                 .map(|(p, val)| [*p, *val])
                 .collect();
             let data_total_line = Line::new(data_2d);
+            let (name, colour) = workspace.combined_style(model, line_colour(ctx));
             plot_ui.line(
                 data_total_line
-                    .name("Combined")
-                    .color(line_colour(ctx))
+                    .name(name)
+                    .color(colour)
                     .style(LineStyle::dashed_loose()),
             );
+            workspace.show_overlays(
+                plot_ui,
+                model,
+                &data_params.data_type,
+                &data_points,
+                |values| {
+                    pos.iter()
+                        .zip(values.iter())
+                        .map(|(p, val)| [*p, *val])
+                        .collect()
+                },
+            );
+            let mut observed = data_total.clone();
+            if data_params.noise.enabled {
+                let stream = match plot_view {
+                    PlotView::XY => unreachable!("noise stream only for profile views"),
+                    PlotView::XZ => 0,
+                    PlotView::YZ => 1,
+                };
+                let noisy =
+                    data_params
+                        .noise
+                        .apply(model, &data_params.data_type, &data_points, stream);
+                let data_2d: Vec<_> = pos
+                    .into_iter()
+                    .zip(noisy.noisy.iter())
+                    .map(|(p, val)| [*p, *val])
+                    .collect();
+                plot_ui.points(
+                    Points::new(data_2d)
+                        .name(format!("Noisy (SNR {:.1})", noisy.snr))
+                        .color(line_colour(ctx))
+                        .radius(1.5),
+                );
+                observed = noisy.noisy;
+            }
+            if let Some(processed) =
+                data_params
+                    .processing
+                    .profile(&pos.to_vec(), &observed, &background)
+            {
+                let data_2d: Vec<_> = pos
+                    .into_iter()
+                    .zip(processed.iter())
+                    .map(|(p, val)| [*p, *val])
+                    .collect();
+                plot_ui.line(
+                    Line::new(data_2d)
+                        .name("Processed")
+                        .color(processing::COLOUR),
+                );
+            }
         });
 
         let plot_response = model_plot
@@ -732,9 +1876,54 @@ fn plot(
                         .color(line_colour(ctx)),
                 );
 
+                let [i, j] = plot_view.axes();
+                sources.show(plot_ui, [i, j]);
+                let plane = data_points[[0, 3 - i - j]];
                 for (id, object) in model.objects.iter() {
                     match object {
-                        Some(obj) => match obj.object.clone() {
+                        Some(obj) if !obj.is_hidden && section.enabled => {
+                            let name = format!("{}: {}", obj.id, obj.name);
+                            match section.section(&obj.object, plot_view, plane) {
+                                Section::Inside(footprint) => plot_ui.polygon(
+                                    Polygon::new(PlotPoints::new(footprint_points(footprint)))
+                                        .name(name)
+                                        .color(obj.colour)
+                                        .highlight(obj.is_selected),
+                                ),
+                                // The plot fills polygons as convex, so concave cuts are
+                                // drawn as outlines only.
+                                Section::Cut(loops) => {
+                                    for outline in loops {
+                                        let convex = is_convex(&outline);
+                                        let mut polygon = Polygon::new(PlotPoints::new(outline))
+                                            .name(&name)
+                                            .color(obj.colour)
+                                            .highlight(obj.is_selected);
+                                        if !convex {
+                                            polygon = polygon.fill_alpha(0.);
+                                        }
+                                        plot_ui.polygon(polygon);
+                                    }
+                                }
+                                Section::Outside(footprint, distance) => {
+                                    if !section.hide_outside {
+                                        plot_ui.polygon(
+                                            Polygon::new(PlotPoints::new(footprint_points(
+                                                footprint,
+                                            )))
+                                            .name(format!("{name} ({distance:.1} m off section)"))
+                                            .color(
+                                                obj.colour.linear_multiply(section.fade(distance)),
+                                            )
+                                            .style(LineStyle::Dashed { length: 5. })
+                                            .fill_alpha(0.)
+                                            .highlight(obj.is_selected),
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                        Some(obj) if !obj.is_hidden => match obj.object.clone() {
                             GravityObject::Cuboid(cuboid) => {
                                 match plot_view {
                                     PlotView::XY => todo!(),
@@ -865,8 +2054,17 @@ fn plot(
                                     }
                                 };
                             }
+                            GravityObject::Polyhedron(polyhedron) => {
+                                for edge in polyhedron.edge_lines(plot_view.axes()) {
+                                    plot_ui.line(
+                                        edge.name(format!("{}: {}", obj.id, obj.name.to_string()))
+                                            .color(obj.colour)
+                                            .highlight(obj.is_selected),
+                                    );
+                                }
+                            }
                         },
-                        None => {}
+                        _ => {}
                     }
                 }
 
@@ -880,6 +2078,7 @@ fn plot(
                 {
                     model.select_by_click(plot_ui, plot_view);
                 }
+                box_select(ctx, plot_ui, model, plot_view);
 
                 if plot_ui.plot_hovered()
                     && ctx.input().key_pressed(Key::C)
@@ -893,14 +2092,18 @@ fn plot(
                 }
 
                 if plot_ui.plot_hovered() && ctx.input().key_down(Key::M) {
-                    model.translate_selected(plot_ui, plot_view);
+                    model.translate_selected(plot_ui, plot_view, snap);
                 }
 
                 if plot_ui.plot_hovered()
                     && ctx.input().key_down(Key::L)
                     && model.number_objects_selected() == 1
                 {
-                    model.scale_selected(plot_ui, plot_view);
+                    model.scale_selected(plot_ui, plot_view, snap);
+                }
+
+                if plot_ui.plot_hovered() && ctx.input().key_down(Key::P) {
+                    model.rotate_selected(plot_ui, plot_view, snap);
                 }
             })
             .response;
@@ -908,6 +2111,623 @@ fn plot(
     [*data_total.min().unwrap(), *data_total.max().unwrap()]
 }
 
+/// Euler, half-width and analytic-signal estimates from gz along the x and y profiles and
+/// over the XY grid, with the noise and optionally the processing applied.
+fn estimation_ui(
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    sources: &mut SourceEstimation,
+) {
+    if sources.ui(ui) {
+        let processing = &data_params.processing;
+        let gz = |points: &Array2<f64>, stream: u64| {
+            if data_params.noise.enabled {
+                data_params
+                    .noise
+                    .apply(model, &DataType::Gz, points, stream)
+                    .noisy
+            } else {
+                model.calculate(&DataType::Gz, points)
+            }
+        };
+        let profiles: Vec<ProfileData<'_>> = [
+            ("x profile", data_params.points_xz(), 0),
+            ("y profile", data_params.points_yz(), 1),
+        ]
+        .into_iter()
+        .map(|(name, points, axis)| {
+            let mut data = gz(&points, axis as u64);
+            if sources.processed {
+                let positions = points.column(axis).to_vec();
+                let background = model.background.calculate(&DataType::Gz, &points);
+                data = processing
+                    .profile(&positions, &data, &background)
+                    .unwrap_or(data);
+            }
+            ProfileData {
+                name,
+                points,
+                axis,
+                gz: data,
+            }
+        })
+        .collect();
+        let points = data_params.points_xy();
+        let mut grid = GridData::from_points(
+            &points,
+            &gz(&points, 2),
+            data_params.grid_x_n,
+            data_params.grid_y_n,
+        );
+        if sources.processed {
+            let background = data_params.background_grid(model, &DataType::Gz, &points);
+            grid = processing.grid(&grid, &background).unwrap_or(grid);
+        }
+        sources.run(&profiles, Some((&points, &grid)));
+    }
+
+    ui.separator();
+    ui.label(format!("{} estimates", sources.estimates.len()));
+    egui::ScrollArea::vertical()
+        .max_height(200.)
+        .show(ui, |ui| {
+            egui::Grid::new("source_estimates")
+                .striped(true)
+                .show(ui, |ui| {
+                    for heading in ["Method", "Data", "x (m)", "y (m)", "z (m)"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for estimate in &sources.estimates {
+                        ui.label(estimate.method.label());
+                        ui.label(&estimate.data);
+                        for value in estimate.position {
+                            ui.label(format!("{value:.2}"));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+/// Jacobian of the data type along the x and y profiles and over the XY grid with respect
+/// to the parameters of one object, with the noise level as the data error.
+fn sensitivity_ui(
+    ctx: &egui::Context,
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    sensitivity: &mut Sensitivity,
+) {
+    if sensitivity.ui(ui, model) {
+        let surveys = [
+            Survey {
+                name: "x profile",
+                points: data_params.points_xz(),
+                layout: Layout::Profile(0),
+            },
+            Survey {
+                name: "y profile",
+                points: data_params.points_yz(),
+                layout: Layout::Profile(1),
+            },
+            Survey {
+                name: "XY grid",
+                points: data_params.points_xy(),
+                layout: Layout::Grid(data_params.grid_x_n, data_params.grid_y_n),
+            },
+        ];
+        // Effective σ of the noise model, pooled over the noisy data of the surveys.
+        let sigma = data_params.noise.enabled.then(|| {
+            let (mut sum_squares, mut n) = (0., 0);
+            for (stream, survey) in surveys.iter().enumerate() {
+                let noisy = data_params.noise.apply(
+                    model,
+                    &data_params.data_type,
+                    &survey.points,
+                    stream as u64,
+                );
+                sum_squares += noisy.sigma.powi(2) * noisy.noisy.len() as f64;
+                n += noisy.noisy.len();
+            }
+            (sum_squares / n.max(1) as f64).sqrt()
+        });
+        sensitivity.compute(model, &data_params.data_type, &surveys, sigma);
+    }
+    ui.separator();
+    sensitivity.show(ctx, ui, &data_params.data_type, 400.);
+}
+
+/// MCMC sampling of object parameters against the observed data along the x and y
+/// profiles, the noisy data when noise is enabled.
+fn uncertainty_ui(
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    uncertainty: &mut Uncertainty,
+) {
+    if uncertainty.ui(ui, model) {
+        let data_type = &data_params.data_type;
+        let profiles = [
+            ("x profile", data_params.points_xz(), 0),
+            ("y profile", data_params.points_yz(), 1),
+        ]
+        .into_iter()
+        .map(|(name, points, axis)| {
+            let observed = if data_params.noise.enabled {
+                data_params
+                    .noise
+                    .apply(model, data_type, &points, axis as u64)
+                    .noisy
+            } else {
+                model.calculate(data_type, &points)
+            };
+            ObservedProfile {
+                name,
+                points,
+                axis,
+                observed,
+            }
+        })
+        .collect();
+        uncertainty.start(model, data_type, profiles);
+    }
+    ui.separator();
+    uncertainty.show(ui, model, 400.);
+}
+
+/// Models in the workspace and their misfits against the observed data of the reference
+/// model along the x and y profiles and over the XY grid.
+fn workspace_ui(
+    ui: &mut Ui,
+    model: &mut Model,
+    data_params: &DataParameters,
+    workspace: &mut Workspace,
+) {
+    workspace.ui(ui, model);
+    ui.separator();
+    let data_type = &data_params.data_type;
+    let models = workspace.models(model);
+    let reference = models[workspace.reference.min(models.len() - 1)].1;
+    ui.label(format!(
+        "Misfit of {} against the {} data of {}",
+        data_type.label(),
+        if data_params.noise.enabled {
+            "noisy"
+        } else {
+            "noise-free"
+        },
+        reference.name
+    ));
+    let surveys = [
+        ("x profile", data_params.points_xz()),
+        ("y profile", data_params.points_yz()),
+        ("XY grid", data_params.points_xy()),
+    ];
+    egui::Grid::new("workspace_misfits")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Model");
+            for heading in ["Survey", "RMS", "Mean", "Max", "χ²/N"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for (stream, (name, points)) in surveys.iter().enumerate() {
+                // χ² against the effective σ of the noise added on this survey.
+                let (observed, sigma) = if data_params.noise.enabled {
+                    let noisy =
+                        data_params
+                            .noise
+                            .apply(reference, data_type, points, stream as u64);
+                    (noisy.noisy, Some(noisy.sigma))
+                } else {
+                    (reference.calculate(data_type, points), None)
+                };
+                for (entry, other) in &models {
+                    let misfit = Misfit::new(&observed, &other.calculate(data_type, points), sigma);
+                    ui.colored_label(entry.colour, &other.name);
+                    ui.label(*name);
+                    for value in [misfit.rms, misfit.mean, misfit.max] {
+                        ui.label(format!("{value:.3}"));
+                    }
+                    ui.label(
+                        misfit
+                            .chi2
+                            .map_or("–".to_string(), |chi2| format!("{chi2:.2}")),
+                    );
+                    ui.end_row();
+                }
+            }
+        });
+}
+
+/// Timeline playback and keys, and the difference anomaly between two epochs along the x
+/// and y profiles with the time series at their stations.
+fn timeline_ui(
+    ui: &mut Ui,
+    model: &mut Model,
+    data_params: &DataParameters,
+    dialog: &mut TimelineDialog,
+) {
+    dialog.ui(ui, model);
+    let epochs = model.timeline.epochs.clone();
+    if epochs.len() < 2 {
+        ui.label("Add two epochs to compare them.");
+        return;
+    }
+    ui.separator();
+    dialog.base = dialog.base.min(epochs.len() - 1);
+    dialog.monitor = dialog.monitor.min(epochs.len() - 1);
+    ui.horizontal(|ui| {
+        for (label, index) in [("Base", &mut dialog.base), ("Monitor", &mut dialog.monitor)] {
+            egui::ComboBox::from_label(label)
+                .selected_text(&epochs[*index].name)
+                .show_ui(ui, |ui| {
+                    for (k, epoch) in epochs.iter().enumerate() {
+                        ui.selectable_value(index, k, &epoch.name);
+                    }
+                });
+        }
+    });
+
+    let data_type = &data_params.data_type;
+    let base = model.at_time(epochs[dialog.base].time);
+    let monitor = model.at_time(epochs[dialog.monitor].time);
+    let profiles = [
+        ("x", data_params.points_xz(), 0),
+        ("y", data_params.points_yz(), 1),
+    ];
+    Plot::new("timelapse_difference")
+        .view_aspect(2.0)
+        .width(PLOT_WIDTH)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            for (label, points, axis) in &profiles {
+                let difference =
+                    monitor.calculate(data_type, points) - base.calculate(data_type, points);
+                let line: Vec<[f64; 2]> = points
+                    .column(*axis)
+                    .iter()
+                    .zip(difference.iter())
+                    .map(|(p, d)| [*p, *d])
+                    .collect();
+                plot_ui.line(Line::new(line).name(format!("along {label}")));
+            }
+        });
+    ui.label(format!(
+        "{}: {} − {} ({})",
+        data_type.label(),
+        epochs[dialog.monitor].name,
+        epochs[dialog.base].name,
+        data_type.units()
+    ));
+
+    let metadata = data_params.export_metadata(model);
+    let mut result = None;
+    ui.horizontal(|ui| {
+        ui.label("Time series (CSV): ");
+        for (label, points, _) in &profiles {
+            if ui.button(*label).clicked() {
+                let mut columns: Vec<(String, Array1<f64>)> = epochs
+                    .iter()
+                    .map(|epoch| {
+                        (
+                            format!("{} ({} yr)", epoch.name, epoch.time),
+                            model.at_time(epoch.time).calculate(data_type, points),
+                        )
+                    })
+                    .collect();
+                columns.push((
+                    "difference".to_string(),
+                    &columns[dialog.monitor].1 - &columns[dialog.base].1,
+                ));
+                let csv = profile_csv(points, &columns, &metadata);
+                let file_name = format!("{}_{label}_timeseries.csv", metadata.file_stem());
+                result = Some(save_text(&file_name, &csv));
+            }
+        }
+    });
+    let id = egui::Id::new("timeline_export_status");
+    match result {
+        Some(Ok(true)) => {
+            ui.data().insert_temp(id, "Saved".to_string());
+        }
+        Some(Ok(false)) => {
+            ui.data().remove::<String>(id);
+        }
+        Some(Err(err)) => {
+            ui.data().insert_temp(id, format!("Export failed: {err}"));
+        }
+        None => {}
+    }
+    if let Some(status) = ui.data().get_temp::<String>(id) {
+        ui.label(status);
+    }
+}
+
+fn detectability_ui(
+    ui: &mut Ui,
+    detectability: &mut DetectabilityAnalysis,
+    data_params: &DataParameters,
+) {
+    detectability.ui(ui);
+    ui.horizontal(|ui| {
+        if ui.button("From survey").clicked() {
+            detectability.noise_sigma = data_params.noise.white_sigma;
+            detectability.profile_length = data_params.x_end - data_params.x_start;
+            detectability.station_spacing =
+                detectability.profile_length / (data_params.x_n.max(2) - 1) as f64;
+        }
+        if ui.button("Run").clicked() {
+            detectability.run(&data_params.data_type, data_params.z);
+        }
+    });
+    let result = match &detectability.result {
+        Some(result) => result,
+        None => return,
+    };
+
+    ui.label(format!(
+        "log10(peak {} / noise), threshold {} sigma",
+        data_params.data_type.label(),
+        detectability.threshold
+    ));
+    let mut points: Array2<f64> = Array2::zeros((result.ratio.len(), 2));
+    let mut values: Array1<f64> = Array1::zeros(result.ratio.len());
+    let mut idx = 0;
+    for (i, depth) in result.depths.iter().enumerate() {
+        for (j, size) in result.sizes.iter().enumerate() {
+            points[[idx, 0]] = *size;
+            points[[idx, 1]] = -depth;
+            values[idx] = result.ratio[[i, j]].max(f64::MIN_POSITIVE).log10();
+            idx += 1;
+        }
+    }
+    let min = *values.min().unwrap_or(&0.);
+    let max = *values.max().unwrap_or(&0.);
+    let min_size_line: Vec<[f64; 2]> = result
+        .depths
+        .iter()
+        .zip(result.min_size.iter())
+        .filter_map(|(depth, size)| size.map(|size| [size, -depth]))
+        .collect();
+    Plot::new("detectability_map")
+        .view_aspect(1.5)
+        .width(PLOT_WIDTH / 1.5)
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_map_points(plot_ui, &points, &values, min, max);
+            plot_ui.line(
+                Line::new(min_size_line.clone())
+                    .name("Minimum detectable size")
+                    .color(Color32::RED)
+                    .width(2.),
+            );
+        });
+    ui.label("Minimum detectable size (m) against depth to top (m)");
+    Plot::new("detectability_min_size")
+        .view_aspect(3.)
+        .width(PLOT_WIDTH / 1.5)
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(
+                    min_size_line
+                        .iter()
+                        .map(|[size, depth]| [-depth, *size])
+                        .collect::<Vec<_>>(),
+                )
+                .name("Minimum detectable size"),
+            );
+        });
+}
+
+/// Finite-baseline gradiometer reading against the point derivative along the sensor
+/// axis, and their difference (the baseline error).
+fn plot_sensor(
+    ctx: &Context,
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    sensor: &GradiometerSensor,
+    plot_group: &PlotGroup,
+    plot_view: &PlotView,
+) {
+    let (data_points, idx, group, cursor_group) = match plot_view {
+        PlotView::XY => unreachable!("sensor profiles only for the XZ and YZ views"),
+        PlotView::XZ => (
+            data_params.points_xz(),
+            0,
+            &plot_group.axes[0],
+            &plot_group.cursors[0],
+        ),
+        PlotView::YZ => (
+            data_params.points_yz(),
+            1,
+            &plot_group.axes[1],
+            &plot_group.cursors[1],
+        ),
+    };
+    let pos = data_points.index_axis(Axis(1), idx);
+    let measured = sensor.calculate(model, &data_points);
+    let point = sensor.point_gradient(model, &data_points);
+    let error = &measured - &point;
+    let to_line = |data: &Array1<f64>| -> Vec<[f64; 2]> {
+        pos.iter()
+            .zip(data.iter())
+            .map(|(p, val)| [*p, *val])
+            .collect()
+    };
+
+    ui.label("Gradient along sensor axis (E)");
+    Plot::new(format!("sensor_{idx}"))
+        .view_aspect(2.0)
+        .width(PLOT_WIDTH)
+        .link_axis(group.clone())
+        .link_cursor(cursor_group.clone())
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(to_line(&measured))
+                    .name(format!("Sensor, {} m baseline", sensor.baseline))
+                    .color(line_colour(ctx)),
+            );
+            plot_ui.line(
+                Line::new(to_line(&point))
+                    .name("Point derivative")
+                    .style(LineStyle::dashed_loose()),
+            );
+        });
+    ui.label("Baseline error (E)");
+    Plot::new(format!("sensor_error_{idx}"))
+        .view_aspect(4.0)
+        .width(PLOT_WIDTH)
+        .link_axis(group.clone())
+        .link_cursor(cursor_group.clone())
+        .legend(Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(to_line(&error)).name("Sensor - point derivative"));
+        });
+}
+
+/// Stacked profiles of each compared component, sharing the x axis and cursor of the
+/// matching data and model plots.
+fn plot_component_profiles(
+    ctx: &Context,
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    plot_group: &PlotGroup,
+    plot_view: &PlotView,
+) {
+    let (data_points, idx, group, cursor_group) = match plot_view {
+        PlotView::XY => unreachable!("component profiles only for the XZ and YZ views"),
+        PlotView::XZ => (
+            data_params.points_xz(),
+            0,
+            &plot_group.axes[0],
+            &plot_group.cursors[0],
+        ),
+        PlotView::YZ => (
+            data_params.points_yz(),
+            1,
+            &plot_group.axes[1],
+            &plot_group.cursors[1],
+        ),
+    };
+    let pos = data_points.index_axis(Axis(1), idx);
+    let height = (PLOT_WIDTH / 4.).max(120.);
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for data_type in data_params.data_types.iter() {
+            ui.label(format!("{} ({})", data_type.label(), data_type.units()));
+            Plot::new(format!("component_{idx}_{data_type:?}"))
+                .height(height)
+                .width(PLOT_WIDTH)
+                .link_axis(group.clone())
+                .link_cursor(cursor_group.clone())
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    if data_type.is_linear() {
+                        for obj in model.objects.values().flatten() {
+                            let data = obj.object.as_calc().calculate(data_type, &data_points);
+                            let data_2d: Vec<_> = pos
+                                .iter()
+                                .zip(data.iter())
+                                .map(|(p, val)| [*p, *val])
+                                .collect();
+                            plot_ui.line(
+                                Line::new(data_2d)
+                                    .name(format!("{}: {}", obj.id, obj.name))
+                                    .color(obj.colour)
+                                    .highlight(obj.is_selected),
+                            );
+                        }
+                    }
+                    let data_total = model.calculate(data_type, &data_points);
+                    let data_2d: Vec<_> = pos
+                        .iter()
+                        .zip(data_total.iter())
+                        .map(|(p, val)| [*p, *val])
+                        .collect();
+                    plot_ui.line(
+                        Line::new(data_2d)
+                            .name("Combined")
+                            .color(line_colour(ctx))
+                            .style(LineStyle::dashed_loose()),
+                    );
+                });
+        }
+    });
+}
+
+/// The full gradient tensor as a 3x3 matrix of XY maps sharing axes and cursor with
+/// the XY view.
+fn plot_tensor_grid(
+    ui: &mut Ui,
+    model: &Model,
+    data_params: &DataParameters,
+    plot_group: &PlotGroup,
+) {
+    let data_points = data_params.points_xy();
+    let field = model.field(&data_points);
+    let tensor = [
+        [DataType::Gxx, DataType::Gxy, DataType::Gxz],
+        [DataType::Gxy, DataType::Gyy, DataType::Gyz],
+        [DataType::Gxz, DataType::Gyz, DataType::Gzz],
+    ];
+    let size = PLOT_WIDTH / 3.;
+    egui::Grid::new("tensor_grid").show(ui, |ui| {
+        for (i, row) in tensor.iter().enumerate() {
+            for (j, data_type) in row.iter().enumerate() {
+                ui.vertical(|ui| {
+                    ui.label(format!("{} ({})", data_type.label(), data_type.units()));
+                    let data = field.data(data_type);
+                    let min = *data.min().unwrap_or(&0.);
+                    let max = *data.max().unwrap_or(&0.);
+                    Plot::new(format!("tensor_{i}_{j}"))
+                        .view_aspect(1.0)
+                        .width(size)
+                        .height(size)
+                        .link_axis(plot_group.axes_xy.clone())
+                        .link_cursor(plot_group.cursors_xy.clone())
+                        .show(ui, |plot_ui| {
+                            plot_map_points(plot_ui, &data_points, &data, min, max);
+                        });
+                });
+            }
+            ui.end_row();
+        }
+    });
+}
+
+/// Draw gridded data as points coloured by value.
+fn plot_map_points(
+    plot_ui: &mut PlotUi,
+    data_points: &Array2<f64>,
+    data: &Array1<f64>,
+    min: f64,
+    max: f64,
+) {
+    let gradient = colorous::VIRIDIS;
+    for (x, y, val) in izip!(
+        data_points.index_axis(Axis(1), 0),
+        data_points.index_axis(Axis(1), 1),
+        data.iter(),
+    ) {
+        let val_norm = normalize_range(*val, min, max);
+        let colour = gradient.eval_continuous(val_norm);
+
+        plot_ui.points(
+            Points::new([*x, *y])
+                .radius(5.)
+                .name(format!("{val:.2}"))
+                .color(Color32::from_rgba_premultiplied(
+                    colour.r, colour.g, colour.b, 100,
+                )),
+        );
+    }
+}
+
 pub fn normalize_range(value: f64, min: f64, max: f64) -> f64 {
     (value - min) / (max - min)
 }
@@ -0,0 +1,337 @@
+use crate::gravity_objects::{Cuboid, DataType, Field, GravityCalc, G, GG_SCALING, G_SCALING};
+use egui::Ui;
+use ndarray::{Array1, Array2, Axis};
+use std::f64::consts::PI;
+
+/// Layer of the half-space, modelled as a large prism so its edges are included, or as
+/// an infinite horizontal Bouguer slab.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub enabled: bool,
+    /// Elevation of the top of the layer at `centre`, in metres.
+    pub top: f64,
+    /// True thickness, perpendicular to the bedding.
+    pub thickness: f64,
+    /// Density contrast in kg/m^3.
+    pub density: f64,
+    /// Dip in degrees, ignored for infinite slabs.
+    pub dip: f64,
+    /// Direction the layer dips towards, in degrees anticlockwise from the x axis.
+    pub dip_direction: f64,
+    /// Side length of the prism in metres.
+    pub extent: f64,
+    pub centre: [f64; 2],
+    /// Model as an infinite slab rather than a prism.
+    pub infinite: bool,
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            name: "Layer".to_string(),
+            enabled: true,
+            top: 0.,
+            thickness: 10.,
+            density: 100.,
+            dip: 0.,
+            dip_direction: 0.,
+            extent: 5000.,
+            centre: [0., 0.],
+            infinite: false,
+        }
+    }
+}
+
+impl Layer {
+    /// Prism of the layer, tilted down by the dip towards the dip direction.
+    pub fn prism(&self) -> Cuboid {
+        let (dip, direction) = (self.dip.to_radians(), self.dip_direction.to_radians());
+        // Normal to the bedding, half a thickness below the top surface is the centre.
+        let normal = [
+            dip.sin() * direction.cos(),
+            dip.sin() * direction.sin(),
+            dip.cos(),
+        ];
+        let offset = self.thickness / 2.;
+        Cuboid {
+            x_length: self.extent,
+            y_length: self.extent,
+            z_length: self.thickness,
+            x_centroid: self.centre[0] - offset * normal[0],
+            y_centroid: self.centre[1] - offset * normal[1],
+            z_centroid: self.top - offset * normal[2],
+            x_rotation: 0.,
+            y_rotation: dip,
+            z_rotation: direction,
+            density: self.density,
+        }
+    }
+
+    fn field(&self, points: &Array2<f64>) -> Field {
+        if !self.infinite {
+            return self.prism().field(points);
+        }
+        // Slab attraction depends only on the thickness above and below each point.
+        let mut field = Field::zeros(points.len_of(Axis(0)));
+        let bottom = self.top - self.thickness;
+        for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+            let below = (point[2] - bottom).clamp(0., self.thickness);
+            let above = self.thickness - below;
+            let gz = -2. * PI * G * self.density * (below - above);
+            field.g[[i, 2]] = gz * G_SCALING;
+            if above > 0. && below > 0. {
+                field.gg[[i, 2, 2]] = -4. * PI * G * self.density * GG_SCALING;
+            }
+        }
+        field
+    }
+}
+
+/// Regional trend of gz as a polynomial in x and y about `origin`. Its horizontal
+/// derivatives go into Gxz and Gyz, other components are unchanged.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Regional {
+    pub enabled: bool,
+    /// 0 is a constant, 1 a plane, up to 3.
+    pub order: usize,
+    /// Coefficients in µGal/m^n of the terms x^p y^q with p + q = n, by increasing n then
+    /// q, i.e. 1, x, y, x², xy, y²...
+    pub coefficients: Vec<f64>,
+    pub origin: [f64; 2],
+}
+
+impl Default for Regional {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            order: 1,
+            coefficients: vec![0.; 3],
+            origin: [0., 0.],
+        }
+    }
+}
+
+impl Regional {
+    pub const MAX_ORDER: usize = 3;
+
+    /// Powers of x and y of each term up to `order`.
+    pub fn terms(order: usize) -> Vec<(i32, i32)> {
+        (0..=order as i32)
+            .flat_map(|n| (0..=n).map(move |q| (n - q, q)))
+            .collect()
+    }
+
+    fn term_label((p, q): (i32, i32)) -> String {
+        let power = |name: &str, n: i32| match n {
+            0 => String::new(),
+            1 => name.to_string(),
+            2 => format!("{name}²"),
+            _ => format!("{name}³"),
+        };
+        match (p, q) {
+            (0, 0) => "1".to_string(),
+            _ => power("x", p) + &power("y", q),
+        }
+    }
+
+    /// Value and x and y derivatives of the trend at a point.
+    pub fn evaluate(&self, x: f64, y: f64) -> [f64; 3] {
+        let (x, y) = (x - self.origin[0], y - self.origin[1]);
+        let mut value = [0.; 3];
+        for ((p, q), c) in Self::terms(self.order).into_iter().zip(&self.coefficients) {
+            value[0] += c * x.powi(p) * y.powi(q);
+            if p > 0 {
+                value[1] += c * p as f64 * x.powi(p - 1) * y.powi(q);
+            }
+            if q > 0 {
+                value[2] += c * q as f64 * x.powi(p) * y.powi(q - 1);
+            }
+        }
+        value
+    }
+
+    fn field(&self, points: &Array2<f64>) -> Field {
+        let mut field = Field::zeros(points.len_of(Axis(0)));
+        // Tensor components are derivatives of the SI gravity vector, in Eötvös.
+        let scale = GG_SCALING / G_SCALING;
+        for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+            let [gz, dx, dy] = self.evaluate(point[0], point[1]);
+            field.g[[i, 2]] = gz;
+            for (j, d) in [(0, dx), (1, dy)] {
+                field.gg[[i, j, 2]] = d * scale;
+                field.gg[[i, 2, j]] = d * scale;
+            }
+        }
+        field
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.enabled, "Regional trend");
+            egui::ComboBox::from_id_source("regional_order")
+                .selected_text(Self::order_label(self.order))
+                .show_ui(ui, |ui| {
+                    for order in 0..=Self::MAX_ORDER {
+                        ui.selectable_value(&mut self.order, order, Self::order_label(order));
+                    }
+                });
+        });
+        let terms = Self::terms(self.order);
+        self.coefficients.resize(terms.len(), 0.);
+        ui.add_enabled_ui(self.enabled, |ui| {
+            egui::Grid::new("regional_coefficients").show(ui, |ui| {
+                for (term, c) in terms.into_iter().zip(self.coefficients.iter_mut()) {
+                    let n = term.0 + term.1;
+                    let units = match n {
+                        0 => " µGal".to_string(),
+                        1 => " µGal/m".to_string(),
+                        _ => format!(" µGal/m^{n}"),
+                    };
+                    ui.label(Self::term_label(term));
+                    ui.add(
+                        egui::DragValue::new(c)
+                            .speed(10f64.powi(-2 * n))
+                            .suffix(units),
+                    );
+                    ui.end_row();
+                }
+                ui.label("origin");
+                ui.horizontal(|ui| {
+                    for value in self.origin.iter_mut() {
+                        ui.add(egui::DragValue::new(value).speed(0.1).suffix(" m"));
+                    }
+                });
+                ui.end_row();
+            });
+        });
+    }
+
+    fn order_label(order: usize) -> &'static str {
+        match order {
+            0 => "Constant",
+            1 => "Planar",
+            2 => "Quadratic",
+            _ => "Cubic",
+        }
+    }
+}
+
+/// Layered half-space and regional field added to the response of the model's objects.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Background {
+    pub layers: Vec<Layer>,
+    pub regional: Regional,
+}
+
+impl Background {
+    pub fn is_active(&self) -> bool {
+        self.regional.enabled || self.layers.iter().any(|layer| layer.enabled)
+    }
+
+    pub fn field(&self, points: &Array2<f64>) -> Field {
+        let mut field = Field::zeros(points.len_of(Axis(0)));
+        for layer in self.layers.iter().filter(|layer| layer.enabled) {
+            field += layer.field(points);
+        }
+        if self.regional.enabled {
+            field += self.regional.field(points);
+        }
+        field
+    }
+
+    /// Response of the background alone, e.g. to remove it from observed data.
+    pub fn calculate(&self, data_type: &DataType, points: &Array2<f64>) -> Array1<f64> {
+        if self.is_active() {
+            self.field(points).data(data_type)
+        } else {
+            Array1::zeros(points.len_of(Axis(0)))
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        let mut remove = None;
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut layer.enabled, "");
+                    ui.add(egui::TextEdit::singleline(&mut layer.name).desired_width(80.));
+                    ui.checkbox(&mut layer.infinite, "Infinite slab")
+                        .on_hover_text("Bouguer slab without edges, horizontal only");
+                    if ui.button("🗑").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                egui::Grid::new("layer").show(ui, |ui| {
+                    ui.label("top");
+                    ui.add(egui::DragValue::new(&mut layer.top).speed(0.1).suffix(" m"));
+                    ui.label("thickness");
+                    ui.add(
+                        egui::DragValue::new(&mut layer.thickness)
+                            .speed(0.1)
+                            .clamp_range(0.0..=f64::INFINITY)
+                            .suffix(" m"),
+                    );
+                    ui.end_row();
+                    ui.label("density");
+                    ui.add(egui::DragValue::new(&mut layer.density).suffix(" kg/m^3"));
+                    ui.end_row();
+                    ui.add_enabled_ui(!layer.infinite, |ui| {
+                        ui.label("dip");
+                    });
+                    ui.add_enabled(
+                        !layer.infinite,
+                        egui::DragValue::new(&mut layer.dip)
+                            .clamp_range(-90.0..=90.0)
+                            .suffix("°"),
+                    );
+                    ui.add_enabled_ui(!layer.infinite, |ui| {
+                        ui.label("towards");
+                    });
+                    ui.add_enabled(
+                        !layer.infinite,
+                        egui::DragValue::new(&mut layer.dip_direction).suffix("°"),
+                    );
+                    ui.end_row();
+                    ui.add_enabled_ui(!layer.infinite, |ui| {
+                        ui.label("extent");
+                    });
+                    ui.add_enabled(
+                        !layer.infinite,
+                        egui::DragValue::new(&mut layer.extent)
+                            .speed(10.)
+                            .clamp_range(1.0..=f64::INFINITY)
+                            .suffix(" m"),
+                    );
+                    ui.label("centre");
+                    ui.horizontal(|ui| {
+                        for value in layer.centre.iter_mut() {
+                            ui.add(egui::DragValue::new(value).speed(0.1));
+                        }
+                    });
+                    ui.end_row();
+                });
+            });
+            ui.separator();
+        }
+        if let Some(i) = remove {
+            self.layers.remove(i);
+        }
+        if ui.button("Add layer").clicked() {
+            // New layers go under the deepest one.
+            let top = self
+                .layers
+                .iter()
+                .map(|layer| layer.top - layer.thickness)
+                .fold(0., f64::min);
+            self.layers.push(Layer {
+                name: format!("Layer {}", self.layers.len() + 1),
+                top,
+                ..Layer::default()
+            });
+        }
+        ui.separator();
+        self.regional.ui(ui);
+    }
+}
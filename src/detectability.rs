@@ -0,0 +1,172 @@
+use crate::gravity_objects::{Cuboid, DataType, GravityCalc, Sphere};
+use egui::Ui;
+use ndarray::{Array1, Array2};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TargetShape {
+    Sphere,
+    Cube,
+}
+
+/// Peak anomaly of a target swept over depth and size, sampled at the survey station
+/// spacing and compared against the survey noise.
+#[derive(Debug, Clone)]
+pub struct DetectabilityResult {
+    /// Depth to the top of the target in metres.
+    pub depths: Array1<f64>,
+    /// Diameter (sphere) or edge length (cube) in metres.
+    pub sizes: Array1<f64>,
+    /// Sampled peak absolute anomaly over the noise sigma, indexed [depth, size].
+    pub ratio: Array2<f64>,
+    /// Smallest detectable size at each depth, if any size in the sweep is detectable.
+    pub min_size: Vec<Option<f64>>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct DetectabilityAnalysis {
+    pub open: bool,
+    pub shape: TargetShape,
+    /// Density contrast of the target in kg/m^3.
+    pub density: f64,
+    pub depth_min: f64,
+    pub depth_max: f64,
+    pub depth_n: usize,
+    pub size_min: f64,
+    pub size_max: f64,
+    pub size_n: usize,
+    /// Standard deviation of the survey noise, in the units of the component.
+    pub noise_sigma: f64,
+    /// Detection threshold as a multiple of the noise sigma.
+    pub threshold: f64,
+    /// Number of stations that must exceed the threshold.
+    pub min_stations: usize,
+    pub station_spacing: f64,
+    pub profile_length: f64,
+    #[serde(skip)]
+    pub result: Option<DetectabilityResult>,
+}
+
+impl Default for DetectabilityAnalysis {
+    fn default() -> Self {
+        Self {
+            open: false,
+            shape: TargetShape::Sphere,
+            density: -1800.,
+            depth_min: 0.5,
+            depth_max: 10.,
+            depth_n: 20,
+            size_min: 0.25,
+            size_max: 5.,
+            size_n: 20,
+            noise_sigma: 1.,
+            threshold: 3.,
+            min_stations: 2,
+            station_spacing: 0.5,
+            profile_length: 40.,
+            result: None,
+        }
+    }
+}
+
+impl DetectabilityAnalysis {
+    /// Profile stations at `sensor_height` running through the target. The target sits
+    /// midway between two stations, the worst case for a given spacing.
+    pub fn stations(&self, sensor_height: f64) -> Array2<f64> {
+        let spacing = self.station_spacing.max(1e-3);
+        let n_half = (self.profile_length / 2. / spacing).ceil() as usize;
+        let mut points: Array2<f64> = Array2::zeros((2 * n_half, 3));
+        for i in 0..2 * n_half {
+            points[[i, 0]] = (i as f64 - n_half as f64 + 0.5) * spacing;
+            points[[i, 2]] = sensor_height;
+        }
+        points
+    }
+
+    pub fn target(&self, depth: f64, size: f64) -> Box<dyn GravityCalc> {
+        let z_centroid = -(depth + size / 2.);
+        match self.shape {
+            TargetShape::Sphere => Box::new(Sphere {
+                z_centroid,
+                radius: size / 2.,
+                density: self.density,
+                ..Default::default()
+            }),
+            TargetShape::Cube => Box::new(Cuboid {
+                x_length: size,
+                y_length: size,
+                z_length: size,
+                z_centroid,
+                density: self.density,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn run(&mut self, data_type: &DataType, sensor_height: f64) {
+        let depths: Array1<f64> =
+            ndarray::Array::linspace(self.depth_min, self.depth_max, self.depth_n.max(1));
+        // Ascending, so the first detected size along a row is the smallest.
+        let sizes: Array1<f64> = ndarray::Array::linspace(
+            self.size_min.min(self.size_max),
+            self.size_min.max(self.size_max),
+            self.size_n.max(1),
+        );
+        let stations = self.stations(sensor_height);
+        let sigma = self.noise_sigma.max(f64::EPSILON);
+        let mut ratio: Array2<f64> = Array2::zeros((depths.len(), sizes.len()));
+        let mut min_size = vec![None; depths.len()];
+        for (i, depth) in depths.iter().enumerate() {
+            for (j, size) in sizes.iter().enumerate() {
+                let data = self.target(*depth, *size).calculate(data_type, &stations);
+                let peak = data.iter().fold(0f64, |peak, v| peak.max(v.abs()));
+                ratio[[i, j]] = peak / sigma;
+                let n_detected = data
+                    .iter()
+                    .filter(|v| v.abs() > self.threshold * sigma)
+                    .count();
+                if n_detected >= self.min_stations && min_size[i].is_none() {
+                    min_size[i] = Some(*size);
+                }
+            }
+        }
+        self.result = Some(DetectabilityResult {
+            depths,
+            sizes,
+            ratio,
+            min_size,
+        });
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Target");
+            ui.radio_value(&mut self.shape, TargetShape::Sphere, "Sphere");
+            ui.radio_value(&mut self.shape, TargetShape::Cube, "Cube");
+        });
+        ui.label("density contrast");
+        ui.add(egui::Slider::new(&mut self.density, -3000.0..=22590.).text("kg/m^3"));
+        ui.collapsing("depth to top", |ui| {
+            ui.add(egui::Slider::new(&mut self.depth_min, 0.0..=50.).text("min m"));
+            ui.add(egui::Slider::new(&mut self.depth_max, 0.0..=50.).text("max m"));
+            ui.add(egui::Slider::new(&mut self.depth_n, 1..=50).text("steps"));
+        });
+        ui.collapsing("size", |ui| {
+            ui.add(egui::Slider::new(&mut self.size_min, 0.05..=50.).text("min m"));
+            ui.add(egui::Slider::new(&mut self.size_max, self.size_min..=50.).text("max m"));
+            ui.add(egui::Slider::new(&mut self.size_n, 1..=50).text("steps"));
+        });
+        ui.collapsing("survey", |ui| {
+            ui.label("noise sigma");
+            ui.add(egui::Slider::new(&mut self.noise_sigma, 0.001..=100.).logarithmic(true));
+            ui.label("threshold");
+            ui.add(egui::Slider::new(&mut self.threshold, 1.0..=10.).text("sigma"));
+            ui.label("stations above threshold");
+            ui.add(egui::Slider::new(&mut self.min_stations, 1..=10));
+            ui.label("station spacing");
+            ui.add(egui::Slider::new(&mut self.station_spacing, 0.05..=10.).text("m"));
+            ui.label("profile length");
+            ui.add(egui::Slider::new(&mut self.profile_length, 1.0..=200.).text("m"));
+        });
+    }
+}
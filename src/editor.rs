@@ -0,0 +1,174 @@
+use crate::gravity_objects::{GravityModelObject, Parameter, ParameterRanges};
+use crate::model::Model;
+use egui::Ui;
+
+/// Editor for the selected objects. Parameters shared by every selected object show
+/// their value and are set absolutely; differing parameters show as mixed and edits are
+/// applied as an offset to each object. Locked objects are left unchanged.
+pub fn selection_ui(ui: &mut Ui, model: &mut Model) {
+    let ids: Vec<String> = model
+        .ordered_ids()
+        .into_iter()
+        .filter(|id| match model.objects.get(id) {
+            Some(Some(obj)) => obj.is_selected && !obj.is_locked,
+            _ => false,
+        })
+        .collect();
+    let n_locked = model.number_objects_selected() as usize - ids.len();
+    if n_locked > 0 {
+        ui.weak(format!("{n_locked} locked object(s) not edited"));
+    }
+    if ids.is_empty() {
+        return;
+    }
+
+    if let [id] = ids.as_slice() {
+        if let Some(Some(obj)) = model.objects.get_mut(id) {
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                ui.text_edit_singleline(&mut obj.name);
+            });
+        }
+    } else {
+        ui.label(format!("{} objects", ids.len()));
+    }
+    let first_colour = model.objects[&ids[0]].as_ref().map(|obj| obj.colour);
+    if let Some(mut colour) = first_colour {
+        ui.horizontal(|ui| {
+            ui.label("Colour: ");
+            if ui.color_edit_button_srgba(&mut colour).changed() {
+                for obj in objects_mut(model, &ids) {
+                    obj.colour = colour;
+                }
+            }
+        });
+    }
+
+    let ranges = model.ranges.clone();
+    egui::CollapsingHeader::new("Centroid")
+        .default_open(true)
+        .show(ui, |ui| {
+            for parameter in [
+                Parameter::XCentroid,
+                Parameter::YCentroid,
+                Parameter::ZCentroid,
+            ] {
+                parameter_row(ui, model, &ids, parameter, &ranges);
+            }
+        });
+    egui::CollapsingHeader::new("Rotation").show(ui, |ui| {
+        for parameter in [
+            Parameter::XRotation,
+            Parameter::YRotation,
+            Parameter::ZRotation,
+        ] {
+            parameter_row(ui, model, &ids, parameter, &ranges);
+        }
+    });
+    egui::CollapsingHeader::new("Size")
+        .default_open(true)
+        .show(ui, |ui| {
+            for parameter in [
+                Parameter::XLength,
+                Parameter::YLength,
+                Parameter::ZLength,
+                Parameter::Radius,
+            ] {
+                parameter_row(ui, model, &ids, parameter, &ranges);
+            }
+        });
+    egui::CollapsingHeader::new("Density").show(ui, |ui| {
+        parameter_row(ui, model, &ids, Parameter::Density, &ranges);
+        ui.horizontal(|ui| {
+            for (label, density) in [
+                ("Soil Void", -1800.),
+                ("Concrete", 2000.),
+                ("Lead", 11340.),
+                ("Tungsten", 19300.),
+            ] {
+                if ui.button(label).clicked() {
+                    for obj in objects_mut(model, &ids) {
+                        Parameter::Density.set(&mut obj.object, density);
+                    }
+                }
+            }
+        });
+    });
+}
+
+/// Numeric field for one parameter across the objects that have it. Click to type a
+/// value, drag to change it.
+fn parameter_row(
+    ui: &mut Ui,
+    model: &mut Model,
+    ids: &[String],
+    parameter: Parameter,
+    ranges: &ParameterRanges,
+) {
+    let values: Vec<f64> = ids
+        .iter()
+        .filter_map(|id| model.objects.get(id))
+        .flatten()
+        .filter_map(|obj| parameter.get(&obj.object))
+        .collect();
+    if values.is_empty() {
+        return;
+    }
+    let [min, max] = ranges.range(&parameter);
+    let speed = (max - min) / 1000.;
+    let suffix = format!(" {}", parameter.units());
+    let mixed = values.iter().any(|v| (v - values[0]).abs() > 1e-9);
+
+    ui.horizontal(|ui| {
+        ui.label(parameter.label());
+        let objects = objects_mut(model, ids);
+        if mixed {
+            let mut offset = 0.;
+            let response = ui
+                .add(
+                    egui::DragValue::new(&mut offset)
+                        .speed(speed)
+                        .prefix("mixed, Δ ")
+                        .suffix(suffix),
+                )
+                .on_hover_text(format!(
+                    "{:.3} to {:.3}, edits are added to each object",
+                    values.iter().copied().fold(f64::INFINITY, f64::min),
+                    values.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+                ));
+            if response.changed() {
+                for obj in objects {
+                    if let Some(value) = parameter.get(&obj.object) {
+                        parameter.set(&mut obj.object, (value + offset).clamp(min, max));
+                    }
+                }
+            }
+        } else {
+            let mut value = values[0];
+            let response = ui.add(
+                egui::DragValue::new(&mut value)
+                    .speed(speed)
+                    .clamp_range(min..=max)
+                    .suffix(suffix),
+            );
+            if response.changed() {
+                for obj in objects {
+                    if parameter.get(&obj.object).is_some() {
+                        parameter.set(&mut obj.object, value);
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn objects_mut<'a>(
+    model: &'a mut Model,
+    ids: &'a [String],
+) -> impl Iterator<Item = &'a mut GravityModelObject> {
+    model
+        .objects
+        .iter_mut()
+        .filter(move |(id, _)| ids.contains(id))
+        .filter_map(|(_, obj)| obj.as_mut())
+}
@@ -0,0 +1,520 @@
+use crate::map::GridData;
+use crate::processing::{least_squares, Direction, Operation};
+use egui::plot::{MarkerShape, PlotUi, Points};
+use egui::{Color32, Ui};
+use ndarray::{Array1, Array2};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Method {
+    Euler,
+    HalfWidth,
+    AnalyticSignal,
+}
+
+impl Method {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Method::Euler => "Euler",
+            Method::HalfWidth => "Half-width",
+            Method::AnalyticSignal => "Analytic signal",
+        }
+    }
+
+    fn marker(&self) -> (MarkerShape, Color32) {
+        match self {
+            Method::Euler => (MarkerShape::Cross, Color32::from_rgb(220, 40, 160)),
+            Method::HalfWidth => (MarkerShape::Diamond, Color32::from_rgb(20, 150, 150)),
+            Method::AnalyticSignal => (MarkerShape::Asterisk, Color32::from_rgb(230, 120, 20)),
+        }
+    }
+}
+
+/// Estimated source location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    pub method: Method,
+    pub position: [f64; 3],
+    /// Data the estimate came from, e.g. "x profile".
+    pub data: String,
+}
+
+/// Structural indices of gravity sources, one less than for the magnetic equivalents.
+const STRUCTURAL_INDICES: [(f64, &str); 3] = [
+    (0., "0: contact, sill, dyke"),
+    (1., "1: horizontal cylinder"),
+    (2., "2: sphere"),
+];
+
+/// Distance from the peak at which a source of structural index `index` falls to half
+/// of its peak value, over the depth. Gz above a sphere and the analytic signal of a
+/// cylinder both vary as (1 + x²/h²)^(-(N + 1)/2).
+fn half_width_factor(index: f64) -> f64 {
+    (2f64.powf(2. / (index + 1.)) - 1.).sqrt()
+}
+
+/// Half-width over the depth of the analytic signal |A| of gz from a compact source of
+/// structural index `index` on a grid. Over the source, gz ∝ h / r^(N+1) gives
+/// |A| ∝ √((N+1)² x² h² + (x² − N h²)²) / r^(N+3), e.g. √(x² + 4h²) / r⁴ for a sphere,
+/// solved for half its peak by bisection. For N = 1 this is the 2D form, and sources
+/// of lower index are only 2D, so those use it.
+fn signal_half_width_factor(index: f64) -> f64 {
+    if index <= 1. {
+        return half_width_factor(index);
+    }
+    let signal = |x: f64| {
+        let r2 = x * x + 1.;
+        ((index + 1.).powi(2) * x * x + (x * x - index).powi(2)).sqrt() / r2.powf((index + 3.) / 2.)
+    };
+    let half = signal(0.) / 2.;
+    let [mut low, mut high] = [0., 10.];
+    for _ in 0..60 {
+        let middle = (low + high) / 2.;
+        if signal(middle) > half {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.
+}
+
+/// Distance from `peak` to where `values` first fall to half of its value, averaged
+/// over the sides where that happens.
+fn half_width(positions: &[f64], values: &[f64], peak: usize) -> Option<f64> {
+    let half = values[peak] / 2.;
+    let crossing = |indices: &mut dyn Iterator<Item = usize>| {
+        let mut previous = peak;
+        for i in indices {
+            if values[i] <= half {
+                let t = (values[previous] - half) / (values[previous] - values[i]);
+                let position = positions[previous] + t * (positions[i] - positions[previous]);
+                return Some((position - positions[peak]).abs());
+            }
+            previous = i;
+        }
+        None
+    };
+    let sides: Vec<f64> = [
+        crossing(&mut (0..peak).rev()),
+        crossing(&mut (peak + 1..values.len())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!sides.is_empty()).then(|| sides.iter().sum::<f64>() / sides.len() as f64)
+}
+
+/// Position of the peak at index `peak` from a parabola through it and its neighbours.
+fn peak_position(positions: &[f64], values: &[f64], peak: usize) -> f64 {
+    if peak == 0 || peak + 1 >= values.len() {
+        return positions[peak];
+    }
+    let [a, b, c] = [values[peak - 1], values[peak], values[peak + 1]];
+    let curvature = a - 2. * b + c;
+    if curvature >= 0. {
+        return positions[peak];
+    }
+    let offset = 0.5 * (a - c) / curvature;
+    positions[peak] + offset * (positions[peak + 1] - positions[peak - 1]) / 2.
+}
+
+/// Local maxima above `threshold` times the largest value.
+fn peaks(values: &[f64], threshold: f64) -> Vec<usize> {
+    let max = values.iter().copied().fold(0., f64::max);
+    (1..values.len().saturating_sub(1))
+        .filter(|&i| {
+            values[i] > threshold * max && values[i] >= values[i - 1] && values[i] > values[i + 1]
+        })
+        .collect()
+}
+
+/// Gz profile along a straight line, with the stations' distance along it.
+pub struct ProfileData<'a> {
+    pub name: &'a str,
+    pub points: Array2<f64>,
+    /// Column of `points` the profile runs along.
+    pub axis: usize,
+    pub gz: Array1<f64>,
+}
+
+impl ProfileData<'_> {
+    fn position(&self, distance: f64, z: f64) -> [f64; 3] {
+        let mut position = [self.points[[0, 0]], self.points[[0, 1]], z];
+        position[self.axis] = distance;
+        position
+    }
+}
+
+/// Euler deconvolution, half-width and analytic-signal depth estimates from gz.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct SourceEstimation {
+    pub open: bool,
+    pub index: f64,
+    /// Euler window in stations, or stations per side on grids.
+    pub window: usize,
+    pub euler: bool,
+    pub half_width: bool,
+    pub analytic_signal: bool,
+    /// Peaks below this fraction of the largest are ignored.
+    pub threshold: f64,
+    /// Estimate from the data after the active processing batch.
+    pub processed: bool,
+    pub show: bool,
+    #[serde(skip)]
+    pub estimates: Vec<Estimate>,
+}
+
+impl Default for SourceEstimation {
+    fn default() -> Self {
+        Self {
+            open: false,
+            index: 2.,
+            window: 10,
+            euler: true,
+            half_width: true,
+            analytic_signal: true,
+            threshold: 0.2,
+            processed: false,
+            show: true,
+            estimates: vec![],
+        }
+    }
+}
+
+impl SourceEstimation {
+    /// Replace the estimates with those from the profiles and grid.
+    pub fn run(&mut self, profiles: &[ProfileData<'_>], grid: Option<(&Array2<f64>, &GridData)>) {
+        self.estimates.clear();
+        for profile in profiles {
+            self.profile(profile);
+        }
+        if let Some((points, grid)) = grid {
+            self.grid(points, grid);
+        }
+    }
+
+    fn push(&mut self, method: Method, position: [f64; 3], data: &str) {
+        self.estimates.push(Estimate {
+            method,
+            position,
+            data: data.to_string(),
+        });
+    }
+
+    /// Profile estimates treat the sources as 2D, striking across the line. Compact bodies
+    /// come out too deep from Euler and the analytic signal, the grid is better for those.
+    fn profile(&mut self, profile: &ProfileData<'_>) {
+        let n = profile.gz.len();
+        if n < 3 {
+            return;
+        }
+        let positions = profile.points.column(profile.axis).to_vec();
+        let z = profile.points.column(2).to_vec();
+        let spacing = positions[1] - positions[0];
+        let gz = &profile.gz;
+        let tx = Operation::HorizontalDerivative {
+            direction: Direction::X,
+        }
+        .apply_profile(spacing, gz);
+        // The filter's z is positive down.
+        let tz = -Operation::VerticalDerivative { order: 1 }.apply_profile(spacing, gz);
+
+        if self.euler {
+            let window = self.window.clamp(3, n);
+            let gradient = (&tx * &tx + &tz * &tz).mapv(f64::sqrt);
+            let max_gradient = gradient.iter().copied().fold(0., f64::max);
+            for start in (0..=n - window).step_by((window / 2).max(1)) {
+                let range = start..start + window;
+                let strength = range.clone().map(|i| gradient[i]).fold(0., f64::max);
+                if strength < self.threshold * max_gradient {
+                    continue;
+                }
+                let rows: Vec<Vec<f64>> = range
+                    .clone()
+                    .map(|i| vec![tx[i], tz[i], self.index])
+                    .collect();
+                let rhs: Vec<f64> = range
+                    .clone()
+                    .map(|i| positions[i] * tx[i] + z[i] * tz[i] + self.index * gz[i])
+                    .collect();
+                let solution = least_squares(&rows, &rhs);
+                let (x0, z0) = (solution[0], solution[1]);
+                let (first, last) = (positions[start], positions[start + window - 1]);
+                let span = (last - first).abs();
+                let inside = x0 >= first.min(last) - span && x0 <= first.max(last) + span;
+                let below = range.clone().all(|i| z0 < z[i]);
+                if inside && below && z0.is_finite() {
+                    self.push(Method::Euler, profile.position(x0, z0), profile.name);
+                }
+            }
+        }
+
+        let factor = half_width_factor(self.index);
+        if self.half_width && self.index >= 1. {
+            // The largest anomaly, positive or negative.
+            let peak = (0..n)
+                .max_by(|&i, &j| gz[i].abs().total_cmp(&gz[j].abs()))
+                .unwrap();
+            let sign = gz[peak].signum();
+            let values: Vec<f64> = gz.iter().map(|v| v * sign).collect();
+            if let Some(width) = half_width(&positions, &values, peak) {
+                let distance = peak_position(&positions, &values, peak);
+                let position = profile.position(distance, z[peak] - width / factor);
+                self.push(Method::HalfWidth, position, profile.name);
+            }
+        }
+
+        if self.analytic_signal {
+            let signal: Vec<f64> = tx.iter().zip(&tz).map(|(x, z)| x.hypot(*z)).collect();
+            for peak in peaks(&signal, self.threshold) {
+                if let Some(width) = half_width(&positions, &signal, peak) {
+                    let distance = peak_position(&positions, &signal, peak);
+                    let position = profile.position(distance, z[peak] - width / factor);
+                    self.push(Method::AnalyticSignal, position, profile.name);
+                }
+            }
+        }
+    }
+
+    /// Euler windows and analytic-signal peaks on the grid, `points` in x-major order.
+    fn grid(&mut self, points: &Array2<f64>, grid: &GridData) {
+        let (nx, ny) = grid.values.dim();
+        if nx < 3 || ny < 3 {
+            return;
+        }
+        let derivative = |direction| Operation::HorizontalDerivative { direction }.apply_grid(grid);
+        let tx = derivative(Direction::X).values;
+        let ty = derivative(Direction::Y).values;
+        let tz = -Operation::VerticalDerivative { order: 1 }
+            .apply_grid(grid)
+            .values;
+        let z = |i: usize, j: usize| points[[i * ny + j, 2]];
+
+        if self.euler {
+            let window = self.window.clamp(3, nx.min(ny));
+            let gradient = (&tx * &tx + &ty * &ty + &tz * &tz).mapv(f64::sqrt);
+            let max_gradient = gradient.iter().copied().fold(0., f64::max);
+            let step = (window / 2).max(1);
+            for i0 in (0..=nx - window).step_by(step) {
+                for j0 in (0..=ny - window).step_by(step) {
+                    let cells: Vec<(usize, usize)> = (i0..i0 + window)
+                        .flat_map(|i| (j0..j0 + window).map(move |j| (i, j)))
+                        .collect();
+                    let strength = cells.iter().map(|&c| gradient[c]).fold(0., f64::max);
+                    if strength < self.threshold * max_gradient {
+                        continue;
+                    }
+                    let rows: Vec<Vec<f64>> = cells
+                        .iter()
+                        .map(|&c| vec![tx[c], ty[c], tz[c], self.index])
+                        .collect();
+                    let rhs: Vec<f64> = cells
+                        .iter()
+                        .map(|&(i, j)| {
+                            grid.x[i] * tx[[i, j]]
+                                + grid.y[j] * ty[[i, j]]
+                                + z(i, j) * tz[[i, j]]
+                                + self.index * grid.values[[i, j]]
+                        })
+                        .collect();
+                    let solution = least_squares(&rows, &rhs);
+                    let [x0, y0, z0] = [solution[0], solution[1], solution[2]];
+                    let span = [
+                        grid.x[i0 + window - 1] - grid.x[i0],
+                        grid.y[j0 + window - 1] - grid.y[j0],
+                    ];
+                    let inside = x0 >= grid.x[i0] - span[0]
+                        && x0 <= grid.x[i0 + window - 1] + span[0]
+                        && y0 >= grid.y[j0] - span[1]
+                        && y0 <= grid.y[j0 + window - 1] + span[1];
+                    let below = cells.iter().all(|&(i, j)| z0 < z(i, j));
+                    if inside && below && z0.is_finite() {
+                        self.push(Method::Euler, [x0, y0, z0], "grid");
+                    }
+                }
+            }
+        }
+
+        if self.analytic_signal {
+            let signal = (&tx * &tx + &ty * &ty + &tz * &tz).mapv(f64::sqrt);
+            let max = signal.iter().copied().fold(0., f64::max);
+            let factor = signal_half_width_factor(self.index);
+            for i in 1..nx - 1 {
+                for j in 1..ny - 1 {
+                    let value = signal[[i, j]];
+                    let neighbours = [
+                        (0, 1),
+                        (2, 1),
+                        (1, 0),
+                        (1, 2),
+                        (0, 0),
+                        (2, 2),
+                        (0, 2),
+                        (2, 0),
+                    ];
+                    let is_peak = value > self.threshold * max
+                        && neighbours
+                            .iter()
+                            .all(|&(di, dj)| signal[[i + di - 1, j + dj - 1]] < value);
+                    if !is_peak {
+                        continue;
+                    }
+                    // Average the half-widths along x and y through the peak.
+                    let widths: Vec<f64> = [
+                        half_width(grid.x.as_slice().unwrap(), &signal.column(j).to_vec(), i),
+                        half_width(grid.y.as_slice().unwrap(), &signal.row(i).to_vec(), j),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                    if !widths.is_empty() {
+                        let width = widths.iter().sum::<f64>() / widths.len() as f64;
+                        let position = [grid.x[i], grid.y[j], z(i, j) - width / factor];
+                        self.push(Method::AnalyticSignal, position, "grid");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw the estimates in a view with the given axes, e.g. [0, 2] for XZ.
+    pub fn show(&self, plot_ui: &mut PlotUi, axes: [usize; 2]) {
+        if !self.show {
+            return;
+        }
+        for method in [Method::Euler, Method::HalfWidth, Method::AnalyticSignal] {
+            let points: Vec<[f64; 2]> = self
+                .estimates
+                .iter()
+                .filter(|estimate| estimate.method == method)
+                .map(|estimate| [estimate.position[axes[0]], estimate.position[axes[1]]])
+                .collect();
+            if !points.is_empty() {
+                let (shape, colour) = method.marker();
+                plot_ui.points(
+                    Points::new(points)
+                        .name(method.label())
+                        .shape(shape)
+                        .color(colour)
+                        .radius(4.),
+                );
+            }
+        }
+    }
+
+    /// Settings, returning whether to estimate again.
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        ui.label("Source locations from gz on the x and y profiles and the XY grid.");
+        egui::ComboBox::from_label("Structural index")
+            .selected_text(format!("{}", self.index))
+            .show_ui(ui, |ui| {
+                for (index, label) in STRUCTURAL_INDICES {
+                    ui.selectable_value(&mut self.index, index, label);
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.euler, Method::Euler.label());
+            ui.add_enabled(
+                self.euler,
+                egui::DragValue::new(&mut self.window)
+                    .clamp_range(3..=50)
+                    .suffix(" station window"),
+            );
+        });
+        ui.add_enabled(
+            self.index >= 1.,
+            egui::Checkbox::new(&mut self.half_width, "Half-width rule (profiles)"),
+        )
+        .on_disabled_hover_text("Needs a sphere or cylinder structural index");
+        ui.checkbox(&mut self.analytic_signal, "Analytic-signal peaks");
+        ui.add(
+            egui::Slider::new(&mut self.threshold, 0.0..=1.0)
+                .text("threshold")
+                .clamp_to_range(true),
+        )
+        .on_hover_text("Ignore windows and peaks weaker than this fraction of the strongest");
+        ui.checkbox(&mut self.processed, "Use processed data");
+        ui.checkbox(&mut self.show, "Show in the model views");
+        ui.button("Estimate").clicked()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gravity_objects::{DataType, GravityCalc, Sphere};
+    use crate::testing::sphere_grid;
+
+    const DEPTH: f64 = 10.;
+
+    fn sphere() -> Sphere {
+        Sphere {
+            x_centroid: 2.,
+            y_centroid: 0.,
+            z_centroid: -DEPTH,
+            ..Sphere::default()
+        }
+    }
+
+    fn estimation(method: Method) -> SourceEstimation {
+        SourceEstimation {
+            euler: method == Method::Euler,
+            half_width: method == Method::HalfWidth,
+            analytic_signal: method == Method::AnalyticSignal,
+            ..SourceEstimation::default()
+        }
+    }
+
+    fn median_depth(estimates: &[Estimate]) -> f64 {
+        let mut depths: Vec<f64> = estimates.iter().map(|e| -e.position[2]).collect();
+        assert!(!depths.is_empty());
+        depths.sort_by(f64::total_cmp);
+        depths[depths.len() / 2]
+    }
+
+    #[test]
+    fn half_width_depth_of_sphere() {
+        let points = Array2::from_shape_fn((201, 3), |(i, c)| match c {
+            0 => i as f64 * 0.5 - 50.,
+            _ => 0.,
+        });
+        let gz = sphere().calculate(&DataType::Gz, &points);
+        let profile = ProfileData {
+            name: "x profile",
+            points,
+            axis: 0,
+            gz,
+        };
+        let mut estimation = estimation(Method::HalfWidth);
+        estimation.run(&[profile], None);
+        let [estimate] = estimation.estimates.as_slice() else {
+            panic!("{:?}", estimation.estimates);
+        };
+        assert!((estimate.position[0] - 2.).abs() < 0.1, "{estimate:?}");
+        let depth = -estimate.position[2];
+        assert!((depth - DEPTH).abs() < 0.02 * DEPTH, "{estimate:?}");
+    }
+
+    #[test]
+    fn euler_depth_of_sphere() {
+        let (points, grid) = sphere_grid(&sphere(), 81, 1., 0.);
+        let mut estimation = estimation(Method::Euler);
+        estimation.run(&[], Some((&points, &grid)));
+        let depth = median_depth(&estimation.estimates);
+        assert!((depth - DEPTH).abs() < 0.05 * DEPTH, "{depth}");
+    }
+
+    #[test]
+    fn analytic_signal_depth_of_sphere() {
+        let (points, grid) = sphere_grid(&sphere(), 81, 1., 0.);
+        let mut estimation = estimation(Method::AnalyticSignal);
+        estimation.run(&[], Some((&points, &grid)));
+        let [estimate] = estimation.estimates.as_slice() else {
+            panic!("{:?}", estimation.estimates);
+        };
+        assert!((estimate.position[0] - 2.).abs() < 1e-9, "{estimate:?}");
+        let depth = -estimate.position[2];
+        assert!((depth - DEPTH).abs() < 0.02 * DEPTH, "{depth}");
+    }
+}
@@ -0,0 +1,472 @@
+use crate::gravity_objects::{GravityModelObject, GravityObject, Parameter, TriangleMesh};
+use crate::map::GridData;
+use crate::model::Model;
+use ndarray::{Array1, Array2, Axis};
+use std::error::Error;
+use std::fmt::Write;
+
+/// Value written for missing data in the ESRI grid.
+const ESRI_NODATA: f64 = -9999.;
+
+/// Description of exported data, written as a header where the format allows it.
+#[derive(Debug, Clone)]
+pub struct ExportMetadata {
+    pub model_name: String,
+    pub component: String,
+    pub units: String,
+    /// Survey height at the origin in metres.
+    pub height: f64,
+    /// Slope of the survey surface along x and y in m/m.
+    pub gradient: [f64; 2],
+    /// Seed of the added noise, if any.
+    pub noise_seed: Option<u64>,
+}
+
+impl ExportMetadata {
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("model", self.model_name.to_string()),
+            ("component", self.component.to_string()),
+            ("units", self.units.to_string()),
+            ("height_m", self.height.to_string()),
+            ("x_gradient", self.gradient[0].to_string()),
+            ("y_gradient", self.gradient[1].to_string()),
+        ];
+        match self.noise_seed {
+            Some(seed) => entries.push(("noise_seed", seed.to_string())),
+            None => entries.push(("noise", "none".to_string())),
+        }
+        entries
+    }
+
+    /// `# key: value` header lines.
+    fn comment_header(&self) -> String {
+        self.entries()
+            .iter()
+            .map(|(key, value)| format!("# {key}: {value}\n"))
+            .collect()
+    }
+
+    /// Short form for file names, e.g. `Default_Gz`.
+    pub fn file_stem(&self) -> String {
+        format!("{}_{}", self.model_name, self.component)
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GridFormat {
+    Xyz,
+    SurferGrd,
+    EsriAscii,
+    CfJson,
+}
+
+impl GridFormat {
+    pub const ALL: [GridFormat; 4] = [
+        GridFormat::Xyz,
+        GridFormat::SurferGrd,
+        GridFormat::EsriAscii,
+        GridFormat::CfJson,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GridFormat::Xyz => "XYZ",
+            GridFormat::SurferGrd => "Surfer ASCII GRD",
+            GridFormat::EsriAscii => "ESRI ASCII grid",
+            GridFormat::CfJson => "CF-JSON",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GridFormat::Xyz => "xyz",
+            GridFormat::SurferGrd => "grd",
+            GridFormat::EsriAscii => "asc",
+            GridFormat::CfJson => "json",
+        }
+    }
+
+    pub fn write(&self, grid: &GridData, metadata: &ExportMetadata) -> String {
+        match self {
+            GridFormat::Xyz => grid_xyz(grid, metadata),
+            GridFormat::SurferGrd => grid_surfer(grid),
+            GridFormat::EsriAscii => grid_esri(grid),
+            GridFormat::CfJson => grid_cf_json(grid, metadata),
+        }
+    }
+}
+
+/// CSV of a profile: station coordinates followed by one column per named series.
+pub fn profile_csv(
+    points: &Array2<f64>,
+    columns: &[(String, Array1<f64>)],
+    metadata: &ExportMetadata,
+) -> String {
+    let mut csv = metadata.comment_header();
+    let header: Vec<String> = ["x_m", "y_m", "z_m"]
+        .iter()
+        .map(|s| s.to_string())
+        .chain(columns.iter().map(|(name, _)| csv_field(name)))
+        .collect();
+    csv.push_str(&header.join(","));
+    csv.push('\n');
+    for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+        let mut row: Vec<String> = point.iter().map(|v| v.to_string()).collect();
+        row.extend(columns.iter().map(|(_, data)| data[i].to_string()));
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Whitespace separated x, y, value triples with a commented header.
+pub fn grid_xyz(grid: &GridData, metadata: &ExportMetadata) -> String {
+    let mut xyz = metadata.comment_header();
+    xyz.push_str("# x_m y_m value\n");
+    for (j, y) in grid.y.iter().enumerate() {
+        for (i, x) in grid.x.iter().enumerate() {
+            writeln!(xyz, "{x} {y} {}", grid.values[[i, j]]).unwrap();
+        }
+    }
+    xyz
+}
+
+/// Surfer 6 ASCII grid (DSAA). Rows run from the minimum to the maximum y. The format
+/// has no room for metadata.
+pub fn grid_surfer(grid: &GridData) -> String {
+    let [x0, x1, y0, y1] = grid.bounds();
+    let finite = grid.values.iter().copied().filter(|v| v.is_finite());
+    let z_min = finite.clone().fold(f64::INFINITY, f64::min);
+    let z_max = finite.fold(f64::NEG_INFINITY, f64::max);
+    let mut grd = String::from("DSAA\n");
+    writeln!(grd, "{} {}", grid.x.len(), grid.y.len()).unwrap();
+    writeln!(grd, "{x0} {x1}").unwrap();
+    writeln!(grd, "{y0} {y1}").unwrap();
+    writeln!(grd, "{z_min} {z_max}").unwrap();
+    for j in 0..grid.y.len() {
+        let row: Vec<String> = (0..grid.x.len())
+            .map(|i| match grid.values[[i, j]] {
+                v if v.is_finite() => v.to_string(),
+                // Surfer's blanking value.
+                _ => "1.70141e38".to_string(),
+            })
+            .collect();
+        grd.push_str(&row.join(" "));
+        grd.push('\n');
+    }
+    grd
+}
+
+/// ESRI ASCII raster with cell centres on the grid nodes. Rows run from the maximum to
+/// the minimum y. Non-square cells use the `dx`/`dy` extension read by GDAL.
+pub fn grid_esri(grid: &GridData) -> String {
+    let [x0, x1, y0, y1] = grid.bounds();
+    let nx = grid.x.len();
+    let ny = grid.y.len();
+    let dx = (x1 - x0) / (nx.max(2) - 1) as f64;
+    let dy = (y1 - y0) / (ny.max(2) - 1) as f64;
+    let mut asc = String::new();
+    writeln!(asc, "ncols {nx}").unwrap();
+    writeln!(asc, "nrows {ny}").unwrap();
+    writeln!(asc, "xllcenter {x0}").unwrap();
+    writeln!(asc, "yllcenter {y0}").unwrap();
+    if (dx - dy).abs() <= 1e-9 * dx.abs().max(1.) {
+        writeln!(asc, "cellsize {dx}").unwrap();
+    } else {
+        writeln!(asc, "dx {dx}").unwrap();
+        writeln!(asc, "dy {dy}").unwrap();
+    }
+    writeln!(asc, "NODATA_value {ESRI_NODATA}").unwrap();
+    for j in (0..ny).rev() {
+        let row: Vec<String> = (0..nx)
+            .map(|i| match grid.values[[i, j]] {
+                v if v.is_finite() => v.to_string(),
+                _ => ESRI_NODATA.to_string(),
+            })
+            .collect();
+        asc.push_str(&row.join(" "));
+        asc.push('\n');
+    }
+    asc
+}
+
+/// JSON laid out like a CF-convention NetCDF file: dimensions, variables with
+/// attributes and data, and global attributes.
+pub fn grid_cf_json(grid: &GridData, metadata: &ExportMetadata) -> String {
+    let values: Vec<Vec<Option<f64>>> = (0..grid.y.len())
+        .map(|j| {
+            (0..grid.x.len())
+                .map(|i| Some(grid.values[[i, j]]).filter(|v| v.is_finite()))
+                .collect()
+        })
+        .collect();
+    let mut attributes = serde_json::Map::new();
+    attributes.insert("Conventions".to_string(), "CF-1.8".into());
+    attributes.insert(
+        "title".to_string(),
+        format!("{} {}", metadata.model_name, metadata.component).into(),
+    );
+    attributes.insert("source".to_string(), "Gravity Model Builder".into());
+    for (key, value) in metadata.entries() {
+        attributes.insert(key.to_string(), value.into());
+    }
+    let json = serde_json::json!({
+        "dimensions": { "y": grid.y.len(), "x": grid.x.len() },
+        "variables": {
+            "x": {
+                "dimensions": ["x"],
+                "attributes": {
+                    "standard_name": "projection_x_coordinate",
+                    "units": "m",
+                },
+                "data": grid.x.to_vec(),
+            },
+            "y": {
+                "dimensions": ["y"],
+                "attributes": {
+                    "standard_name": "projection_y_coordinate",
+                    "units": "m",
+                },
+                "data": grid.y.to_vec(),
+            },
+            "data": {
+                "dimensions": ["y", "x"],
+                "attributes": {
+                    "long_name": metadata.component,
+                    "units": metadata.units,
+                    "height": metadata.height,
+                },
+                "data": values,
+            },
+        },
+        "attributes": attributes,
+    });
+    serde_json::to_string_pretty(&json).unwrap()
+}
+
+/// Cuboid vertex order for a VTK hexahedron: the x = min face wound towards x = max,
+/// then the matching x = max vertices.
+const VTK_HEXAHEDRON_ORDER: [usize; 8] = [0, 3, 2, 1, 4, 5, 6, 7];
+const VTK_TRIANGLE: u8 = 5;
+const VTK_TETRA: u8 = 10;
+const VTK_HEXAHEDRON: u8 = 12;
+
+/// Bodies of the model in display order.
+fn bodies(model: &Model) -> Vec<&GravityModelObject> {
+    model
+        .ordered_ids()
+        .iter()
+        .filter_map(|id| model.objects.get(id))
+        .flatten()
+        .collect()
+}
+
+/// Legacy VTK unstructured grid of the model bodies. Cuboids are single hexahedra,
+/// spheres are fans of tetrahedra from the centre to the tessellated surface and
+/// imported meshes keep their surface triangles, which need not be star shaped about any
+/// point. Each cell carries the density and id of its body.
+pub fn bodies_vtk(model: &Model) -> String {
+    let mut points: Vec<[f64; 3]> = vec![];
+    let mut cells: Vec<(u8, Vec<usize>, &GravityModelObject)> = vec![];
+    for obj in bodies(model) {
+        let offset = points.len();
+        let mesh = obj.object.mesh();
+        points.extend(&mesh.vertices);
+        match &obj.object {
+            GravityObject::Cuboid(_) => {
+                let ids = VTK_HEXAHEDRON_ORDER.iter().map(|i| offset + i).collect();
+                cells.push((VTK_HEXAHEDRON, ids, obj));
+            }
+            GravityObject::Sphere(_) => {
+                let centre = obj.object.as_calc().centre();
+                points.push([centre[0], centre[1], centre[2]]);
+                let centre_id = points.len() - 1;
+                // Surface triangles are wound outwards, so reverse them for a positive
+                // tetrahedron volume.
+                for [a, b, c] in &mesh.triangles {
+                    let ids = vec![offset + a, offset + c, offset + b, centre_id];
+                    cells.push((VTK_TETRA, ids, obj));
+                }
+            }
+            GravityObject::Polyhedron(_) => {
+                for triangle in &mesh.triangles {
+                    let ids = triangle.iter().map(|i| offset + i).collect();
+                    cells.push((VTK_TRIANGLE, ids, obj));
+                }
+            }
+        }
+    }
+
+    let mut vtk = String::from("# vtk DataFile Version 3.0\n");
+    writeln!(vtk, "{} bodies", model.name.replace('\n', " ")).unwrap();
+    vtk.push_str("ASCII\nDATASET UNSTRUCTURED_GRID\n");
+    writeln!(vtk, "POINTS {} double", points.len()).unwrap();
+    for [x, y, z] in &points {
+        writeln!(vtk, "{x} {y} {z}").unwrap();
+    }
+    let size: usize = cells.iter().map(|(_, ids, _)| ids.len() + 1).sum();
+    writeln!(vtk, "CELLS {} {size}", cells.len()).unwrap();
+    for (_, ids, _) in &cells {
+        let ids: Vec<String> = ids.iter().map(|i| i.to_string()).collect();
+        writeln!(vtk, "{} {}", ids.len(), ids.join(" ")).unwrap();
+    }
+    writeln!(vtk, "CELL_TYPES {}", cells.len()).unwrap();
+    for (cell_type, _, _) in &cells {
+        writeln!(vtk, "{cell_type}").unwrap();
+    }
+    writeln!(vtk, "CELL_DATA {}", cells.len()).unwrap();
+    vtk.push_str("SCALARS density double 1\nLOOKUP_TABLE default\n");
+    for (_, _, obj) in &cells {
+        let density = Parameter::Density.get(&obj.object).unwrap_or_default();
+        writeln!(vtk, "{density}").unwrap();
+    }
+    vtk.push_str("SCALARS id long 1\nLOOKUP_TABLE default\n");
+    for (_, _, obj) in &cells {
+        writeln!(vtk, "{}", obj.id).unwrap();
+    }
+    vtk
+}
+
+/// Wavefront OBJ with one named object per body.
+pub fn bodies_obj(model: &Model) -> String {
+    let mut obj_file = format!("# {} bodies\n", model.name.replace('\n', " "));
+    let mut offset = 1;
+    for obj in bodies(model) {
+        let mesh = obj.object.mesh();
+        let density = Parameter::Density.get(&obj.object).unwrap_or_default();
+        writeln!(
+            obj_file,
+            "o {}_{}",
+            obj.id,
+            obj.name.replace(char::is_whitespace, "_")
+        )
+        .unwrap();
+        writeln!(obj_file, "# density {density} kg/m^3").unwrap();
+        for [x, y, z] in &mesh.vertices {
+            writeln!(obj_file, "v {x} {y} {z}").unwrap();
+        }
+        for [a, b, c] in &mesh.triangles {
+            writeln!(obj_file, "f {} {} {}", a + offset, b + offset, c + offset).unwrap();
+        }
+        offset += mesh.vertices.len();
+    }
+    obj_file
+}
+
+/// ASCII STL with one solid per body.
+pub fn bodies_stl(model: &Model) -> String {
+    let mut stl = String::new();
+    for obj in bodies(model) {
+        let name = format!("{}_{}", obj.id, obj.name.replace(char::is_whitespace, "_"));
+        stl_solid(&mut stl, &name, &obj.object.mesh());
+    }
+    stl
+}
+
+fn stl_solid(stl: &mut String, name: &str, mesh: &TriangleMesh) {
+    writeln!(stl, "solid {name}").unwrap();
+    for triangle in &mesh.triangles {
+        let [nx, ny, nz] = mesh.normal(triangle);
+        writeln!(stl, "  facet normal {nx:e} {ny:e} {nz:e}").unwrap();
+        stl.push_str("    outer loop\n");
+        for [x, y, z] in triangle.map(|i| mesh.vertices[i]) {
+            writeln!(stl, "      vertex {x:e} {y:e} {z:e}").unwrap();
+        }
+        stl.push_str("    endloop\n  endfacet\n");
+    }
+    writeln!(stl, "endsolid {name}").unwrap();
+}
+
+/// Legacy VTK structured grid of data on the observation surface. `points` and `data`
+/// are ordered as `DataParameters::points_xy`, x major.
+pub fn grid_vtk(
+    points: &Array2<f64>,
+    data: &Array1<f64>,
+    nx: usize,
+    ny: usize,
+    metadata: &ExportMetadata,
+) -> String {
+    let mut vtk = String::from("# vtk DataFile Version 3.0\n");
+    let header: Vec<String> = metadata
+        .entries()
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    writeln!(vtk, "{}", header.join(" ").replace('\n', " ")).unwrap();
+    vtk.push_str("ASCII\nDATASET STRUCTURED_GRID\n");
+    writeln!(vtk, "DIMENSIONS {nx} {ny} 1").unwrap();
+    writeln!(vtk, "POINTS {} double", nx * ny).unwrap();
+    // VTK runs x fastest.
+    for j in 0..ny {
+        for i in 0..nx {
+            let point = points.row(i * ny + j);
+            writeln!(vtk, "{} {} {}", point[0], point[1], point[2]).unwrap();
+        }
+    }
+    writeln!(vtk, "POINT_DATA {}", nx * ny).unwrap();
+    let name: String = metadata
+        .component
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect();
+    writeln!(vtk, "SCALARS {name} double 1\nLOOKUP_TABLE default").unwrap();
+    for j in 0..ny {
+        for i in 0..nx {
+            writeln!(vtk, "{}", data[i * ny + j]).unwrap();
+        }
+    }
+    vtk
+}
+
+/// Save text through a native file dialog, or as a browser download on the web. Returns
+/// Ok(false) if the dialog is cancelled.
+pub fn save_text(file_name: &str, contents: &str) -> Result<bool, Box<dyn Error>> {
+    save_file(file_name, contents.as_bytes())
+}
+
+/// Save bytes through a native file dialog. Returns Ok(false) if the dialog is cancelled.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(file_name: &str, contents: &[u8]) -> Result<bool, Box<dyn Error>> {
+    match rfd::FileDialog::new().set_file_name(file_name).save_file() {
+        Some(path) => {
+            std::fs::write(path, contents)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Save bytes as a browser download.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(file_name: &str, contents: &[u8]) -> Result<bool, Box<dyn Error>> {
+    use wasm_bindgen::JsCast;
+
+    let js_error = |err: wasm_bindgen::JsValue| format!("{err:?}");
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document")?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_error)?
+        .unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(js_error)?;
+    Ok(true)
+}
@@ -0,0 +1,815 @@
+use crate::map::Colormap;
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use egui::{Align2, Color32, ColorImage, Ui};
+use std::error::Error;
+use std::fmt::Write;
+
+/// How a series is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    Line,
+    DashedLine,
+    Points,
+    /// Closed outline with a translucent fill.
+    Polygon,
+}
+
+#[derive(Debug, Clone)]
+pub struct Series {
+    /// Legend entry, unlabelled series are left out of the legend.
+    pub name: Option<String>,
+    pub colour: Color32,
+    pub mark: Mark,
+    pub points: Vec<[f64; 2]>,
+}
+
+impl Series {
+    pub fn new(name: Option<String>, colour: Color32, mark: Mark, points: Vec<[f64; 2]>) -> Self {
+        Self {
+            name,
+            colour,
+            mark,
+            points,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ColourBar {
+    pub colormap: Colormap,
+    pub range: [f64; 2],
+    pub label: String,
+}
+
+/// One set of axes.
+#[derive(Clone, Default)]
+pub struct Panel {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub series: Vec<Series>,
+    /// Image drawn under the series, with its [x0, x1, y0, y1] bounds.
+    pub image: Option<(ColorImage, [f64; 4])>,
+    pub colour_bar: Option<ColourBar>,
+    /// Use the same scale on both axes, for cross-sections and maps.
+    pub equal_aspect: bool,
+}
+
+/// Plot description independent of egui, rendered headlessly to SVG or PNG. Panels are
+/// stacked vertically.
+#[derive(Clone, Default)]
+pub struct Figure {
+    pub title: String,
+    pub panels: Vec<Panel>,
+}
+
+/// Size of exported figures.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct FigureSettings {
+    /// Size in SVG pixels (1/96 inch).
+    pub width: f32,
+    pub height: f32,
+    /// PNG pixels per SVG pixel.
+    pub pixel_ratio: f32,
+    pub font_size: f32,
+}
+
+impl Default for FigureSettings {
+    fn default() -> Self {
+        Self {
+            width: 800.,
+            height: 700.,
+            pixel_ratio: 2.,
+            font_size: 13.,
+        }
+    }
+}
+
+impl FigureSettings {
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Size: ");
+            ui.add(
+                egui::DragValue::new(&mut self.width)
+                    .clamp_range(200.0..=4000.)
+                    .suffix(" px"),
+            );
+            ui.label("×");
+            ui.add(
+                egui::DragValue::new(&mut self.height)
+                    .clamp_range(200.0..=4000.)
+                    .suffix(" px"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("PNG resolution: ");
+            ui.add(
+                egui::DragValue::new(&mut self.pixel_ratio)
+                    .speed(0.1)
+                    .clamp_range(0.5..=8.)
+                    .suffix(" x"),
+            );
+            ui.weak(format!(
+                "{:.0} dpi, {:.0} × {:.0} pixels",
+                96. * self.pixel_ratio,
+                self.width * self.pixel_ratio,
+                self.height * self.pixel_ratio
+            ));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Font size: ");
+            ui.add(
+                egui::DragValue::new(&mut self.font_size)
+                    .clamp_range(6.0..=40.)
+                    .suffix(" px"),
+            );
+        });
+    }
+}
+
+impl Figure {
+    pub fn svg(&self, settings: &FigureSettings) -> String {
+        let mut canvas = SvgCanvas::new(settings.width, settings.height);
+        self.draw(&mut canvas, settings);
+        canvas.finish()
+    }
+
+    pub fn png(&self, settings: &FigureSettings) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut canvas = PngCanvas::new(settings.width, settings.height, settings.pixel_ratio)?;
+        self.draw(&mut canvas, settings);
+        Ok(canvas.pixmap.encode_png()?)
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas, settings: &FigureSettings) {
+        let f = settings.font_size;
+        canvas.text(
+            [settings.width / 2., f],
+            &self.title,
+            1.3 * f,
+            Align2::CENTER_TOP,
+            false,
+        );
+        let top = 3. * f;
+        let height = (settings.height - top) / self.panels.len().max(1) as f32;
+        for (i, panel) in self.panels.iter().enumerate() {
+            let area = [0., top + i as f32 * height, settings.width, height];
+            panel.draw(canvas, area, f);
+        }
+    }
+}
+
+impl Panel {
+    fn ranges(&self) -> [[f64; 2]; 2] {
+        let mut range = [[f64::INFINITY, f64::NEG_INFINITY]; 2];
+        let mut include = |p: [f64; 2]| {
+            for k in 0..2 {
+                if p[k].is_finite() {
+                    range[k] = [range[k][0].min(p[k]), range[k][1].max(p[k])];
+                }
+            }
+        };
+        for series in &self.series {
+            series.points.iter().for_each(|p| include(*p));
+        }
+        let padded = range.map(|[min, max]| {
+            let pad = 0.05 * (max - min);
+            [min - pad, max + pad]
+        });
+        let mut range = padded;
+        if let Some((_, [x0, x1, y0, y1])) = &self.image {
+            range[0] = [range[0][0].min(*x0), range[0][1].max(*x1)];
+            range[1] = [range[1][0].min(*y0), range[1][1].max(*y1)];
+        }
+        range.map(|[min, max]| {
+            if !min.is_finite() || !max.is_finite() {
+                [0., 1.]
+            } else if max - min <= f64::EPSILON * max.abs().max(1.) {
+                [min - 1., max + 1.]
+            } else {
+                [min, max]
+            }
+        })
+    }
+
+    /// Draw into `area` = [x, y, width, height] with font size `f`.
+    fn draw(&self, canvas: &mut dyn Canvas, area: [f32; 4], f: f32) {
+        let [ax, ay, aw, ah] = area;
+        let bar_height = if self.colour_bar.is_some() {
+            4.5 * f
+        } else {
+            0.
+        };
+        let left = ax + 5.5 * f;
+        let right = ax + aw - 1.5 * f;
+        let top = ay + 2. * f;
+        let bottom = ay + ah - 3.5 * f - bar_height;
+        let [mut x_range, mut y_range] = self.ranges();
+
+        if self.equal_aspect {
+            let x_scale = (x_range[1] - x_range[0]) / (right - left) as f64;
+            let y_scale = (y_range[1] - y_range[0]) / (bottom - top) as f64;
+            let scale = x_scale.max(y_scale);
+            for (range, pixels) in [(&mut x_range, right - left), (&mut y_range, bottom - top)] {
+                let centre = (range[0] + range[1]) / 2.;
+                let half = scale * pixels as f64 / 2.;
+                *range = [centre - half, centre + half];
+            }
+        }
+        let to_screen = |p: [f64; 2]| -> [f32; 2] {
+            [
+                left + ((p[0] - x_range[0]) / (x_range[1] - x_range[0])) as f32 * (right - left),
+                bottom - ((p[1] - y_range[0]) / (y_range[1] - y_range[0])) as f32 * (bottom - top),
+            ]
+        };
+
+        canvas.text(
+            [(left + right) / 2., ay + 0.4 * f],
+            &self.title,
+            1.1 * f,
+            Align2::CENTER_TOP,
+            false,
+        );
+
+        // Grid and ticks.
+        let grid_colour = Color32::from_gray(220);
+        for x in ticks(x_range, 8) {
+            let [sx, _] = to_screen([x, y_range[0]]);
+            canvas.polyline(&[[sx, top], [sx, bottom]], grid_colour, 0.5, false, false);
+            canvas.polyline(
+                &[[sx, bottom], [sx, bottom + 0.3 * f]],
+                Color32::BLACK,
+                1.,
+                false,
+                false,
+            );
+            canvas.text(
+                [sx, bottom + 0.4 * f],
+                &tick_label(x, x_range, 8),
+                f,
+                Align2::CENTER_TOP,
+                false,
+            );
+        }
+        for y in ticks(y_range, 6) {
+            let [_, sy] = to_screen([x_range[0], y]);
+            canvas.polyline(&[[left, sy], [right, sy]], grid_colour, 0.5, false, false);
+            canvas.polyline(
+                &[[left - 0.3 * f, sy], [left, sy]],
+                Color32::BLACK,
+                1.,
+                false,
+                false,
+            );
+            canvas.text(
+                [left - 0.5 * f, sy],
+                &tick_label(y, y_range, 6),
+                f,
+                Align2::RIGHT_CENTER,
+                false,
+            );
+        }
+        canvas.text(
+            [(left + right) / 2., bottom + 1.9 * f],
+            &self.x_label,
+            f,
+            Align2::CENTER_TOP,
+            false,
+        );
+        canvas.text(
+            [ax + 0.9 * f, (top + bottom) / 2.],
+            &self.y_label,
+            f,
+            Align2::CENTER_CENTER,
+            true,
+        );
+
+        if let Some((image, [x0, x1, y0, y1])) = &self.image {
+            let [sx0, sy1] = to_screen([*x0, *y1]);
+            let [sx1, sy0] = to_screen([*x1, *y0]);
+            canvas.image([sx0, sy1, sx1 - sx0, sy0 - sy1], image);
+        }
+
+        for series in &self.series {
+            let points: Vec<[f32; 2]> = series.points.iter().map(|p| to_screen(*p)).collect();
+            match series.mark {
+                Mark::Line => canvas.polyline(&points, series.colour, 1.5, false, false),
+                Mark::DashedLine => canvas.polyline(&points, series.colour, 1.5, true, false),
+                Mark::Points => {
+                    for p in &points {
+                        canvas.circle(*p, 1.5, series.colour);
+                    }
+                }
+                Mark::Polygon => {
+                    let [r, g, b, _] = series.colour.to_srgba_unmultiplied();
+                    canvas.polygon(&points, Color32::from_rgba_unmultiplied(r, g, b, 50));
+                    canvas.polyline(&points, series.colour, 1.5, false, true);
+                }
+            }
+        }
+
+        canvas.polyline(
+            &[[left, top], [right, top], [right, bottom], [left, bottom]],
+            Color32::BLACK,
+            1.,
+            false,
+            true,
+        );
+
+        // Legend in the top right corner.
+        let entries: Vec<&Series> = self.series.iter().filter(|s| s.name.is_some()).collect();
+        if !entries.is_empty() {
+            let longest = entries
+                .iter()
+                .filter_map(|s| s.name.as_ref())
+                .map(|name| name.chars().count())
+                .max()
+                .unwrap_or_default();
+            let width = 3.2 * f + 0.55 * f * longest as f32;
+            let row = 1.3 * f;
+            let x = right - width - 0.5 * f;
+            let y = top + 0.5 * f;
+            let height = row * entries.len() as f32 + 0.4 * f;
+            let corners = [
+                [x, y],
+                [x + width, y],
+                [x + width, y + height],
+                [x, y + height],
+            ];
+            canvas.polygon(&corners, Color32::from_white_alpha(220));
+            canvas.polyline(&corners, Color32::from_gray(160), 0.5, false, true);
+            for (i, series) in entries.iter().enumerate() {
+                let cy = y + 0.2 * f + row * (i as f32 + 0.5);
+                let swatch = [[x + 0.4 * f, cy], [x + 2.2 * f, cy]];
+                match series.mark {
+                    Mark::Points => canvas.circle([x + 1.3 * f, cy], 2., series.colour),
+                    Mark::DashedLine => canvas.polyline(&swatch, series.colour, 1.5, true, false),
+                    _ => canvas.polyline(&swatch, series.colour, 2., false, false),
+                }
+                canvas.text(
+                    [x + 2.7 * f, cy],
+                    series.name.as_deref().unwrap_or_default(),
+                    f,
+                    Align2::LEFT_CENTER,
+                    false,
+                );
+            }
+        }
+
+        if let Some(bar) = &self.colour_bar {
+            let y = bottom + 3.4 * f;
+            let n_steps = 128;
+            let step = (right - left) / n_steps as f32;
+            for i in 0..n_steps {
+                let x = left + i as f32 * step;
+                let colour = bar.colormap.colour((i as f64 + 0.5) / n_steps as f64);
+                canvas.polygon(
+                    &[
+                        [x, y],
+                        [x + step + 0.3, y],
+                        [x + step + 0.3, y + f],
+                        [x, y + f],
+                    ],
+                    colour,
+                );
+            }
+            canvas.polyline(
+                &[[left, y], [right, y], [right, y + f], [left, y + f]],
+                Color32::BLACK,
+                1.,
+                false,
+                true,
+            );
+            for value in ticks(bar.range, 6) {
+                let t = ((value - bar.range[0]) / (bar.range[1] - bar.range[0])) as f32;
+                let x = left + t * (right - left);
+                canvas.polyline(
+                    &[[x, y + f], [x, y + 1.3 * f]],
+                    Color32::BLACK,
+                    1.,
+                    false,
+                    false,
+                );
+                canvas.text(
+                    [x, y + 1.4 * f],
+                    &tick_label(value, bar.range, 6),
+                    0.9 * f,
+                    Align2::CENTER_TOP,
+                    false,
+                );
+            }
+            canvas.text(
+                [right, y - 0.2 * f],
+                &bar.label,
+                f,
+                Align2::RIGHT_BOTTOM,
+                false,
+            );
+        }
+    }
+}
+
+/// Step between ticks, 1, 2 or 5 times a power of ten, giving about `target` ticks.
+fn tick_step(range: [f64; 2], target: usize) -> f64 {
+    let raw = (range[1] - range[0]).abs() / target.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1., 2., 5., 10.]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10. * magnitude)
+}
+
+fn ticks(range: [f64; 2], target: usize) -> Vec<f64> {
+    let step = tick_step(range, target);
+    if !step.is_finite() || step <= 0. {
+        return vec![];
+    }
+    let first = (range[0] / step).ceil() as i64;
+    let last = (range[1] / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+fn tick_label(value: f64, range: [f64; 2], target: usize) -> String {
+    let decimals = (-tick_step(range, target).log10().floor()).max(0.) as usize;
+    // Avoid "-0".
+    let value = if value.abs() < 1e-12 { 0. } else { value };
+    format!("{value:.decimals$}")
+}
+
+/// Drawing primitives in SVG pixels, y down. Text is black.
+trait Canvas {
+    fn polyline(
+        &mut self,
+        points: &[[f32; 2]],
+        colour: Color32,
+        width: f32,
+        dashed: bool,
+        closed: bool,
+    );
+    fn polygon(&mut self, points: &[[f32; 2]], fill: Color32);
+    fn circle(&mut self, centre: [f32; 2], radius: f32, fill: Color32);
+    /// Text anchored at `position`, `vertical` text reads upwards.
+    fn text(&mut self, position: [f32; 2], text: &str, size: f32, anchor: Align2, vertical: bool);
+    /// Image stretched over `rect` = [x, y, width, height].
+    fn image(&mut self, rect: [f32; 4], image: &ColorImage);
+}
+
+struct SvgCanvas {
+    svg: String,
+}
+
+impl SvgCanvas {
+    fn new(width: f32, height: f32) -> Self {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             viewBox=\"0 0 {width} {height}\" font-family=\"Ubuntu, Helvetica, Arial, sans-serif\">\n"
+        );
+        writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>").unwrap();
+        Self { svg }
+    }
+
+    fn finish(mut self) -> String {
+        self.svg.push_str("</svg>\n");
+        self.svg
+    }
+}
+
+/// SVG colour and opacity attributes of `colour`.
+fn svg_paint(attribute: &str, colour: Color32) -> String {
+    let [r, g, b, a] = colour.to_srgba_unmultiplied();
+    let mut paint = format!("{attribute}=\"#{r:02x}{g:02x}{b:02x}\"");
+    if a < 255 {
+        write!(paint, " {attribute}-opacity=\"{:.3}\"", a as f32 / 255.).unwrap();
+    }
+    paint
+}
+
+fn svg_points(points: &[[f32; 2]]) -> String {
+    let points: Vec<String> = points
+        .iter()
+        .map(|[x, y]| format!("{x:.2},{y:.2}"))
+        .collect();
+    points.join(" ")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Canvas for SvgCanvas {
+    fn polyline(
+        &mut self,
+        points: &[[f32; 2]],
+        colour: Color32,
+        width: f32,
+        dashed: bool,
+        closed: bool,
+    ) {
+        let element = if closed { "polygon" } else { "polyline" };
+        let dash = if dashed {
+            " stroke-dasharray=\"6,4\""
+        } else {
+            ""
+        };
+        writeln!(
+            self.svg,
+            "<{element} points=\"{}\" fill=\"none\" {} stroke-width=\"{width}\" stroke-linejoin=\"round\"{dash}/>",
+            svg_points(points),
+            svg_paint("stroke", colour)
+        )
+        .unwrap();
+    }
+
+    fn polygon(&mut self, points: &[[f32; 2]], fill: Color32) {
+        writeln!(
+            self.svg,
+            "<polygon points=\"{}\" {}/>",
+            svg_points(points),
+            svg_paint("fill", fill)
+        )
+        .unwrap();
+    }
+
+    fn circle(&mut self, centre: [f32; 2], radius: f32, fill: Color32) {
+        writeln!(
+            self.svg,
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{radius}\" {}/>",
+            centre[0],
+            centre[1],
+            svg_paint("fill", fill)
+        )
+        .unwrap();
+    }
+
+    fn text(&mut self, position: [f32; 2], text: &str, size: f32, anchor: Align2, vertical: bool) {
+        let [x, y] = position;
+        let text_anchor = match anchor.x() {
+            egui::Align::Min => "start",
+            egui::Align::Center => "middle",
+            egui::Align::Max => "end",
+        };
+        let baseline = match anchor.y() {
+            egui::Align::Min => "hanging",
+            egui::Align::Center => "central",
+            egui::Align::Max => "text-after-edge",
+        };
+        let rotate = if vertical {
+            format!(" transform=\"rotate(-90 {x:.2} {y:.2})\"")
+        } else {
+            String::new()
+        };
+        writeln!(
+            self.svg,
+            "<text x=\"{x:.2}\" y=\"{y:.2}\" font-size=\"{size}\" text-anchor=\"{text_anchor}\" \
+             dominant-baseline=\"{baseline}\"{rotate}>{}</text>",
+            escape_xml(text)
+        )
+        .unwrap();
+    }
+
+    fn image(&mut self, rect: [f32; 4], image: &ColorImage) {
+        let Some(png) = image_pixmap(image).and_then(|pixmap| pixmap.encode_png().ok()) else {
+            return;
+        };
+        let [x, y, width, height] = rect;
+        writeln!(
+            self.svg,
+            "<image x=\"{x:.2}\" y=\"{y:.2}\" width=\"{width:.2}\" height=\"{height:.2}\" \
+             preserveAspectRatio=\"none\" href=\"data:image/png;base64,{}\"/>",
+            base64(&png)
+        )
+        .unwrap();
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn tiny_colour(colour: Color32) -> tiny_skia::Color {
+    let [r, g, b, a] = colour.to_srgba_unmultiplied();
+    tiny_skia::Color::from_rgba8(r, g, b, a)
+}
+
+fn image_pixmap(image: &ColorImage) -> Option<tiny_skia::Pixmap> {
+    let [width, height] = image.size;
+    let mut pixmap = tiny_skia::Pixmap::new(width as u32, height as u32)?;
+    for (pixel, colour) in pixmap.pixels_mut().iter_mut().zip(&image.pixels) {
+        // Both are premultiplied.
+        let [r, g, b, a] = colour.to_array();
+        *pixel = tiny_skia::PremultipliedColorU8::from_rgba(r, g, b, a)?;
+    }
+    Some(pixmap)
+}
+
+struct PngCanvas {
+    pixmap: tiny_skia::Pixmap,
+    /// PNG pixels per SVG pixel.
+    scale: f32,
+    font: FontVec,
+}
+
+impl PngCanvas {
+    fn new(width: f32, height: f32, scale: f32) -> Result<Self, Box<dyn Error>> {
+        let mut pixmap = tiny_skia::Pixmap::new((width * scale) as u32, (height * scale) as u32)
+            .ok_or("invalid figure size")?;
+        pixmap.fill(tiny_skia::Color::WHITE);
+        // The proportional font egui ships with, so that figures match the app on every
+        // platform.
+        let fonts = egui::FontDefinitions::default();
+        let data = fonts.font_data.get("Ubuntu-Light").ok_or("missing font")?;
+        let font = FontVec::try_from_vec(data.font.to_vec())?;
+        Ok(Self {
+            pixmap,
+            scale,
+            font,
+        })
+    }
+
+    fn path(points: &[[f32; 2]], closed: bool) -> Option<tiny_skia::Path> {
+        let mut builder = tiny_skia::PathBuilder::new();
+        let (first, rest) = points.split_first()?;
+        builder.move_to(first[0], first[1]);
+        for p in rest {
+            builder.line_to(p[0], p[1]);
+        }
+        if closed {
+            builder.close();
+        }
+        builder.finish()
+    }
+
+    fn paint(colour: Color32) -> tiny_skia::Paint<'static> {
+        let mut paint = tiny_skia::Paint::default();
+        paint.set_color(tiny_colour(colour));
+        paint.anti_alias = true;
+        paint
+    }
+
+    fn transform(&self) -> tiny_skia::Transform {
+        tiny_skia::Transform::from_scale(self.scale, self.scale)
+    }
+}
+
+impl Canvas for PngCanvas {
+    fn polyline(
+        &mut self,
+        points: &[[f32; 2]],
+        colour: Color32,
+        width: f32,
+        dashed: bool,
+        closed: bool,
+    ) {
+        if let Some(path) = Self::path(points, closed) {
+            let stroke = tiny_skia::Stroke {
+                width,
+                line_join: tiny_skia::LineJoin::Round,
+                dash: if dashed {
+                    tiny_skia::StrokeDash::new(vec![6., 4.], 0.)
+                } else {
+                    None
+                },
+                ..Default::default()
+            };
+            let transform = self.transform();
+            self.pixmap
+                .stroke_path(&path, &Self::paint(colour), &stroke, transform, None);
+        }
+    }
+
+    fn polygon(&mut self, points: &[[f32; 2]], fill: Color32) {
+        if let Some(path) = Self::path(points, true) {
+            let transform = self.transform();
+            self.pixmap.fill_path(
+                &path,
+                &Self::paint(fill),
+                tiny_skia::FillRule::Winding,
+                transform,
+                None,
+            );
+        }
+    }
+
+    fn circle(&mut self, centre: [f32; 2], radius: f32, fill: Color32) {
+        if let Some(path) = tiny_skia::PathBuilder::from_circle(centre[0], centre[1], radius) {
+            let transform = self.transform();
+            self.pixmap.fill_path(
+                &path,
+                &Self::paint(fill),
+                tiny_skia::FillRule::Winding,
+                transform,
+                None,
+            );
+        }
+    }
+
+    fn text(&mut self, position: [f32; 2], text: &str, size: f32, anchor: Align2, vertical: bool) {
+        let font = self.font.as_scaled(PxScale::from(size * self.scale));
+        let mut glyphs = vec![];
+        let mut x = 0.;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                x += font.kern(previous, id);
+            }
+            glyphs
+                .push(id.with_scale_and_position(font.scale(), ab_glyph::point(x, font.ascent())));
+            x += font.h_advance(id);
+            previous = Some(id);
+        }
+        let width = x.ceil().max(1.);
+        let height = (font.ascent() - font.descent()).ceil().max(1.);
+
+        // Render into a separate pixmap, then place (and rotate) it.
+        let Some(mut label) = tiny_skia::Pixmap::new(width as u32, height as u32) else {
+            return;
+        };
+        let label_width = label.width() as i32;
+        let label_height = label.height() as i32;
+        let pixels = label.pixels_mut();
+        for glyph in glyphs {
+            if let Some(outline) = self.font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i32 + gx as i32;
+                    let py = bounds.min.y as i32 + gy as i32;
+                    if (0..label_width).contains(&px) && (0..label_height).contains(&py) {
+                        let alpha = (coverage.clamp(0., 1.) * 255.) as u8;
+                        let pixel = &mut pixels[(py * label_width + px) as usize];
+                        if alpha > pixel.alpha() {
+                            *pixel =
+                                tiny_skia::PremultipliedColorU8::from_rgba(0, 0, 0, alpha).unwrap();
+                        }
+                    }
+                });
+            }
+        }
+
+        let offset = |align: egui::Align, length: f32| match align {
+            egui::Align::Min => 0.,
+            egui::Align::Center => -length / 2.,
+            egui::Align::Max => -length,
+        };
+        let [x, y] = position.map(|p| p * self.scale);
+        let transform = if vertical {
+            tiny_skia::Transform::from_translate(
+                offset(anchor.x(), width),
+                offset(anchor.y(), height),
+            )
+            .post_concat(tiny_skia::Transform::from_rotate(-90.))
+            .post_translate(x, y)
+        } else {
+            tiny_skia::Transform::from_translate(
+                x + offset(anchor.x(), width),
+                y + offset(anchor.y(), height),
+            )
+        };
+        self.pixmap.draw_pixmap(
+            0,
+            0,
+            label.as_ref(),
+            &tiny_skia::PixmapPaint::default(),
+            transform,
+            None,
+        );
+    }
+
+    fn image(&mut self, rect: [f32; 4], image: &ColorImage) {
+        let Some(pixmap) = image_pixmap(image) else {
+            return;
+        };
+        let [x, y, width, height] = rect;
+        let transform = tiny_skia::Transform::from_scale(
+            width / pixmap.width() as f32,
+            height / pixmap.height() as f32,
+        )
+        .post_translate(x, y)
+        .post_scale(self.scale, self.scale);
+        let paint = tiny_skia::PixmapPaint {
+            quality: tiny_skia::FilterQuality::Bilinear,
+            ..Default::default()
+        };
+        self.pixmap
+            .draw_pixmap(0, 0, pixmap.as_ref(), &paint, transform, None);
+    }
+}
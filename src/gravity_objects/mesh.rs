@@ -0,0 +1,241 @@
+use super::{Cuboid, GravityObject, Sphere};
+use ndarray::Axis;
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
+
+/// Closed triangulated surface of a body. Triangles are wound anticlockwise when viewed
+/// from outside.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct TriangleMesh {
+    pub vertices: Vec<[f64; 3]>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    /// Outward unit normal of a triangle.
+    pub fn normal(&self, triangle: &[usize; 3]) -> [f64; 3] {
+        let [a, b, c] = triangle.map(|i| self.vertices[i]);
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = (n[0].powi(2) + n[1].powi(2) + n[2].powi(2)).sqrt();
+        if length > 0. {
+            n.map(|n| n / length)
+        } else {
+            n
+        }
+    }
+
+    /// Enclosed volume, negative if the triangles are wound inwards.
+    pub fn volume(&self) -> f64 {
+        self.triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|i| self.vertices[i]);
+                dot(a, cross(b, c)) / 6.
+            })
+            .sum()
+    }
+
+    /// Centre of the enclosed volume, the mean vertex position if the volume is zero.
+    pub fn centroid(&self) -> [f64; 3] {
+        let mut moment = [0.; 3];
+        let mut volume = 0.;
+        for triangle in &self.triangles {
+            let [a, b, c] = triangle.map(|i| self.vertices[i]);
+            let v = dot(a, cross(b, c)) / 6.;
+            volume += v;
+            for k in 0..3 {
+                moment[k] += v * (a[k] + b[k] + c[k]) / 4.;
+            }
+        }
+        if volume.abs() > f64::EPSILON {
+            moment.map(|m| m / volume)
+        } else {
+            let n = self.vertices.len().max(1) as f64;
+            let mut mean = [0.; 3];
+            for vertex in &self.vertices {
+                for k in 0..3 {
+                    mean[k] += vertex[k] / n;
+                }
+            }
+            mean
+        }
+    }
+
+    /// Minimum and maximum corners of the bounding box.
+    pub fn bounds(&self) -> [[f64; 3]; 2] {
+        let mut bounds = [[f64::INFINITY; 3], [f64::NEG_INFINITY; 3]];
+        for vertex in &self.vertices {
+            for k in 0..3 {
+                bounds[0][k] = bounds[0][k].min(vertex[k]);
+                bounds[1][k] = bounds[1][k].max(vertex[k]);
+            }
+        }
+        bounds
+    }
+
+    /// Merge vertices closer than `tolerance` and drop triangles that collapse. Formats
+    /// such as STL repeat the vertices of every triangle.
+    pub fn weld(&mut self, tolerance: f64) {
+        let key = |v: [f64; 3]| v.map(|x| (x / tolerance).round() as i64);
+        let mut index: HashMap<[i64; 3], usize> = HashMap::new();
+        let mut vertices = vec![];
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        for vertex in &self.vertices {
+            let i = *index.entry(key(*vertex)).or_insert_with(|| {
+                vertices.push(*vertex);
+                vertices.len() - 1
+            });
+            remap.push(i);
+        }
+        self.vertices = vertices;
+        self.triangles = self
+            .triangles
+            .iter()
+            .map(|triangle| triangle.map(|i| remap[i]))
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .collect();
+    }
+
+    /// True if every edge is shared by exactly two triangles with opposite winding.
+    pub fn is_closed(&self) -> bool {
+        let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+        for [a, b, c] in &self.triangles {
+            for edge in [(*a, *b), (*b, *c), (*c, *a)] {
+                *edges.entry(edge).or_default() += 1;
+            }
+        }
+        !self.triangles.is_empty()
+            && edges
+                .iter()
+                .all(|(&(i, j), &count)| count == 1 && edges.get(&(j, i)) == Some(&1))
+    }
+
+    /// Reverse the winding of every triangle.
+    pub fn flip(&mut self) {
+        for triangle in self.triangles.iter_mut() {
+            triangle.swap(1, 2);
+        }
+    }
+
+    /// Edges on the boundary or between triangles whose normals differ by more than
+    /// `angle` radians, i.e. the edges worth drawing.
+    pub fn feature_edges(&self, angle: f64) -> Vec<[usize; 2]> {
+        let mut normals: HashMap<(usize, usize), Vec<[f64; 3]>> = HashMap::new();
+        for triangle in &self.triangles {
+            let normal = self.normal(triangle);
+            let [a, b, c] = *triangle;
+            for (i, j) in [(a, b), (b, c), (c, a)] {
+                normals
+                    .entry((i.min(j), i.max(j)))
+                    .or_default()
+                    .push(normal);
+            }
+        }
+        let cos_angle = angle.cos();
+        normals
+            .into_iter()
+            .filter(|(_, normals)| match normals.as_slice() {
+                [n1, n2] => dot(*n1, *n2) < cos_angle,
+                _ => true,
+            })
+            .map(|((i, j), _)| [i, j])
+            .collect()
+    }
+}
+
+pub(super) fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(super) fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Triangles of the six cuboid faces, indexed into `Cuboid::vertices_axis_aligned`.
+const CUBOID_TRIANGLES: [[usize; 3]; 12] = [
+    [0, 1, 2],
+    [0, 2, 3],
+    [4, 5, 6],
+    [4, 6, 7],
+    [0, 4, 7],
+    [0, 7, 1],
+    [3, 2, 6],
+    [3, 6, 5],
+    [0, 3, 5],
+    [0, 5, 4],
+    [1, 7, 6],
+    [1, 6, 2],
+];
+
+impl Cuboid {
+    pub fn mesh(&self) -> TriangleMesh {
+        let verts = self.vertices_rotated();
+        TriangleMesh {
+            vertices: verts
+                .axis_iter(Axis(0))
+                .map(|v| [v[0], v[1], v[2]])
+                .collect(),
+            triangles: CUBOID_TRIANGLES.to_vec(),
+        }
+    }
+}
+
+impl Sphere {
+    /// UV sphere with `n_lat` rings of latitude and `n_lon` segments of longitude.
+    pub fn mesh(&self, n_lat: usize, n_lon: usize) -> TriangleMesh {
+        let n_lat = n_lat.max(2);
+        let n_lon = n_lon.max(3);
+        let centre = [self.x_centroid, self.y_centroid, self.z_centroid];
+        let mut vertices = vec![[centre[0], centre[1], centre[2] + self.radius]];
+        for i in 1..n_lat {
+            let theta = PI * i as f64 / n_lat as f64;
+            for j in 0..n_lon {
+                let phi = TAU * j as f64 / n_lon as f64;
+                vertices.push([
+                    centre[0] + self.radius * theta.sin() * phi.cos(),
+                    centre[1] + self.radius * theta.sin() * phi.sin(),
+                    centre[2] + self.radius * theta.cos(),
+                ]);
+            }
+        }
+        vertices.push([centre[0], centre[1], centre[2] - self.radius]);
+        let bottom = vertices.len() - 1;
+        let ring = |i: usize, j: usize| 1 + (i - 1) * n_lon + j % n_lon;
+
+        let mut triangles = vec![];
+        for j in 0..n_lon {
+            triangles.push([0, ring(1, j), ring(1, j + 1)]);
+            for i in 1..n_lat - 1 {
+                let (a, b) = (ring(i, j), ring(i, j + 1));
+                let (c, d) = (ring(i + 1, j + 1), ring(i + 1, j));
+                triangles.push([a, d, c]);
+                triangles.push([a, c, b]);
+            }
+            triangles.push([ring(n_lat - 1, j), bottom, ring(n_lat - 1, j + 1)]);
+        }
+        TriangleMesh {
+            vertices,
+            triangles,
+        }
+    }
+}
+
+impl GravityObject {
+    pub fn mesh(&self) -> TriangleMesh {
+        match self {
+            GravityObject::Cuboid(cuboid) => cuboid.mesh(),
+            GravityObject::Sphere(sphere) => sphere.mesh(12, 24),
+            GravityObject::Polyhedron(polyhedron) => polyhedron.mesh.clone(),
+        }
+    }
+}
@@ -3,14 +3,27 @@ use egui::{Color32, Ui, Vec2};
 use ndarray::prelude::*;
 use std::f64::consts::PI;
 use std::fmt;
+use std::ops::{Add, AddAssign};
 
-const G: f64 = 6.674e-11;
+mod mesh;
+mod polyhedron;
+
+pub use mesh::TriangleMesh;
+pub use polyhedron::Polyhedron;
+
+pub const G: f64 = 6.674e-11;
+/// Scaling from SI to display units: µGal·m for potential, µGal for the gravity vector
+/// (positive downwards) and Eötvös for the gradient tensor.
+const U_SCALING: f64 = 1E8;
+pub const G_SCALING: f64 = -1E8;
+pub const GG_SCALING: f64 = 1E9;
 
 /// Required methods to define a new gravity object, to be used within a gravity model.
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
 pub enum GravityObject {
     Cuboid(Cuboid),
     Sphere(Sphere),
+    Polyhedron(Polyhedron),
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
@@ -20,119 +33,262 @@ pub struct GravityModelObject {
     pub id: u128,
     pub colour: Color32,
     pub is_selected: bool,
+    /// Hidden objects are not drawn or picked in the views but still contribute to the data.
+    #[serde(default)]
+    pub is_hidden: bool,
+    /// Locked objects cannot be moved, resized, rotated or deleted.
+    #[serde(default)]
+    pub is_locked: bool,
 }
 
-pub trait InputUI {
-    fn ui(&mut self, ui: &mut Ui);
-}
-
-impl InputUI for GravityModelObject {
-    fn ui(&mut self, ui: &mut Ui) {
-        ui.horizontal(|ui| {
-            ui.label("Name: ");
-            ui.text_edit_singleline(&mut self.name);
-        });
-        ui.horizontal(|ui| {
-            ui.label("Colour: ");
-            ui.color_edit_button_srgba(&mut self.colour);
-        });
+impl GravityObject {
+    pub fn as_calc(&self) -> &dyn GravityCalc {
+        match self {
+            GravityObject::Cuboid(cuboid) => cuboid,
+            GravityObject::Sphere(sphere) => sphere,
+            GravityObject::Polyhedron(polyhedron) => polyhedron,
+        }
+    }
 
-        match &mut self.object {
+    pub fn set_centre(&mut self, centre: [f64; 3]) {
+        match self {
             GravityObject::Cuboid(cuboid) => {
-                egui::CollapsingHeader::new("Centroid").show(ui, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("x");
-                        ui.add(egui::Slider::new(&mut cuboid.x_centroid, -50.0..=50.0).text("m"));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("y");
-                        ui.add(egui::Slider::new(&mut cuboid.y_centroid, -50.0..=50.0).text("m"));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("z");
-                        ui.add(egui::Slider::new(&mut cuboid.z_centroid, -25.0..=25.0).text("m"));
-                    });
-                });
-
-                egui::CollapsingHeader::new("Rotation").show(ui, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("x");
-                        ui.add(
-                            egui::Slider::new(&mut cuboid.x_rotation, -PI / 2.0..=PI / 2.)
-                                .text("rad"),
-                        );
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("y");
-                        ui.add(
-                            egui::Slider::new(&mut cuboid.y_rotation, -PI / 2.0..=PI / 2.)
-                                .text("rad"),
-                        );
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("z");
-                        ui.add(
-                            egui::Slider::new(&mut cuboid.z_rotation, -PI / 2.0..=PI / 2.)
-                                .text("rad"),
-                        );
-                    });
-                });
-
-                egui::CollapsingHeader::new("Size").show(ui, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("x");
-                        ui.add(egui::Slider::new(&mut cuboid.x_length, 0.1..=100.0).text("m"));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("y");
-                        ui.add(egui::Slider::new(&mut cuboid.y_length, 0.1..=100.0).text("m"));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("z");
-                        ui.add(egui::Slider::new(&mut cuboid.z_length, 0.1..=25.0).text("m"));
-                    });
-                });
-                egui::CollapsingHeader::new("Density").show(ui, |ui| {
-                    ui.add(egui::Slider::new(&mut cuboid.density, -3000.0..=22590.).text("kg/m^3"));
-                    ui.radio_value(&mut cuboid.density, -1800., "Soil Void");
-                    ui.radio_value(&mut cuboid.density, 2000., "Concrete");
-                    ui.radio_value(&mut cuboid.density, 11340., "Lead");
-                    ui.radio_value(&mut cuboid.density, 19300., "Tungsten");
-                });
+                [cuboid.x_centroid, cuboid.y_centroid, cuboid.z_centroid] = centre
             }
             GravityObject::Sphere(sphere) => {
-                egui::CollapsingHeader::new("Centroid").show(ui, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("x");
-                        ui.add(egui::Slider::new(&mut sphere.x_centroid, -50.0..=50.0).text("m"));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("y");
-                        ui.add(egui::Slider::new(&mut sphere.y_centroid, -50.0..=50.0).text("m"));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("z");
-                        ui.add(egui::Slider::new(&mut sphere.z_centroid, -25.0..=25.0).text("m"));
-                    });
-                });
-                egui::CollapsingHeader::new("Radius").show(ui, |ui| {
-                    ui.add(egui::Slider::new(&mut sphere.radius, 0.1..=100.0).text("m"));
-                });
-                egui::CollapsingHeader::new("Density").show(ui, |ui| {
-                    ui.add(egui::Slider::new(&mut sphere.density, -3000.0..=22590.).text("kg/m^3"));
-                    ui.radio_value(&mut sphere.density, -1800., "Soil Void");
-                    ui.radio_value(&mut sphere.density, 2000., "Concrete");
-                    ui.radio_value(&mut sphere.density, 11340., "Lead");
-                    ui.radio_value(&mut sphere.density, 19300., "Tungsten");
-                });
+                [sphere.x_centroid, sphere.y_centroid, sphere.z_centroid] = centre
+            }
+            GravityObject::Polyhedron(polyhedron) => {
+                let current = polyhedron.mesh.centroid();
+                polyhedron.translate([
+                    centre[0] - current[0],
+                    centre[1] - current[1],
+                    centre[2] - current[2],
+                ]);
+            }
+        }
+    }
+
+    /// Rotate about the object centre, spheres are unchanged.
+    pub fn rotate(&mut self, rotation: &Array2<f64>) {
+        match self {
+            GravityObject::Cuboid(cuboid) => cuboid.rotate(rotation),
+            GravityObject::Polyhedron(polyhedron) => polyhedron.rotate(rotation),
+            GravityObject::Sphere(_) => {}
+        }
+    }
+}
+
+/// Editable parameter of a gravity object, in display units (metres, degrees, kg/m^3).
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Parameter {
+    XCentroid,
+    YCentroid,
+    ZCentroid,
+    XRotation,
+    YRotation,
+    ZRotation,
+    XLength,
+    YLength,
+    ZLength,
+    Radius,
+    Density,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 11] = [
+        Parameter::XCentroid,
+        Parameter::YCentroid,
+        Parameter::ZCentroid,
+        Parameter::XRotation,
+        Parameter::YRotation,
+        Parameter::ZRotation,
+        Parameter::XLength,
+        Parameter::YLength,
+        Parameter::ZLength,
+        Parameter::Radius,
+        Parameter::Density,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Parameter::XCentroid | Parameter::XRotation | Parameter::XLength => "x",
+            Parameter::YCentroid | Parameter::YRotation | Parameter::YLength => "y",
+            Parameter::ZCentroid | Parameter::ZRotation | Parameter::ZLength => "z",
+            Parameter::Radius => "radius",
+            Parameter::Density => "density",
+        }
+    }
+
+    /// Label with the quantity, e.g. "x centre".
+    pub fn name(&self) -> String {
+        let label = self.label();
+        match self {
+            Parameter::XCentroid | Parameter::YCentroid | Parameter::ZCentroid => {
+                format!("{label} centre")
+            }
+            Parameter::XRotation | Parameter::YRotation | Parameter::ZRotation => {
+                format!("{label} rotation")
+            }
+            Parameter::XLength | Parameter::YLength | Parameter::ZLength => {
+                format!("{label} length")
+            }
+            Parameter::Radius | Parameter::Density => label.to_string(),
+        }
+    }
+
+    pub fn units(&self) -> &'static str {
+        match self {
+            Parameter::XRotation | Parameter::YRotation | Parameter::ZRotation => "°",
+            Parameter::Density => "kg/m^3",
+            _ => "m",
+        }
+    }
+
+    /// Value of the parameter, None if the object does not have it.
+    pub fn get(&self, object: &GravityObject) -> Option<f64> {
+        match (self, object) {
+            (Parameter::XCentroid, _) => Some(object.as_calc().centre()[0]),
+            (Parameter::YCentroid, _) => Some(object.as_calc().centre()[1]),
+            (Parameter::ZCentroid, _) => Some(object.as_calc().centre()[2]),
+            (Parameter::XRotation, GravityObject::Cuboid(c)) => Some(c.x_rotation.to_degrees()),
+            (Parameter::YRotation, GravityObject::Cuboid(c)) => Some(c.y_rotation.to_degrees()),
+            (Parameter::ZRotation, GravityObject::Cuboid(c)) => Some(c.z_rotation.to_degrees()),
+            (Parameter::XLength, GravityObject::Cuboid(c)) => Some(c.x_length),
+            (Parameter::YLength, GravityObject::Cuboid(c)) => Some(c.y_length),
+            (Parameter::ZLength, GravityObject::Cuboid(c)) => Some(c.z_length),
+            (Parameter::XLength, GravityObject::Polyhedron(p)) => Some(p.size()[0]),
+            (Parameter::YLength, GravityObject::Polyhedron(p)) => Some(p.size()[1]),
+            (Parameter::ZLength, GravityObject::Polyhedron(p)) => Some(p.size()[2]),
+            (Parameter::Radius, GravityObject::Sphere(s)) => Some(s.radius),
+            (Parameter::Density, GravityObject::Cuboid(c)) => Some(c.density),
+            (Parameter::Density, GravityObject::Sphere(s)) => Some(s.density),
+            (Parameter::Density, GravityObject::Polyhedron(p)) => Some(p.density),
+            _ => None,
+        }
+    }
+
+    pub fn set(&self, object: &mut GravityObject, value: f64) {
+        match (self, object) {
+            (
+                Parameter::XCentroid | Parameter::YCentroid | Parameter::ZCentroid,
+                object @ GravityObject::Polyhedron(_),
+            ) => {
+                let mut centre = object.as_calc().centre();
+                centre[*self as usize] = value;
+                object.set_centre([centre[0], centre[1], centre[2]]);
+            }
+            (Parameter::XCentroid, GravityObject::Cuboid(c)) => c.x_centroid = value,
+            (Parameter::YCentroid, GravityObject::Cuboid(c)) => c.y_centroid = value,
+            (Parameter::ZCentroid, GravityObject::Cuboid(c)) => c.z_centroid = value,
+            (Parameter::XCentroid, GravityObject::Sphere(s)) => s.x_centroid = value,
+            (Parameter::YCentroid, GravityObject::Sphere(s)) => s.y_centroid = value,
+            (Parameter::ZCentroid, GravityObject::Sphere(s)) => s.z_centroid = value,
+            (Parameter::XRotation, GravityObject::Cuboid(c)) => c.x_rotation = value.to_radians(),
+            (Parameter::YRotation, GravityObject::Cuboid(c)) => c.y_rotation = value.to_radians(),
+            (Parameter::ZRotation, GravityObject::Cuboid(c)) => c.z_rotation = value.to_radians(),
+            (Parameter::XLength, GravityObject::Cuboid(c)) => c.x_length = value,
+            (Parameter::YLength, GravityObject::Cuboid(c)) => c.y_length = value,
+            (Parameter::ZLength, GravityObject::Cuboid(c)) => c.z_length = value,
+            (Parameter::Radius, GravityObject::Sphere(s)) => s.radius = value,
+            (Parameter::Density, GravityObject::Cuboid(c)) => c.density = value,
+            (Parameter::Density, GravityObject::Sphere(s)) => s.density = value,
+            (Parameter::Density, GravityObject::Polyhedron(p)) => p.density = value,
+            (
+                Parameter::XLength | Parameter::YLength | Parameter::ZLength,
+                GravityObject::Polyhedron(p),
+            ) => {
+                let axis = *self as usize - Parameter::XLength as usize;
+                let size = p.size()[axis];
+                if size > 0. && value > 0. {
+                    let mut factors = [1.; 3];
+                    factors[axis] = value / size;
+                    p.scale(factors);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Limits of the object parameters in the editor, stored with each model so that
+/// kilometre and centimetre scale models can both be edited.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ParameterRanges {
+    /// Horizontal centroid position in metres.
+    pub horizontal: [f64; 2],
+    /// Vertical centroid position in metres.
+    pub vertical: [f64; 2],
+    /// Lengths and radius in metres.
+    pub size: [f64; 2],
+    /// Density contrast in kg/m^3.
+    pub density: [f64; 2],
+}
+
+impl Default for ParameterRanges {
+    fn default() -> Self {
+        Self {
+            horizontal: [-50., 50.],
+            vertical: [-25., 25.],
+            size: [0.1, 100.],
+            density: [-3000., 22590.],
+        }
+    }
+}
+
+impl ParameterRanges {
+    pub fn range(&self, parameter: &Parameter) -> [f64; 2] {
+        match parameter {
+            Parameter::XCentroid | Parameter::YCentroid => self.horizontal,
+            Parameter::ZCentroid => self.vertical,
+            Parameter::XRotation | Parameter::YRotation | Parameter::ZRotation => [-180., 180.],
+            Parameter::XLength | Parameter::YLength | Parameter::ZLength | Parameter::Radius => {
+                self.size
             }
+            Parameter::Density => self.density,
         }
     }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        egui::Grid::new("parameter_ranges").show(ui, |ui| {
+            for (label, range, units) in [
+                ("horizontal", &mut self.horizontal, "m"),
+                ("vertical", &mut self.vertical, "m"),
+                ("size", &mut self.size, "m"),
+                ("density", &mut self.density, "kg/m^3"),
+            ] {
+                ui.label(label);
+                let speed = ((range[1] - range[0]).abs() / 1000.).max(1e-4);
+                ui.add(
+                    egui::DragValue::new(&mut range[0])
+                        .speed(speed)
+                        .prefix("min "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut range[1])
+                        .speed(speed)
+                        .prefix("max ")
+                        .suffix(format!(" {units}")),
+                );
+                ui.end_row();
+                if range[1] < range[0] {
+                    range.swap(0, 1);
+                }
+            }
+        });
+        self.size[0] = self.size[0].max(1e-6);
+        self.size[1] = self.size[1].max(self.size[0]);
+    }
 }
 
 pub trait GravityCalc {
     fn calculate(&self, data_type: &DataType, points: &Array2<f64>) -> Array1<f64>;
 
+    fn field(&self, points: &Array2<f64>) -> Field;
+
+    fn potential(&self, position: &Array1<f64>) -> f64;
+
     fn g(&self, position: &Array1<f64>) -> Array1<f64>;
 
     fn gg(&self, position: &Array1<f64>) -> Array2<f64>;
@@ -164,6 +320,8 @@ pub trait GravityCalc {
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum DataType {
+    /// Gravitational potential U.
+    U,
     Gx,
     Gy,
     Gz,
@@ -173,6 +331,172 @@ pub enum DataType {
     Gyy,
     Gyz,
     Gzz,
+    /// Horizontal gradient magnitude of gz, sqrt(Gxz^2 + Gyz^2).
+    HorizontalGradient,
+    /// Total gradient (3D analytic signal) of gz, sqrt(Gxz^2 + Gyz^2 + Gzz^2).
+    TotalGradient,
+    /// Tensor invariant I1 (Pedersen & Rasmussen, 1990), the sum of the principal minors.
+    I1,
+    /// Tensor invariant I2 (Pedersen & Rasmussen, 1990), the determinant of the tensor.
+    I2,
+    /// Dimensionless invariant ratio -(I2/2)^2 / (I1/3)^3, between 0 and 1.
+    I,
+    /// Curvature component (Gxx - Gyy) / 2.
+    Guv,
+    /// Curvature component 2Gxy.
+    Gne,
+}
+
+impl DataType {
+    pub const ALL: [DataType; 17] = [
+        DataType::U,
+        DataType::Gx,
+        DataType::Gy,
+        DataType::Gz,
+        DataType::Gxx,
+        DataType::Gxy,
+        DataType::Gxz,
+        DataType::Gyy,
+        DataType::Gyz,
+        DataType::Gzz,
+        DataType::HorizontalGradient,
+        DataType::TotalGradient,
+        DataType::I1,
+        DataType::I2,
+        DataType::I,
+        DataType::Guv,
+        DataType::Gne,
+    ];
+
+    /// Linear components can be summed object by object, derived ones must be
+    /// computed from the combined field.
+    pub fn is_linear(&self) -> bool {
+        !matches!(
+            self,
+            DataType::HorizontalGradient
+                | DataType::TotalGradient
+                | DataType::I1
+                | DataType::I2
+                | DataType::I
+        )
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DataType::U => "U",
+            DataType::Gx => "gx",
+            DataType::Gy => "gy",
+            DataType::Gz => "gz",
+            DataType::Gxx => "gxx",
+            DataType::Gxy => "gxy",
+            DataType::Gxz => "gxz",
+            DataType::Gyy => "gyy",
+            DataType::Gyz => "gyz",
+            DataType::Gzz => "gzz",
+            DataType::HorizontalGradient => "horizontal gradient",
+            DataType::TotalGradient => "total gradient",
+            DataType::I1 => "I1",
+            DataType::I2 => "I2",
+            DataType::I => "I",
+            DataType::Guv => "guv",
+            DataType::Gne => "2gxy",
+        }
+    }
+
+    pub fn units(&self) -> &'static str {
+        match self {
+            DataType::U => "µGal m",
+            DataType::Gx | DataType::Gy | DataType::Gz => "µGal",
+            DataType::I1 => "E^2",
+            DataType::I2 => "E^3",
+            DataType::I => "",
+            _ => "E",
+        }
+    }
+}
+
+/// Potential, gravity vector and gradient tensor at a set of points, in display units.
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub u: Array1<f64>,
+    pub g: Array2<f64>,
+    pub gg: Array3<f64>,
+}
+
+impl Field {
+    pub fn zeros(n_points: usize) -> Self {
+        Self {
+            u: Array1::zeros(n_points),
+            g: Array2::zeros((n_points, 3)),
+            gg: Array3::zeros((n_points, 3, 3)),
+        }
+    }
+
+    pub fn data(&self, data_type: &DataType) -> Array1<f64> {
+        let gg = |i: usize, j: usize| self.gg.slice(s![.., i, j]).to_owned();
+        match data_type {
+            DataType::U => self.u.clone(),
+            DataType::Gx => self.g.column(0).to_owned(),
+            DataType::Gy => self.g.column(1).to_owned(),
+            DataType::Gz => self.g.column(2).to_owned(),
+            DataType::Gxx => gg(0, 0),
+            DataType::Gxy => gg(0, 1),
+            DataType::Gxz => gg(0, 2),
+            DataType::Gyy => gg(1, 1),
+            DataType::Gyz => gg(1, 2),
+            DataType::Gzz => gg(2, 2),
+            DataType::HorizontalGradient => {
+                (gg(0, 2).mapv(|v| v.powi(2)) + gg(1, 2).mapv(|v| v.powi(2))).mapv(f64::sqrt)
+            }
+            DataType::TotalGradient => (gg(0, 2).mapv(|v| v.powi(2))
+                + gg(1, 2).mapv(|v| v.powi(2))
+                + gg(2, 2).mapv(|v| v.powi(2)))
+            .mapv(f64::sqrt),
+            DataType::I1 => Array1::from_iter(self.gg.outer_iter().map(|t| invariant_i1(&t))),
+            DataType::I2 => Array1::from_iter(self.gg.outer_iter().map(|t| invariant_i2(&t))),
+            DataType::I => Array1::from_iter(self.gg.outer_iter().map(|t| {
+                let i1 = invariant_i1(&t);
+                let i2 = invariant_i2(&t);
+                if i1 == 0. {
+                    0.
+                } else {
+                    -(i2 / 2.).powi(2) / (i1 / 3.).powi(3)
+                }
+            })),
+            DataType::Guv => (gg(0, 0) - gg(1, 1)) / 2.,
+            DataType::Gne => gg(0, 1) * 2.,
+        }
+    }
+}
+
+impl Add for Field {
+    type Output = Field;
+
+    fn add(mut self, rhs: Field) -> Field {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for Field {
+    fn add_assign(&mut self, rhs: Field) {
+        self.u = &self.u + &rhs.u;
+        self.g = &self.g + &rhs.g;
+        self.gg = &self.gg + &rhs.gg;
+    }
+}
+
+fn invariant_i1(t: &ArrayView2<'_, f64>) -> f64 {
+    t[[0, 0]] * t[[1, 1]] + t[[1, 1]] * t[[2, 2]] + t[[0, 0]] * t[[2, 2]]
+        - t[[0, 1]].powi(2)
+        - t[[1, 2]].powi(2)
+        - t[[0, 2]].powi(2)
+}
+
+fn invariant_i2(t: &ArrayView2<'_, f64>) -> f64 {
+    t[[0, 0]] * (t[[1, 1]] * t[[2, 2]] - t[[1, 2]].powi(2))
+        + t[[0, 1]] * (t[[1, 2]] * t[[0, 2]] - t[[0, 1]] * t[[2, 2]])
+        + t[[0, 2]] * (t[[0, 1]] * t[[1, 2]] - t[[0, 2]] * t[[1, 1]])
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
@@ -200,10 +524,16 @@ impl GravityCalc for Sphere {
     fn calculate(&self, data_type: &DataType, points: &Array2<f64>) -> Array1<f64> {
         let mut data: Array1<f64> = Array1::zeros(points.len_of(Axis(0)));
         let scaling = match data_type {
+            DataType::U => U_SCALING,
             DataType::Gx | DataType::Gy | DataType::Gz => -1E8,
             _ => 1E9,
         };
         match data_type {
+            DataType::U => {
+                for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+                    data[i] += self.potential(&point.to_owned())
+                }
+            }
             DataType::Gx => {
                 for (i, point) in points.axis_iter(Axis(0)).enumerate() {
                     data[i] += self.gx(&point.to_owned())
@@ -249,16 +579,54 @@ impl GravityCalc for Sphere {
                     data[i] += self.gzz(&point.to_owned())
                 }
             }
+            DataType::Guv => {
+                for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+                    data[i] += (self.gxx(&point.to_owned()) - self.gyy(&point.to_owned())) / 2.
+                }
+            }
+            DataType::Gne => {
+                for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+                    data[i] += 2. * self.gxy(&point.to_owned())
+                }
+            }
+            _ => return self.field(points).data(data_type),
         }
         data * scaling
     }
 
+    fn field(&self, points: &Array2<f64>) -> Field {
+        let mut field = Field::zeros(points.len_of(Axis(0)));
+        for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+            let point = point.to_owned();
+            field.u[i] = self.potential(&point) * U_SCALING;
+            field.g.row_mut(i).assign(&(self.g(&point) * G_SCALING));
+            field
+                .gg
+                .index_axis_mut(Axis(0), i)
+                .assign(&(self.gg(&point) * GG_SCALING));
+        }
+        field
+    }
+
+    fn potential(&self, position: &Array1<f64>) -> f64 {
+        let p_dash: Array1<f64> = position * (1. + 1e-7) - self.centre();
+        let r = p_dash.mapv(|p_dash| p_dash.powi(2)).sum().sqrt();
+        G * self.mass() / r
+    }
+
     fn g(&self, position: &Array1<f64>) -> Array1<f64> {
-        todo!()
+        array![self.gx(position), self.gy(position), self.gz(position)]
     }
 
     fn gg(&self, position: &Array1<f64>) -> Array2<f64> {
-        todo!()
+        let gxy = self.gxy(position);
+        let gxz = self.gxz(position);
+        let gyz = self.gyz(position);
+        array![
+            [self.gxx(position), gxy, gxz],
+            [gxy, self.gyy(position), gyz],
+            [gxz, gyz, self.gzz(position)]
+        ]
     }
 
     fn gx(&self, position: &Array1<f64>) -> f64 {
@@ -303,11 +671,11 @@ impl GravityCalc for Sphere {
         let p_dash: Array1<f64> = position * (1. + 1e-7) - self.centre();
         // Only fetch relevant values once
         let r = p_dash.mapv(|p_dash| p_dash.powi(2)).sum();
-        // let x = p_dash[0];
+        let x = p_dash[0];
         let y = p_dash[1];
-        let z = p_dash[2];
+        // let z = p_dash[2];
         let constant = -(4. / 3.) * PI * G * self.radius.powi(3) * self.density;
-        (-3. * constant * y * z) / (r.powf(5. / 2.))
+        (-3. * constant * x * y) / (r.powf(5. / 2.))
     }
 
     fn gxz(&self, position: &Array1<f64>) -> f64 {
@@ -420,10 +788,16 @@ impl GravityCalc for Cuboid {
             .dot(&rotation_matrix_y(self.y_rotation).dot(&rotation_matrix_z(self.z_rotation)));
         let mut data: Array1<f64> = Array1::zeros(points.len_of(Axis(0)));
         let scaling = match data_type {
+            DataType::U => U_SCALING,
             DataType::Gx | DataType::Gy | DataType::Gz => -1E8,
             _ => 1E9,
         };
         match data_type {
+            DataType::U => {
+                for (i, point) in rotated_points.axis_iter(Axis(0)).enumerate() {
+                    data[i] += self.potential(&point.to_owned())
+                }
+            }
             DataType::Gx => {
                 for (i, point) in rotated_points.axis_iter(Axis(0)).enumerate() {
                     data[i] += self.g(&point.to_owned()).dot(&rotation_matrix)[0]
@@ -475,10 +849,69 @@ impl GravityCalc for Cuboid {
                         .dot(&rotation_matrix)[[2, 2]]
                 }
             }
+            DataType::Guv => {
+                for (i, point) in rotated_points.axis_iter(Axis(0)).enumerate() {
+                    let gg = (rotation_matrix.t().dot(&self.gg(&point.to_owned())))
+                        .dot(&rotation_matrix);
+                    data[i] += (gg[[0, 0]] - gg[[1, 1]]) / 2.
+                }
+            }
+            DataType::Gne => {
+                for (i, point) in rotated_points.axis_iter(Axis(0)).enumerate() {
+                    data[i] += 2.
+                        * (rotation_matrix.t().dot(&self.gg(&point.to_owned())))
+                            .dot(&rotation_matrix)[[0, 1]]
+                }
+            }
+            _ => return self.field(points).data(data_type),
         }
         data * scaling
     }
 
+    fn field(&self, points: &Array2<f64>) -> Field {
+        let rotated_points = (points - self.centre())
+            .dot(&rotation_matrix_z(-self.z_rotation))
+            .dot(&rotation_matrix_y(-self.y_rotation))
+            .dot(&rotation_matrix_x(-self.x_rotation))
+            + self.centre();
+        let rotation_matrix = rotation_matrix_x(self.x_rotation)
+            .dot(&rotation_matrix_y(self.y_rotation).dot(&rotation_matrix_z(self.z_rotation)));
+        let mut field = Field::zeros(points.len_of(Axis(0)));
+        for (i, point) in rotated_points.axis_iter(Axis(0)).enumerate() {
+            let point = point.to_owned();
+            field.u[i] = self.potential(&point) * U_SCALING;
+            field
+                .g
+                .row_mut(i)
+                .assign(&(self.g(&point).dot(&rotation_matrix) * G_SCALING));
+            field.gg.index_axis_mut(Axis(0), i).assign(
+                &((rotation_matrix.t().dot(&self.gg(&point))).dot(&rotation_matrix) * GG_SCALING),
+            );
+        }
+        field
+    }
+
+    fn potential(&self, position: &Array1<f64>) -> f64 {
+        let mut u = 0.;
+        let verts = self.vertices_axis_aligned();
+        for i in 0..8 {
+            let p_dash: Array1<f64> =
+                position * (1. + 1e-7) - verts.index_axis(Axis(0), i).to_owned();
+            // Only fetch relevant values once
+            let r = p_dash.mapv(|p_dash| p_dash.powi(2)).sum().sqrt();
+            let sign = Cuboid::index_order()[i];
+            let x = p_dash[0];
+            let y = p_dash[1];
+            let z = p_dash[2];
+            u += sign
+                * ((x * y * (r + z).ln()) + (y * z * (r + x).ln()) + (z * x * (r + y).ln())
+                    - (x.powi(2) / 2. * ((y * z) / (r * x)).atan())
+                    - (y.powi(2) / 2. * ((x * z) / (r * y)).atan())
+                    - (z.powi(2) / 2. * ((x * y) / (r * z)).atan()));
+        }
+        u * G * self.density
+    }
+
     fn gx(&self, position: &Array1<f64>) -> f64 {
         let mut gx = 0.;
         let verts = self.vertices_axis_aligned();
@@ -775,14 +1208,38 @@ impl Cuboid {
         ]
     }
 
-    /// Return verices ordered to plot a rectangle in x-z plane using egui Polygon.
-    /// Assumes no rotation
-    pub fn vertices_xz(&self) -> Vec<[f64; 2]> {
-        let verts = (self.vertices_axis_aligned() - self.centre())
+    /// Apply a further rotation about the cuboid centre, in the row vector convention of
+    /// `rotation_matrix_x` etc., and store the result as x, y and z rotation angles.
+    pub fn rotate(&mut self, rotation: &Array2<f64>) {
+        let m = rotation_matrix_x(self.x_rotation)
+            .dot(&rotation_matrix_y(self.y_rotation))
+            .dot(&rotation_matrix_z(self.z_rotation))
+            .dot(rotation);
+        self.y_rotation = (-m[[0, 2]]).clamp(-1., 1.).asin();
+        if m[[0, 2]].abs() < 1. - 1e-9 {
+            self.x_rotation = m[[1, 2]].atan2(m[[2, 2]]);
+            self.z_rotation = m[[0, 1]].atan2(m[[0, 0]]);
+        } else {
+            // Gimbal lock, only x + z (or x - z) is determined.
+            self.x_rotation = (-m[[2, 1]]).atan2(m[[1, 1]]);
+            self.z_rotation = 0.;
+        }
+    }
+
+    /// Return cuboid vertices with the rotation applied, same order as
+    /// self.vertices_axis_aligned()
+    pub fn vertices_rotated(&self) -> Array2<f64> {
+        (self.vertices_axis_aligned() - self.centre())
             .dot(&rotation_matrix_x(self.x_rotation))
             .dot(&rotation_matrix_y(self.y_rotation))
             .dot(&rotation_matrix_z(self.z_rotation))
-            + self.centre();
+            + self.centre()
+    }
+
+    /// Return verices ordered to plot a rectangle in x-z plane using egui Polygon.
+    /// Assumes no rotation
+    pub fn vertices_xz(&self) -> Vec<[f64; 2]> {
+        let verts = self.vertices_rotated();
         verts
             .slice(s![.., 0])
             .iter()
@@ -815,11 +1272,7 @@ impl Cuboid {
     }
 
     pub fn vertices_xy(&self) -> Vec<[f64; 2]> {
-        let verts = (self.vertices_axis_aligned() - self.centre())
-            .dot(&rotation_matrix_x(self.x_rotation))
-            .dot(&rotation_matrix_y(self.y_rotation))
-            .dot(&rotation_matrix_z(self.z_rotation))
-            + self.centre();
+        let verts = self.vertices_rotated();
         verts
             .slice(s![.., 0])
             .iter()
@@ -852,11 +1305,7 @@ impl Cuboid {
     }
 
     pub fn vertices_yz(&self) -> Vec<[f64; 2]> {
-        let verts = (self.vertices_axis_aligned() - self.centre())
-            .dot(&rotation_matrix_x(self.x_rotation))
-            .dot(&rotation_matrix_y(self.y_rotation))
-            .dot(&rotation_matrix_z(self.z_rotation))
-            + self.centre();
+        let verts = self.vertices_rotated();
         verts
             .slice(s![.., 1])
             .iter()
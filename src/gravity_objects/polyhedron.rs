@@ -0,0 +1,262 @@
+use super::mesh::{cross, dot};
+use super::{DataType, Field, GravityCalc, TriangleMesh, G, GG_SCALING, G_SCALING, U_SCALING};
+use egui::plot::Line;
+use ndarray::prelude::*;
+
+/// Closed triangulated body of uniform density, e.g. imported from a CAD model. The field
+/// is the exact solution for a polyhedron (Werner & Scheeres, 1996), summed over the
+/// faces and edges of the mesh.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Polyhedron {
+    /// Surface with triangles wound anticlockwise when viewed from outside.
+    pub mesh: TriangleMesh,
+    pub density: f64,
+}
+
+impl Polyhedron {
+    pub fn new(mesh: TriangleMesh, density: f64) -> Self {
+        Self { mesh, density }
+    }
+
+    /// Extent of the bounding box along x, y and z.
+    pub fn size(&self) -> [f64; 3] {
+        let [min, max] = self.mesh.bounds();
+        [max[0] - min[0], max[1] - min[1], max[2] - min[2]]
+    }
+
+    pub fn translate(&mut self, offset: [f64; 3]) {
+        for vertex in self.mesh.vertices.iter_mut() {
+            for k in 0..3 {
+                vertex[k] += offset[k];
+            }
+        }
+    }
+
+    /// Scale about the centre by separate factors along x, y and z.
+    pub fn scale(&mut self, factors: [f64; 3]) {
+        let centre = self.mesh.centroid();
+        for vertex in self.mesh.vertices.iter_mut() {
+            for k in 0..3 {
+                vertex[k] = centre[k] + (vertex[k] - centre[k]) * factors[k];
+            }
+        }
+        // A reflection turns the triangles inside out.
+        if factors.iter().product::<f64>() < 0. {
+            self.mesh.flip();
+        }
+    }
+
+    /// Rotate about the centre, in the row vector convention of `rotation_matrix_x` etc.
+    pub fn rotate(&mut self, rotation: &Array2<f64>) {
+        let centre = self.mesh.centroid();
+        for vertex in self.mesh.vertices.iter_mut() {
+            let v = [
+                vertex[0] - centre[0],
+                vertex[1] - centre[1],
+                vertex[2] - centre[2],
+            ];
+            for k in 0..3 {
+                vertex[k] = centre[k]
+                    + v[0] * rotation[[0, k]]
+                    + v[1] * rotation[[1, k]]
+                    + v[2] * rotation[[2, k]];
+            }
+        }
+    }
+
+    /// Feature edges projected onto the model axes `axes`, for the model views.
+    pub fn edge_lines(&self, axes: [usize; 2]) -> Vec<Line> {
+        let [i, j] = axes;
+        self.mesh
+            .feature_edges(30f64.to_radians())
+            .iter()
+            .map(|edge| {
+                let [a, b] = edge.map(|k| self.mesh.vertices[k]);
+                Line::new(vec![[a[i], a[j]], [b[i], b[j]]])
+            })
+            .collect()
+    }
+
+    /// Potential, attraction and gradient tensor at `position` in SI units, with the
+    /// conventions of `Sphere`.
+    fn solve(&self, position: &Array1<f64>) -> (f64, [f64; 3], [[f64; 3]; 3]) {
+        let p = [position[0], position[1], position[2]];
+        let relative = |v: [f64; 3]| [v[0] - p[0], v[1] - p[1], v[2] - p[2]];
+        let norm = |v: [f64; 3]| dot(v, v).sqrt();
+
+        let mut u = 0.;
+        let mut g = [0.; 3];
+        let mut gg = [[0.; 3]; 3];
+        for triangle in &self.mesh.triangles {
+            let n = self.mesh.normal(triangle);
+            let r = triangle.map(|i| relative(self.mesh.vertices[i]));
+            let l = r.map(norm);
+
+            // Face term, weighted by the solid angle the face subtends.
+            let omega = 2.
+                * dot(r[0], cross(r[1], r[2])).atan2(
+                    l[0] * l[1] * l[2]
+                        + l[0] * dot(r[1], r[2])
+                        + l[1] * dot(r[2], r[0])
+                        + l[2] * dot(r[0], r[1]),
+                );
+            let n_r = dot(n, r[0]);
+            u -= n_r * n_r * omega;
+            for a in 0..3 {
+                g[a] += n[a] * n_r * omega;
+                for b in 0..3 {
+                    gg[a][b] -= n[a] * n[b] * omega;
+                }
+            }
+
+            // Edge terms, each edge is visited once from each of its two faces.
+            for k in 0..3 {
+                let (i, j) = (k, (k + 1) % 3);
+                let edge = [r[j][0] - r[i][0], r[j][1] - r[i][1], r[j][2] - r[i][2]];
+                let length = norm(edge);
+                let denominator = l[i] + l[j] - length;
+                if length == 0. || denominator <= 0. {
+                    continue;
+                }
+                let log = ((l[i] + l[j] + length) / denominator).ln();
+                let edge_normal = cross(edge, n).map(|x| x / length);
+                let e_r = dot(edge_normal, r[i]);
+                u += n_r * e_r * log;
+                for a in 0..3 {
+                    g[a] -= n[a] * e_r * log;
+                    for b in 0..3 {
+                        gg[a][b] += n[a] * edge_normal[b] * log;
+                    }
+                }
+            }
+        }
+
+        let constant = G * self.density;
+        let mut tensor = [[0.; 3]; 3];
+        for a in 0..3 {
+            for b in 0..3 {
+                tensor[a][b] = constant * (gg[a][b] + gg[b][a]) / 2.;
+            }
+        }
+        (constant * u / 2., g.map(|g| constant * g), tensor)
+    }
+}
+
+impl GravityCalc for Polyhedron {
+    fn calculate(&self, data_type: &DataType, points: &Array2<f64>) -> Array1<f64> {
+        self.field(points).data(data_type)
+    }
+
+    fn field(&self, points: &Array2<f64>) -> Field {
+        let mut field = Field::zeros(points.len_of(Axis(0)));
+        for (i, point) in points.axis_iter(Axis(0)).enumerate() {
+            let (u, g, gg) = self.solve(&point.to_owned());
+            field.u[i] = u * U_SCALING;
+            field.g.row_mut(i).assign(&(arr1(&g) * G_SCALING));
+            field
+                .gg
+                .index_axis_mut(Axis(0), i)
+                .assign(&(arr2(&gg) * GG_SCALING));
+        }
+        field
+    }
+
+    fn potential(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).0
+    }
+
+    fn g(&self, position: &Array1<f64>) -> Array1<f64> {
+        arr1(&self.solve(position).1)
+    }
+
+    fn gg(&self, position: &Array1<f64>) -> Array2<f64> {
+        arr2(&self.solve(position).2)
+    }
+
+    fn gx(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).1[0]
+    }
+
+    fn gy(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).1[1]
+    }
+
+    fn gz(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).1[2]
+    }
+
+    fn gxx(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).2[0][0]
+    }
+
+    fn gxy(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).2[0][1]
+    }
+
+    fn gxz(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).2[0][2]
+    }
+
+    fn gyy(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).2[1][1]
+    }
+
+    fn gyz(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).2[1][2]
+    }
+
+    fn gzz(&self, position: &Array1<f64>) -> f64 {
+        self.solve(position).2[2][2]
+    }
+
+    fn centre(&self) -> Array1<f64> {
+        arr1(&self.mesh.centroid())
+    }
+
+    fn volume(&self) -> f64 {
+        self.mesh.volume()
+    }
+
+    fn mass(&self) -> f64 {
+        self.density * self.volume()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gravity_objects::Cuboid;
+
+    /// Whether `a` matches `b` to a small fraction of the largest value in `b`.
+    fn close<D: Dimension>(a: &Array<f64, D>, b: &Array<f64, D>) -> bool {
+        let scale = b.iter().fold(0., |max: f64, v| max.max(v.abs()));
+        (a - b).iter().all(|d| d.abs() < 1e-4 * scale)
+    }
+
+    #[test]
+    fn box_mesh_matches_cuboid() {
+        let cuboid = Cuboid {
+            x_length: 4.,
+            y_length: 2.,
+            z_length: 3.,
+            x_centroid: 1.,
+            y_centroid: -0.5,
+            z_centroid: -4.,
+            density: 2500.,
+            ..Cuboid::default()
+        };
+        let polyhedron = Polyhedron::new(cuboid.mesh(), cuboid.density);
+        let points = array![
+            [0., 0., 0.],
+            [3., 1., 0.5],
+            [-2.5, -3., 1.],
+            [1., -0.5, -1.],
+            [6., 4., -4.],
+        ];
+        let expected = cuboid.field(&points);
+        let field = polyhedron.field(&points);
+        assert!(close(&field.u, &expected.u), "{}", field.u);
+        assert!(close(&field.g, &expected.g), "{}", field.g);
+        assert!(close(&field.gg, &expected.gg), "{}", field.gg);
+    }
+}
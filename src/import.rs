@@ -0,0 +1,498 @@
+use crate::gravity_objects::{GravityModelObject, GravityObject, Polyhedron, TriangleMesh};
+use crate::model::Model;
+use egui::{Color32, Ui};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// Meshes with more triangles than this make the views noticeably slow.
+const LARGE_MESH: usize = 5000;
+
+/// Name and contents of a file chosen by the user.
+pub type PickedFile = Arc<Mutex<Option<(String, Result<Vec<u8>, String>)>>>;
+
+/// Read a triangulated surface from STL (ASCII or binary), OBJ or PLY (ASCII or binary),
+/// chosen by the file extension. Polygons are split into triangle fans.
+pub fn read_mesh(file_name: &str, bytes: &[u8]) -> Result<TriangleMesh, Box<dyn Error>> {
+    let extension = file_name
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "stl" => read_stl(bytes),
+        "obj" => read_obj(&String::from_utf8_lossy(bytes)),
+        "ply" => read_ply(bytes),
+        _ => Err(format!("unsupported mesh format '.{extension}'").into()),
+    }
+}
+
+fn read_stl(bytes: &[u8]) -> Result<TriangleMesh, Box<dyn Error>> {
+    // Some binary files also start with "solid", so check the size as well. In an ASCII
+    // file the count is text, so the size can overflow on 32-bit targets such as wasm.
+    let binary_size = bytes.get(80..84).and_then(|n| {
+        u64::from(u32::from_le_bytes(n.try_into().unwrap()))
+            .checked_mul(50)?
+            .checked_add(84)
+    });
+    if binary_size == Some(bytes.len() as u64) {
+        let n_triangles = (bytes.len() - 84) / 50;
+        let mut mesh = TriangleMesh::default();
+        for t in 0..n_triangles {
+            let facet = &bytes[84 + 50 * t..84 + 50 * (t + 1)];
+            for v in 0..3 {
+                let start = 12 + 12 * v;
+                let coordinate = |k: usize| {
+                    let i = start + 4 * k;
+                    f32::from_le_bytes(facet[i..i + 4].try_into().unwrap()) as f64
+                };
+                mesh.vertices
+                    .push([coordinate(0), coordinate(1), coordinate(2)]);
+            }
+            mesh.triangles.push([3 * t, 3 * t + 1, 3 * t + 2]);
+        }
+        return Ok(mesh);
+    }
+    if !bytes.starts_with(b"solid") {
+        return Err("neither an ASCII STL nor a binary STL of the right size".into());
+    }
+
+    let mut mesh = TriangleMesh::default();
+    let mut facet = vec![];
+    for line in String::from_utf8_lossy(bytes).lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("vertex") => facet.push(parse_point(tokens)?),
+            Some("endfacet") => {
+                add_polygon(&mut mesh, &facet);
+                facet.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok(mesh)
+}
+
+fn read_obj(text: &str) -> Result<TriangleMesh, Box<dyn Error>> {
+    let mut mesh = TriangleMesh::default();
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => mesh.vertices.push(parse_point(tokens)?),
+            Some("f") => {
+                let n_vertices = mesh.vertices.len() as i64;
+                let face = tokens
+                    .map(|token| {
+                        // Faces are v, v/vt, v//vn or v/vt/vn with one based, possibly
+                        // negative (relative), indices.
+                        let index: i64 = token.split('/').next().unwrap_or_default().parse()?;
+                        let index = if index < 0 {
+                            n_vertices + index
+                        } else {
+                            index - 1
+                        };
+                        if (0..n_vertices).contains(&index) {
+                            Ok(index as usize)
+                        } else {
+                            Err(format!("face index {token} out of range").into())
+                        }
+                    })
+                    .collect::<Result<Vec<usize>, Box<dyn Error>>>()?;
+                add_face(&mut mesh, &face);
+            }
+            _ => {}
+        }
+    }
+    Ok(mesh)
+}
+
+#[derive(Clone, Copy)]
+enum PlyFormat {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+enum PlyProperty {
+    Scalar {
+        name: String,
+        kind: String,
+    },
+    List {
+        name: String,
+        count: String,
+        kind: String,
+    },
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+fn read_ply(bytes: &[u8]) -> Result<TriangleMesh, Box<dyn Error>> {
+    let end = b"end_header";
+    let header_end = bytes
+        .windows(end.len())
+        .position(|w| w == end)
+        .ok_or("PLY header has no end_header")?;
+    let body_start = bytes[header_end..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|i| header_end + i + 1)
+        .unwrap_or(bytes.len());
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+
+    let mut format = PlyFormat::Ascii;
+    let mut elements: Vec<PlyElement> = vec![];
+    for line in header.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", "ascii", ..] => format = PlyFormat::Ascii,
+            ["format", "binary_little_endian", ..] => format = PlyFormat::LittleEndian,
+            ["format", "binary_big_endian", ..] => format = PlyFormat::BigEndian,
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count.parse()?,
+                properties: vec![],
+            }),
+            ["property", "list", count, kind, name] => elements
+                .last_mut()
+                .ok_or("PLY property before element")?
+                .properties
+                .push(PlyProperty::List {
+                    name: name.to_string(),
+                    count: count.to_string(),
+                    kind: kind.to_string(),
+                }),
+            ["property", kind, name] => elements
+                .last_mut()
+                .ok_or("PLY property before element")?
+                .properties
+                .push(PlyProperty::Scalar {
+                    name: name.to_string(),
+                    kind: kind.to_string(),
+                }),
+            _ => {}
+        }
+    }
+
+    let mut reader = PlyReader {
+        format,
+        bytes: &bytes[body_start..],
+        position: 0,
+        tokens: String::from_utf8_lossy(&bytes[body_start..])
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+    };
+    let mut mesh = TriangleMesh::default();
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut point = [0.; 3];
+            for property in &element.properties {
+                match property {
+                    PlyProperty::Scalar { name, kind } => {
+                        let value = reader.value(kind)?;
+                        match name.as_str() {
+                            "x" => point[0] = value,
+                            "y" => point[1] = value,
+                            "z" => point[2] = value,
+                            _ => {}
+                        }
+                    }
+                    PlyProperty::List { name, count, kind } => {
+                        let n = reader.value(count)? as usize;
+                        let values = (0..n)
+                            .map(|_| reader.value(kind).map(|v| v as usize))
+                            .collect::<Result<Vec<usize>, _>>()?;
+                        if element.name == "face"
+                            && (name == "vertex_indices" || name == "vertex_index")
+                        {
+                            if values.iter().any(|i| *i >= mesh.vertices.len()) {
+                                return Err("PLY face index out of range".into());
+                            }
+                            add_face(&mut mesh, &values);
+                        }
+                    }
+                }
+            }
+            if element.name == "vertex" {
+                mesh.vertices.push(point);
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+/// Sequential reader of PLY values in any of the three encodings.
+struct PlyReader<'a> {
+    format: PlyFormat,
+    bytes: &'a [u8],
+    position: usize,
+    tokens: Vec<String>,
+}
+
+impl PlyReader<'_> {
+    fn value(&mut self, kind: &str) -> Result<f64, Box<dyn Error>> {
+        if let PlyFormat::Ascii = self.format {
+            let token = self.tokens.get(self.position).ok_or("PLY data truncated")?;
+            self.position += 1;
+            return Ok(token.parse()?);
+        }
+        let size = match kind {
+            "char" | "uchar" | "int8" | "uint8" => 1,
+            "short" | "ushort" | "int16" | "uint16" => 2,
+            "int" | "uint" | "float" | "int32" | "uint32" | "float32" => 4,
+            "double" | "float64" => 8,
+            _ => return Err(format!("unknown PLY type {kind}").into()),
+        };
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(
+            self.bytes
+                .get(self.position..self.position + size)
+                .ok_or("PLY data truncated")?,
+        );
+        self.position += size;
+        if let PlyFormat::BigEndian = self.format {
+            raw[..size].reverse();
+        }
+        let b = raw;
+        Ok(match kind {
+            "char" | "int8" => b[0] as i8 as f64,
+            "uchar" | "uint8" => b[0] as f64,
+            "short" | "int16" => i16::from_le_bytes([b[0], b[1]]) as f64,
+            "ushort" | "uint16" => u16::from_le_bytes([b[0], b[1]]) as f64,
+            "int" | "int32" => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            "uint" | "uint32" => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            "float" | "float32" => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            _ => f64::from_le_bytes(b),
+        })
+    }
+}
+
+fn parse_point<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<[f64; 3], Box<dyn Error>> {
+    let mut point = [0.; 3];
+    for value in point.iter_mut() {
+        *value = tokens
+            .next()
+            .ok_or("vertex has fewer than 3 coordinates")?
+            .parse()?;
+    }
+    Ok(point)
+}
+
+/// Add a polygon given by its vertex positions.
+fn add_polygon(mesh: &mut TriangleMesh, polygon: &[[f64; 3]]) {
+    let start = mesh.vertices.len();
+    mesh.vertices.extend(polygon);
+    add_face(mesh, &(start..mesh.vertices.len()).collect::<Vec<_>>());
+}
+
+/// Add a polygon given by vertex indices as a fan of triangles.
+fn add_face(mesh: &mut TriangleMesh, face: &[usize]) {
+    for k in 1..face.len().saturating_sub(1) {
+        mesh.triangles.push([face[0], face[k], face[k + 1]]);
+    }
+}
+
+/// Length units of imported meshes, CAD models are often in millimetres.
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Clone, Copy)]
+pub enum MeshUnits {
+    Metres,
+    Centimetres,
+    Millimetres,
+    Feet,
+}
+
+impl MeshUnits {
+    fn scale(&self) -> f64 {
+        match self {
+            MeshUnits::Metres => 1.,
+            MeshUnits::Centimetres => 0.01,
+            MeshUnits::Millimetres => 0.001,
+            MeshUnits::Feet => 0.3048,
+        }
+    }
+}
+
+/// Import of closed triangulated meshes as polyhedral bodies.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MeshImport {
+    pub density: f64,
+    pub units: MeshUnits,
+    /// Offset in metres added after scaling.
+    pub offset: [f64; 3],
+    pub colour: Color32,
+    #[serde(skip)]
+    picked: PickedFile,
+    #[serde(skip)]
+    status: String,
+}
+
+impl Default for MeshImport {
+    fn default() -> Self {
+        Self {
+            density: -2000.,
+            units: MeshUnits::Metres,
+            offset: [0.; 3],
+            colour: Color32::TEMPORARY_COLOR,
+            picked: PickedFile::default(),
+            status: String::new(),
+        }
+    }
+}
+
+impl MeshImport {
+    pub fn ui(&mut self, ui: &mut Ui, model: &mut Model) {
+        ui.horizontal(|ui| {
+            ui.label("Density: ");
+            ui.add(egui::DragValue::new(&mut self.density).suffix(" kg/m^3"));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Units: ");
+            for (units, label) in [
+                (MeshUnits::Metres, "m"),
+                (MeshUnits::Centimetres, "cm"),
+                (MeshUnits::Millimetres, "mm"),
+                (MeshUnits::Feet, "ft"),
+            ] {
+                ui.radio_value(&mut self.units, units, label);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Offset: ");
+            for value in self.offset.iter_mut() {
+                ui.add(egui::DragValue::new(value).speed(0.05).suffix(" m"));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Colour: ");
+            ui.color_edit_button_srgba(&mut self.colour);
+        });
+        if ui.button("Import STL / OBJ / PLY…").clicked() {
+            pick_file(
+                ui.ctx(),
+                self.picked.clone(),
+                "Mesh",
+                &["stl", "obj", "ply"],
+            );
+        }
+
+        let picked = self.picked.lock().unwrap().take();
+        if let Some((file_name, bytes)) = picked {
+            let result = bytes
+                .map_err(|err| err.into())
+                .and_then(|bytes| self.add_mesh(model, &file_name, &bytes));
+            self.status = match result {
+                Ok(status) => status,
+                Err(err) => format!("Could not import {file_name}: {err}"),
+            };
+        }
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+
+    fn add_mesh(
+        &self,
+        model: &mut Model,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> Result<String, Box<dyn Error>> {
+        let mut mesh = read_mesh(file_name, bytes)?;
+        let scale = self.units.scale();
+        for vertex in mesh.vertices.iter_mut() {
+            for (value, offset) in vertex.iter_mut().zip(&self.offset) {
+                *value = *value * scale + offset;
+            }
+        }
+        let [min, max] = mesh.bounds();
+        let diagonal = (0..3)
+            .map(|k| (max[k] - min[k]).powi(2))
+            .sum::<f64>()
+            .sqrt();
+        mesh.weld(diagonal * 1e-9);
+        if mesh.triangles.is_empty() {
+            return Err("no triangles found".into());
+        }
+        if !mesh.is_closed() {
+            return Err(
+                "the mesh is not closed (watertight) or its faces are not consistently wound"
+                    .into(),
+            );
+        }
+        if mesh.volume() < 0. {
+            mesh.flip();
+        }
+
+        let n_objects = model.objects.values().flatten().count();
+        let n_triangles = mesh.triangles.len();
+        let name = file_name
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(file_name)
+            .to_string();
+        model.add_object(GravityModelObject {
+            object: GravityObject::Polyhedron(Polyhedron::new(mesh, self.density)),
+            name,
+            id: model.object_counter,
+            colour: self.colour,
+            is_selected: false,
+            is_hidden: false,
+            is_locked: false,
+        });
+        if model.objects.values().flatten().count() == n_objects {
+            return Err("the model already has the maximum number of objects".into());
+        }
+        let mut status = format!("Imported {file_name}, {n_triangles} triangles");
+        if n_triangles > LARGE_MESH {
+            status.push_str(", large meshes slow down the plots, consider decimating it");
+        }
+        Ok(status)
+    }
+}
+
+/// Let the user choose a file with one of `extensions`, its contents are left in `picked`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_file(
+    _ctx: &egui::Context,
+    picked: PickedFile,
+    filter: &'static str,
+    extensions: &'static [&'static str],
+) {
+    let path = rfd::FileDialog::new()
+        .add_filter(filter, extensions)
+        .pick_file();
+    if let Some(path) = path {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let contents = std::fs::read(&path).map_err(|err| err.to_string());
+        *picked.lock().unwrap() = Some((file_name, contents));
+    }
+}
+
+/// The browser only offers the file asynchronously, so it arrives on a later frame.
+#[cfg(target_arch = "wasm32")]
+pub fn pick_file(
+    ctx: &egui::Context,
+    picked: PickedFile,
+    filter: &'static str,
+    extensions: &'static [&'static str],
+) {
+    let ctx = ctx.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let file = rfd::AsyncFileDialog::new()
+            .add_filter(filter, extensions)
+            .pick_file()
+            .await;
+        if let Some(file) = file {
+            let contents = file.read().await;
+            *picked.lock().unwrap() = Some((file.file_name(), Ok(contents)));
+            ctx.request_repaint();
+        }
+    });
+}
@@ -1,7 +1,29 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+mod background;
+mod detectability;
+mod editor;
+mod estimation;
+mod export;
+mod figure;
 mod gravity_objects;
+mod import;
+mod map;
 mod model;
+mod noise;
+mod outliner;
 mod plot;
+mod processing;
+mod profile;
+mod sensitivity;
+mod sensor;
+mod template;
+#[cfg(test)]
+mod testing;
+mod timeline;
+mod transform;
+mod uncertainty;
+mod view3d;
+mod workspace;
 pub use app::GravityBuilderApp;
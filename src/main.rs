@@ -1,9 +1,11 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+mod background;
 mod gravity_objects;
 mod model;
 mod plot;
+mod timeline;
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
@@ -0,0 +1,385 @@
+use egui::{
+    plot::{Line, PlotImage, PlotPoint, PlotUi, Text},
+    Align2, Color32, ColorImage, Context, FontId, Sense, Stroke, TextureHandle, TextureOptions, Ui,
+    Vec2,
+};
+use ndarray::{Array1, Array2};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Colormap {
+    Viridis,
+    Inferno,
+    Cividis,
+    Turbo,
+    Greys,
+    RedBlue,
+    Spectral,
+    BrownGreen,
+    PurpleOrange,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 9] = [
+        Colormap::Viridis,
+        Colormap::Inferno,
+        Colormap::Cividis,
+        Colormap::Turbo,
+        Colormap::Greys,
+        Colormap::RedBlue,
+        Colormap::Spectral,
+        Colormap::BrownGreen,
+        Colormap::PurpleOrange,
+    ];
+
+    pub fn gradient(&self) -> colorous::Gradient {
+        match self {
+            Colormap::Viridis => colorous::VIRIDIS,
+            Colormap::Inferno => colorous::INFERNO,
+            Colormap::Cividis => colorous::CIVIDIS,
+            Colormap::Turbo => colorous::TURBO,
+            Colormap::Greys => colorous::GREYS,
+            Colormap::RedBlue => colorous::RED_BLUE,
+            Colormap::Spectral => colorous::SPECTRAL,
+            Colormap::BrownGreen => colorous::BROWN_GREEN,
+            Colormap::PurpleOrange => colorous::PURPLE_ORANGE,
+        }
+    }
+
+    pub fn is_diverging(&self) -> bool {
+        matches!(
+            self,
+            Colormap::RedBlue | Colormap::Spectral | Colormap::BrownGreen | Colormap::PurpleOrange
+        )
+    }
+
+    /// Colour for a value normalised to [0, 1]. Values outside are clamped.
+    pub fn colour(&self, t: f64) -> Color32 {
+        let t = if t.is_finite() { t.clamp(0., 1.) } else { 0. };
+        // colorous diverging schemes run from red to blue, flip so that positive is red.
+        let t = if self.is_diverging() { 1. - t } else { t };
+        let colour = self.gradient().eval_continuous(t);
+        Color32::from_rgb(colour.r, colour.g, colour.b)
+    }
+}
+
+/// Values on a regular grid, indexed [x, y] to match `DataParameters::points_xy`.
+#[derive(Debug, Clone)]
+pub struct GridData {
+    pub x: Array1<f64>,
+    pub y: Array1<f64>,
+    pub values: Array2<f64>,
+}
+
+impl GridData {
+    /// Build from points in x-major order (all y for the first x, then the next x).
+    pub fn from_points(points: &Array2<f64>, data: &Array1<f64>, nx: usize, ny: usize) -> Self {
+        let x = Array1::from_iter((0..nx).map(|i| points[[i * ny, 0]]));
+        let y = Array1::from_iter((0..ny).map(|j| points[[j, 1]]));
+        let values = Array2::from_shape_fn((nx, ny), |(i, j)| data[i * ny + j]);
+        Self { x, y, values }
+    }
+
+    pub fn bounds(&self) -> [f64; 4] {
+        [
+            self.x[0],
+            self.x[self.x.len() - 1],
+            self.y[0],
+            self.y[self.y.len() - 1],
+        ]
+    }
+
+    /// Bilinear interpolation, `None` outside the grid.
+    pub fn value_at(&self, x: f64, y: f64) -> Option<f64> {
+        let (i, tx) = cell(&self.x, x)?;
+        let (j, ty) = cell(&self.y, y)?;
+        let i1 = (i + 1).min(self.x.len() - 1);
+        let j1 = (j + 1).min(self.y.len() - 1);
+        let v = &self.values;
+        Some(
+            v[[i, j]] * (1. - tx) * (1. - ty)
+                + v[[i1, j]] * tx * (1. - ty)
+                + v[[i, j1]] * (1. - tx) * ty
+                + v[[i1, j1]] * tx * ty,
+        )
+    }
+
+    /// Contour segments at `level` using marching squares.
+    pub fn contour_segments(&self, level: f64) -> Vec<[[f64; 2]; 2]> {
+        let mut segments = vec![];
+        let v = &self.values;
+        let crossing = |a: [f64; 3], b: [f64; 3]| -> Option<[f64; 2]> {
+            if (a[2] > level) == (b[2] > level) {
+                return None;
+            }
+            let t = (level - a[2]) / (b[2] - a[2]);
+            Some([a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])])
+        };
+        for i in 0..self.x.len().saturating_sub(1) {
+            for j in 0..self.y.len().saturating_sub(1) {
+                let c00 = [self.x[i], self.y[j], v[[i, j]]];
+                let c10 = [self.x[i + 1], self.y[j], v[[i + 1, j]]];
+                let c11 = [self.x[i + 1], self.y[j + 1], v[[i + 1, j + 1]]];
+                let c01 = [self.x[i], self.y[j + 1], v[[i, j + 1]]];
+                let crossings: Vec<[f64; 2]> = [
+                    crossing(c00, c10),
+                    crossing(c10, c11),
+                    crossing(c11, c01),
+                    crossing(c01, c00),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                match crossings.len() {
+                    2 => segments.push([crossings[0], crossings[1]]),
+                    4 => {
+                        // Saddle: resolve using the cell centre value.
+                        let centre = (c00[2] + c10[2] + c11[2] + c01[2]) / 4.;
+                        if (centre > level) == (c00[2] > level) {
+                            segments.push([crossings[0], crossings[1]]);
+                            segments.push([crossings[2], crossings[3]]);
+                        } else {
+                            segments.push([crossings[0], crossings[3]]);
+                            segments.push([crossings[1], crossings[2]]);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        segments
+    }
+}
+
+/// Index of the grid cell containing `value` and the fractional position within it.
+fn cell(axis: &Array1<f64>, value: f64) -> Option<(usize, f64)> {
+    let n = axis.len();
+    if n == 0 {
+        return None;
+    }
+    let (start, end) = (axis[0], axis[n - 1]);
+    if n == 1 || end == start {
+        return if value == start { Some((0, 0.)) } else { None };
+    }
+    let f = (value - start) / (end - start) * (n - 1) as f64;
+    if !(0. ..=(n - 1) as f64).contains(&f) {
+        return None;
+    }
+    let i = (f.floor() as usize).min(n - 2);
+    Some((i, f - i as f64))
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct MapStyle {
+    pub colormap: Colormap,
+    /// Centre the colour range on zero.
+    pub symmetric: bool,
+    /// Clip this percentage of values at each end of the colour range.
+    pub clip_percent: f64,
+    /// Use `range` instead of the data range.
+    pub manual_range: bool,
+    pub range: [f64; 2],
+    pub contours: bool,
+    pub n_contours: usize,
+    pub contour_labels: bool,
+    /// Raster pixels per grid cell.
+    pub upsample: usize,
+}
+
+impl Default for MapStyle {
+    fn default() -> Self {
+        Self {
+            colormap: Colormap::Viridis,
+            symmetric: false,
+            clip_percent: 0.,
+            manual_range: false,
+            range: [-1., 1.],
+            contours: false,
+            n_contours: 10,
+            contour_labels: true,
+            upsample: 4,
+        }
+    }
+}
+
+impl MapStyle {
+    pub fn colour_range(&self, values: &Array2<f64>) -> [f64; 2] {
+        if self.manual_range {
+            return self.range;
+        }
+        let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        if sorted.is_empty() {
+            return [0., 1.];
+        }
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let clip = ((self.clip_percent / 100.) * (sorted.len() - 1) as f64).round() as usize;
+        let clip = clip.min((sorted.len() - 1) / 2);
+        let mut range = [sorted[clip], sorted[sorted.len() - 1 - clip]];
+        if self.symmetric {
+            let max_abs = range[0].abs().max(range[1].abs());
+            range = [-max_abs, max_abs];
+        }
+        if range[0] == range[1] {
+            range = [range[0] - 0.5, range[1] + 0.5];
+        }
+        range
+    }
+
+    pub fn contour_levels(&self, range: [f64; 2]) -> Vec<f64> {
+        let n = self.n_contours.max(1);
+        let step = (range[1] - range[0]) / (n + 1) as f64;
+        (1..=n).map(|i| range[0] + i as f64 * step).collect()
+    }
+
+    /// Bilinearly interpolated raster of the grid, top row at the largest y.
+    pub fn raster(&self, grid: &GridData, range: [f64; 2]) -> ColorImage {
+        let upsample = self.upsample.max(1);
+        let width = (grid.x.len().max(2) - 1) * upsample + 1;
+        let height = (grid.y.len().max(2) - 1) * upsample + 1;
+        let [x0, x1, y0, y1] = grid.bounds();
+        let mut image = ColorImage::new([width, height], Color32::TRANSPARENT);
+        for row in 0..height {
+            let y = y1 - (y1 - y0) * row as f64 / (height - 1) as f64;
+            for col in 0..width {
+                let x = x0 + (x1 - x0) * col as f64 / (width - 1) as f64;
+                if let Some(value) = grid.value_at(x, y) {
+                    let t = (value - range[0]) / (range[1] - range[0]);
+                    image.pixels[row * width + col] = self.colormap.colour(t);
+                }
+            }
+        }
+        image
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        egui::ComboBox::from_label("Colour map")
+            .selected_text(format!("{:?}", self.colormap))
+            .show_ui(ui, |ui| {
+                for colormap in Colormap::ALL {
+                    if ui
+                        .selectable_value(&mut self.colormap, colormap, format!("{colormap:?}"))
+                        .changed()
+                    {
+                        self.symmetric = colormap.is_diverging();
+                    }
+                }
+            });
+        ui.checkbox(&mut self.symmetric, "Symmetric about zero");
+        ui.add(egui::Slider::new(&mut self.clip_percent, 0.0..=25.).text("% clip"));
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.manual_range, "Range");
+            ui.add_enabled(self.manual_range, egui::DragValue::new(&mut self.range[0]));
+            ui.add_enabled(self.manual_range, egui::DragValue::new(&mut self.range[1]));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.contours, "Contours");
+            ui.add_enabled(
+                self.contours,
+                egui::Slider::new(&mut self.n_contours, 1..=30),
+            );
+            ui.add_enabled(
+                self.contours,
+                egui::Checkbox::new(&mut self.contour_labels, "Labels"),
+            );
+        });
+        ui.add(egui::Slider::new(&mut self.upsample, 1..=8).text("interpolation"));
+    }
+}
+
+/// Map style plus the texture the raster is uploaded to.
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+#[serde(default)]
+pub struct MapView {
+    pub style: MapStyle,
+    #[serde(skip)]
+    texture: Option<TextureHandle>,
+}
+
+impl MapView {
+    /// Draw the grid as an interpolated image with optional contours.
+    pub fn show(&mut self, ctx: &Context, plot_ui: &mut PlotUi, grid: &GridData, range: [f64; 2]) {
+        let image = self.style.raster(grid, range);
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::LINEAR);
+                texture
+            }
+            None => self
+                .texture
+                .insert(ctx.load_texture("xy_map", image, TextureOptions::LINEAR)),
+        };
+        let [x0, x1, y0, y1] = grid.bounds();
+        plot_ui.image(
+            PlotImage::new(
+                texture.id(),
+                PlotPoint::new((x0 + x1) / 2., (y0 + y1) / 2.),
+                Vec2::new((x1 - x0) as f32, (y1 - y0) as f32),
+            )
+            .name("Data"),
+        );
+        if self.style.contours {
+            for level in self.style.contour_levels(range) {
+                let segments = grid.contour_segments(level);
+                for segment in segments.iter() {
+                    plot_ui.line(
+                        Line::new(segment.to_vec())
+                            .color(Color32::from_black_alpha(160))
+                            .width(1.),
+                    );
+                }
+                if self.style.contour_labels && !segments.is_empty() {
+                    let [[xa, ya], [xb, yb]] = segments[segments.len() / 2];
+                    plot_ui.text(
+                        Text::new(
+                            PlotPoint::new((xa + xb) / 2., (ya + yb) / 2.),
+                            format!("{level:.2}"),
+                        )
+                        .color(Color32::BLACK),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Horizontal colour bar with tick labels spanning `range`.
+pub fn colour_bar(ui: &mut Ui, colormap: &Colormap, range: [f64; 2], label: &str, width: f32) {
+    let n_ticks = 5;
+    let (rect, _response) = ui.allocate_exact_size(Vec2::new(width, 40.), Sense::hover());
+    let bar = egui::Rect::from_min_size(rect.min, Vec2::new(width, 16.));
+    let n_steps = 128;
+    let step = width / n_steps as f32;
+    for i in 0..n_steps {
+        let t = (i as f64 + 0.5) / n_steps as f64;
+        let strip = egui::Rect::from_min_size(
+            bar.min + Vec2::new(i as f32 * step, 0.),
+            Vec2::new(step + 0.5, bar.height()),
+        );
+        ui.painter().rect_filled(strip, 0., colormap.colour(t));
+    }
+    let text_colour = ui.visuals().text_color();
+    ui.painter()
+        .rect_stroke(bar, 0., Stroke::new(1., text_colour));
+    for i in 0..n_ticks {
+        let t = i as f32 / (n_ticks - 1) as f32;
+        let x = bar.min.x + t * width;
+        let value = range[0] + (range[1] - range[0]) * t as f64;
+        ui.painter().line_segment(
+            [egui::pos2(x, bar.max.y), egui::pos2(x, bar.max.y + 4.)],
+            Stroke::new(1., text_colour),
+        );
+        let align = match i {
+            0 => Align2::LEFT_TOP,
+            i if i == n_ticks - 1 => Align2::RIGHT_TOP,
+            _ => Align2::CENTER_TOP,
+        };
+        ui.painter().text(
+            egui::pos2(x, bar.max.y + 5.),
+            align,
+            format!("{value:.3}"),
+            FontId::proportional(12.),
+            text_colour,
+        );
+    }
+    ui.label(label);
+}
@@ -1,7 +1,12 @@
+use crate::background::Background;
 use crate::gravity_objects;
-use crate::plot::PlotView;
+use crate::plot::{PlotView, Snap};
+use crate::timeline::Timeline;
 use egui::plot::PlotUi;
-use gravity_objects::{Cuboid, GravityModelObject, GravityObject, Sphere};
+use gravity_objects::{
+    Cuboid, DataType, Field, GravityModelObject, GravityObject, ParameterRanges, Sphere,
+};
+use ndarray::{Array1, Array2, Axis};
 use std::{
     collections::{BTreeMap, BTreeSet},
     env::current_dir,
@@ -14,12 +19,31 @@ use std::{
 
 const MAX_OBJECTS: usize = 10;
 
-#[derive(serde::Deserialize, serde::Serialize, Debug)]
+/// How a box selection combines with the current selection.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SelectMode {
+    Replace,
+    Add,
+    Subtract,
+    Intersect,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct Model {
     pub name: String,
     pub objects: BTreeMap<String, Option<GravityModelObject>>,
     pub groups: BTreeMap<String, Option<BTreeSet<String>>>,
     pub object_counter: u128,
+    /// Display order of the object ids in the outliner.
+    #[serde(default)]
+    pub order: Vec<String>,
+    #[serde(default)]
+    pub ranges: ParameterRanges,
+    #[serde(default)]
+    pub timeline: Timeline,
+    /// Layers and regional trend included in the combined response.
+    #[serde(default)]
+    pub background: Background,
 }
 
 impl Default for Model {
@@ -33,6 +57,10 @@ impl Default for Model {
             objects,
             groups,
             object_counter: 0,
+            order: vec![],
+            ranges: ParameterRanges::default(),
+            timeline: Timeline::default(),
+            background: Background::default(),
         }
     }
 }
@@ -68,45 +96,80 @@ impl Model {
         selected_object_ids
     }
 
+    /// Toggle the selection of the topmost visible object under the pointer.
     pub fn select_by_click(&mut self, plot_ui: &mut PlotUi, plot_view: &mut PlotView) {
-        for (_, object) in self.objects.iter_mut() {
-            let pointer_pos = plot_ui.pointer_coordinate().unwrap();
-            match object {
-                Some(obj) => match &obj.object {
-                    GravityObject::Cuboid(cuboid) => {
-                        let pos: [f64; 2] = match plot_view {
-                            PlotView::XY => [cuboid.x_centroid, cuboid.y_centroid],
-                            PlotView::XZ => [cuboid.x_centroid, cuboid.z_centroid],
-                            PlotView::YZ => [cuboid.y_centroid, cuboid.z_centroid],
-                        };
-                        if ((pos[0] - pointer_pos.x as f64).powi(2)
-                            + (pos[1] - pointer_pos.y as f64).powi(2))
-                        .sqrt()
-                            < 0.5
-                        {
-                            obj.is_selected = !obj.is_selected;
-                        }
-                    }
-                    GravityObject::Sphere(sphere) => {
-                        let pos: [f64; 2] = match plot_view {
-                            PlotView::XY => [sphere.x_centroid, sphere.y_centroid],
-                            PlotView::XZ => [sphere.x_centroid, sphere.z_centroid],
-                            PlotView::YZ => [sphere.y_centroid, sphere.z_centroid],
-                        };
-                        if ((pos[0] - pointer_pos.x as f64).powi(2)
-                            + (pos[1] - pointer_pos.y as f64).powi(2))
-                        .sqrt()
-                            < sphere.radius
-                        {
-                            obj.is_selected = !obj.is_selected;
-                        }
-                    }
-                },
-                None => {}
+        let pointer = match plot_ui.pointer_coordinate() {
+            Some(p) => [p.x, p.y],
+            None => return,
+        };
+        let mut topmost: Option<(f64, &mut GravityModelObject)> = None;
+        for obj in self.objects.values_mut().flatten() {
+            if obj.is_hidden || !plot_view.footprint(&obj.object).contains(pointer) {
+                continue;
             }
+            // Later objects are drawn on top, so they win ties.
+            let depth = plot_view.depth(obj.object.as_calc().centre().as_slice().unwrap());
+            if topmost.as_ref().map_or(true, |(top, _)| depth >= *top) {
+                topmost = Some((depth, obj));
+            }
+        }
+        if let Some((_, obj)) = topmost {
+            obj.is_selected = !obj.is_selected;
         }
     }
 
+    /// Combine the visible objects lying entirely inside the box with the selection.
+    pub fn select_in_box(
+        &mut self,
+        plot_view: &PlotView,
+        corners: [[f64; 2]; 2],
+        mode: SelectMode,
+    ) {
+        let min = [
+            corners[0][0].min(corners[1][0]),
+            corners[0][1].min(corners[1][1]),
+        ];
+        let max = [
+            corners[0][0].max(corners[1][0]),
+            corners[0][1].max(corners[1][1]),
+        ];
+        for obj in self.objects.values_mut().flatten() {
+            let inside = !obj.is_hidden && plot_view.footprint(&obj.object).within(min, max);
+            obj.is_selected = match mode {
+                SelectMode::Replace => inside,
+                SelectMode::Add => obj.is_selected || inside,
+                SelectMode::Subtract => obj.is_selected && !inside,
+                SelectMode::Intersect => obj.is_selected && inside,
+            };
+        }
+    }
+
+    /// Combined response of all objects and the background. Derived data types are
+    /// computed from the summed field rather than summed object by object.
+    pub fn calculate(&self, data_type: &DataType, points: &Array2<f64>) -> Array1<f64> {
+        if data_type.is_linear() {
+            let mut data = self.background.calculate(data_type, points);
+            for obj in self.objects.values().flatten() {
+                data = data + obj.object.as_calc().calculate(data_type, points);
+            }
+            data
+        } else {
+            self.field(points).data(data_type)
+        }
+    }
+
+    pub fn field(&self, points: &Array2<f64>) -> Field {
+        let mut field = if self.background.is_active() {
+            self.background.field(points)
+        } else {
+            Field::zeros(points.len_of(Axis(0)))
+        };
+        for obj in self.objects.values().flatten() {
+            field += obj.object.as_calc().field(points);
+        }
+        field
+    }
+
     pub fn deselect_all(&mut self) {
         for (_, object) in self.objects.iter_mut() {
             match object {
@@ -116,97 +179,87 @@ impl Model {
         }
     }
 
-    pub fn translate_selected(&mut self, plot_ui: &mut PlotUi, plot_view: &mut PlotView) {
-        for (_, object) in self.objects.iter_mut() {
-            let pointer_delta = plot_ui.pointer_coordinate_drag_delta();
-            match object {
-                Some(obj) => match &mut obj.object {
-                    GravityObject::Cuboid(cuboid) => {
-                        if obj.is_selected {
-                            match plot_view {
-                                PlotView::XY => {
-                                    cuboid.x_centroid += pointer_delta.x as f64;
-                                    cuboid.y_centroid += pointer_delta.y as f64;
-                                }
-                                PlotView::XZ => {
-                                    cuboid.x_centroid += pointer_delta.x as f64;
-                                    cuboid.z_centroid += pointer_delta.y as f64;
-                                }
-                                PlotView::YZ => {
-                                    cuboid.y_centroid += pointer_delta.x as f64;
-                                    cuboid.z_centroid += pointer_delta.y as f64;
-                                }
-                            };
-                        }
-                    }
-                    GravityObject::Sphere(sphere) => {
-                        if obj.is_selected {
-                            match plot_view {
-                                PlotView::XY => {
-                                    sphere.x_centroid += pointer_delta.x as f64;
-                                    sphere.y_centroid += pointer_delta.y as f64;
-                                }
-                                PlotView::XZ => {
-                                    sphere.x_centroid += pointer_delta.x as f64;
-                                    sphere.z_centroid += pointer_delta.y as f64;
-                                }
-                                PlotView::YZ => {
-                                    sphere.y_centroid += pointer_delta.x as f64;
-                                    sphere.z_centroid += pointer_delta.y as f64;
-                                }
-                            };
-                        }
-                    }
-                },
-                None => {}
+    /// Pointer coordinates at the start and end of this frame's drag, in the view's axes.
+    fn pointer_drag(plot_ui: &PlotUi) -> ([f64; 2], [f64; 2]) {
+        let delta = plot_ui.pointer_coordinate_drag_delta();
+        let to = plot_ui
+            .pointer_coordinate()
+            .map(|p| [p.x, p.y])
+            .unwrap_or_default();
+        ([to[0] - delta.x as f64, to[1] - delta.y as f64], to)
+    }
+
+    pub fn translate_selected(
+        &mut self,
+        plot_ui: &mut PlotUi,
+        plot_view: &mut PlotView,
+        snap: &Snap,
+    ) {
+        let (from, to) = Self::pointer_drag(plot_ui);
+        let [i, j] = plot_view.axes();
+        for obj in self.objects.values_mut().flatten() {
+            if obj.is_selected && !obj.is_locked {
+                let mut centre = obj.object.as_calc().centre();
+                centre[i] = snap.drag(centre[i], from[0], to[0]);
+                centre[j] = snap.drag(centre[j], from[1], to[1]);
+                obj.object.set_centre([centre[0], centre[1], centre[2]]);
             }
         }
     }
 
-    pub fn scale_selected(&mut self, plot_ui: &mut PlotUi, plot_view: &mut PlotView) {
-        for (_, object) in self.objects.iter_mut() {
-            let pointer_delta = plot_ui.pointer_coordinate_drag_delta();
-            match object {
-                Some(obj) => match &mut obj.object {
-                    GravityObject::Cuboid(cuboid) => {
-                        if obj.is_selected {
-                            match plot_view {
-                                PlotView::XY => {
-                                    if (cuboid.x_length + pointer_delta.x as f64) > 0. {
-                                        cuboid.x_length += pointer_delta.x as f64;
-                                    }
-                                    if (cuboid.y_length + pointer_delta.y as f64) > 0. {
-                                        cuboid.y_length += pointer_delta.y as f64;
-                                    }
-                                }
-                                PlotView::XZ => {
-                                    if (cuboid.x_length + pointer_delta.x as f64) > 0. {
-                                        cuboid.x_length += pointer_delta.x as f64;
-                                    }
-                                    if (cuboid.z_length + pointer_delta.y as f64) > 0. {
-                                        cuboid.z_length += pointer_delta.y as f64;
-                                    }
-                                }
-                                PlotView::YZ => {
-                                    if (cuboid.y_length + pointer_delta.x as f64) > 0. {
-                                        cuboid.y_length += pointer_delta.x as f64;
-                                    }
-                                    if (cuboid.z_length + pointer_delta.y as f64) > 0. {
-                                        cuboid.z_length += pointer_delta.y as f64;
-                                    }
-                                }
-                            };
+    pub fn scale_selected(&mut self, plot_ui: &mut PlotUi, plot_view: &mut PlotView, snap: &Snap) {
+        let (from, to) = Self::pointer_drag(plot_ui);
+        let [i, j] = plot_view.axes();
+        for obj in self.objects.values_mut().flatten() {
+            if !obj.is_selected || obj.is_locked {
+                continue;
+            }
+            match &mut obj.object {
+                GravityObject::Cuboid(cuboid) => {
+                    for (axis, from, to) in [(i, from[0], to[0]), (j, from[1], to[1])] {
+                        let length = match axis {
+                            0 => &mut cuboid.x_length,
+                            1 => &mut cuboid.y_length,
+                            _ => &mut cuboid.z_length,
+                        };
+                        let new_length = snap.drag(*length, from, to);
+                        if new_length > 0. {
+                            *length = new_length;
                         }
                     }
-                    GravityObject::Sphere(sphere) => {
-                        if obj.is_selected {
-                            if (sphere.radius + pointer_delta.y as f64) > 0. {
-                                sphere.radius += pointer_delta.y as f64;
-                            }
+                }
+                GravityObject::Sphere(sphere) => {
+                    let radius = snap.drag(sphere.radius, from[1], to[1]);
+                    if radius > 0. {
+                        sphere.radius = radius;
+                    }
+                }
+                GravityObject::Polyhedron(polyhedron) => {
+                    let size = polyhedron.size();
+                    let mut factors = [1.; 3];
+                    for (axis, from, to) in [(i, from[0], to[0]), (j, from[1], to[1])] {
+                        let new_size = snap.drag(size[axis], from, to);
+                        if new_size > 0. && size[axis] > 0. {
+                            factors[axis] = new_size / size[axis];
                         }
                     }
-                },
-                None => {}
+                    polyhedron.scale(factors);
+                }
+            }
+        }
+    }
+
+    /// Rotate the selected objects about their centres and the view normal, following
+    /// the pointer's angle around each centre.
+    pub fn rotate_selected(&mut self, plot_ui: &mut PlotUi, plot_view: &mut PlotView, snap: &Snap) {
+        let (from, to) = Self::pointer_drag(plot_ui);
+        let [i, j] = plot_view.axes();
+        for obj in self.objects.values_mut().flatten() {
+            if obj.is_selected && !obj.is_locked {
+                let centre = obj.object.as_calc().centre();
+                let angle = |p: [f64; 2]| (p[1] - centre[j]).atan2(p[0] - centre[i]);
+                let rotation = snap.angle_drag(angle(from), angle(to));
+                obj.object.rotate(&plot_view.rotation(rotation));
             }
         }
     }
@@ -227,25 +280,98 @@ impl Model {
                 match &mut new_object.object {
                     GravityObject::Cuboid(cuboid) => cuboid.z_centroid += 1.,
                     GravityObject::Sphere(sphere) => sphere.z_centroid += 1.,
+                    GravityObject::Polyhedron(polyhedron) => polyhedron.translate([0., 0., 1.]),
                 }
+                let new_id = new_object.id.to_string();
                 self.add_object(new_object);
+                if let Some(group) = self.group_of(&id) {
+                    self.set_group(&new_id, Some(&group));
+                }
             }
         }
     }
 
     pub fn add_object(&mut self, object: GravityModelObject) {
         if self.objects.len() < MAX_OBJECTS {
+            self.order.push(object.id.to_string());
             self.objects.insert(object.id.to_string(), Some(object));
             self.object_counter += 1;
         }
     }
 
+    /// Object ids in display order. Objects missing from `order` (e.g. from models saved
+    /// before it existed) follow in id order.
+    pub fn ordered_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .order
+            .iter()
+            .filter(|id| matches!(self.objects.get(*id), Some(Some(_))))
+            .cloned()
+            .collect();
+        for (id, object) in self.objects.iter() {
+            if object.is_some() && !ids.contains(id) {
+                ids.push(id.to_string());
+            }
+        }
+        ids
+    }
+
+    /// Move an object up (negative) or down (positive) the display order.
+    pub fn move_object(&mut self, id: &str, offset: isize) {
+        let mut ids = self.ordered_ids();
+        if let Some(index) = ids.iter().position(|other| other == id) {
+            let new_index = (index as isize + offset).clamp(0, ids.len() as isize - 1) as usize;
+            let id = ids.remove(index);
+            ids.insert(new_index, id);
+        }
+        self.order = ids;
+    }
+
+    /// Names of the groups, skipping the "None" placeholder.
+    pub fn group_names(&self) -> Vec<String> {
+        self.groups
+            .iter()
+            .filter(|(_, members)| members.is_some())
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    pub fn group_of(&self, id: &str) -> Option<String> {
+        self.groups
+            .iter()
+            .find(|(_, members)| members.as_ref().map_or(false, |m| m.contains(id)))
+            .map(|(name, _)| name.to_string())
+    }
+
+    pub fn add_group(&mut self, name: &str) {
+        if !name.is_empty() && !self.groups.contains_key(name) {
+            self.groups.insert(name.to_string(), Some(BTreeSet::new()));
+        }
+    }
+
+    /// Remove a group, its objects become ungrouped.
+    pub fn remove_group(&mut self, name: &str) {
+        if name != "None" {
+            self.groups.remove(name);
+        }
+    }
+
+    /// Move an object into `group`, or out of any group if `None`.
+    pub fn set_group(&mut self, id: &str, group: Option<&str>) {
+        for members in self.groups.values_mut().flatten() {
+            members.remove(id);
+        }
+        if let Some(Some(members)) = group.and_then(|group| self.groups.get_mut(group)) {
+            members.insert(id.to_string());
+        }
+    }
+
     pub fn delete_objects(&mut self) {
         let mut ids_to_delete: Vec<String> = vec![];
         for (id, object) in self.objects.iter_mut() {
             match object {
                 Some(obj) => {
-                    if obj.is_selected {
+                    if obj.is_selected && !obj.is_locked {
                         ids_to_delete.push(id.to_string());
                     }
                 }
@@ -253,6 +379,9 @@ impl Model {
             }
         }
         for id in ids_to_delete {
+            self.set_group(&id, None);
+            self.order.retain(|other| *other != id);
+            self.timeline.remove_object(&id);
             self.objects.remove(&id.to_string());
         }
     }
@@ -0,0 +1,229 @@
+use crate::gravity_objects::DataType;
+use crate::model::Model;
+use egui::Ui;
+use ndarray::{Array1, Array2, Axis};
+use std::f64::consts::TAU;
+
+/// Small seeded generator (SplitMix64) so that synthetic noise is reproducible.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seed from the operating system (or the browser's crypto API on the web), or from
+    /// the clock if that is unavailable.
+    pub fn random_seed() -> u64 {
+        let mut bytes = [0u8; 8];
+        match getrandom::getrandom(&mut bytes) {
+            Ok(()) => u64::from_le_bytes(bytes),
+            Err(_) => clock_seed(),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in [0, 1).
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal sample (Box-Muller).
+    pub fn normal(&mut self) -> f64 {
+        let u1 = 1. - self.uniform();
+        let u2 = self.uniform();
+        (-2. * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn clock_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn clock_seed() -> u64 {
+    (js_sys::Date::now() * 1e3) as u64
+}
+
+/// Noisy data for a survey along with the resulting signal to noise ratio.
+pub struct NoisyData {
+    pub noisy: Array1<f64>,
+    /// Standard deviation of the added noise, positioning errors and drift included.
+    pub sigma: f64,
+    /// Peak absolute clean signal over the standard deviation of the added noise.
+    pub snr: f64,
+}
+
+/// Instrument and survey errors added to synthetic data. Amplitudes are in the units of
+/// the displayed component, positioning errors in metres.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct NoiseModel {
+    pub enabled: bool,
+    pub seed: u64,
+    /// Standard deviation of white Gaussian noise.
+    pub white_sigma: f64,
+    /// Standard deviation of first-order correlated (Gauss-Markov) noise.
+    pub correlated_sigma: f64,
+    /// Correlation length of the correlated noise in stations.
+    pub correlation_length: f64,
+    /// Time between consecutive stations in seconds.
+    pub station_interval: f64,
+    /// Linear drift per hour.
+    pub drift_rate: f64,
+    /// Amplitude of the tidal-like sinusoidal drift.
+    pub tidal_amplitude: f64,
+    /// Period of the tidal-like drift in hours.
+    pub tidal_period: f64,
+    /// Probability of a tare (step offset) at each station.
+    pub tare_probability: f64,
+    /// Standard deviation of the tare steps.
+    pub tare_sigma: f64,
+    /// Standard deviation of horizontal positioning errors.
+    pub position_sigma: f64,
+    /// Standard deviation of height errors.
+    pub height_sigma: f64,
+}
+
+impl Default for NoiseModel {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: 0,
+            white_sigma: 1.,
+            correlated_sigma: 0.,
+            correlation_length: 10.,
+            station_interval: 30.,
+            drift_rate: 0.,
+            tidal_amplitude: 0.,
+            tidal_period: 12.42,
+            tare_probability: 0.,
+            tare_sigma: 0.,
+            position_sigma: 0.,
+            height_sigma: 0.,
+        }
+    }
+}
+
+impl NoiseModel {
+    /// Forward model the survey at perturbed positions and add instrument noise. Each
+    /// survey uses its own `stream` so that separate lines get independent noise.
+    pub fn apply(
+        &self,
+        model: &Model,
+        data_type: &DataType,
+        points: &Array2<f64>,
+        stream: u64,
+    ) -> NoisyData {
+        let mut rng = Rng::new(self.seed ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03));
+        let clean = model.calculate(data_type, points);
+        let n_points = points.len_of(Axis(0));
+
+        let mut measured_points = points.clone();
+        if self.position_sigma > 0. || self.height_sigma > 0. {
+            for mut point in measured_points.axis_iter_mut(Axis(0)) {
+                point[0] += self.position_sigma * rng.normal();
+                point[1] += self.position_sigma * rng.normal();
+                point[2] += self.height_sigma * rng.normal();
+            }
+        }
+        let mut noisy = if measured_points == *points {
+            clean.clone()
+        } else {
+            model.calculate(data_type, &measured_points)
+        };
+
+        let phi = if self.correlation_length > 0. {
+            (-1. / self.correlation_length).exp()
+        } else {
+            0.
+        };
+        let mut correlated = 0.;
+        let mut tare = 0.;
+        let mut noise: Array1<f64> = Array1::zeros(n_points);
+        for (i, value) in noise.iter_mut().enumerate() {
+            let hours = i as f64 * self.station_interval / 3600.;
+            correlated =
+                phi * correlated + self.correlated_sigma * (1. - phi.powi(2)).sqrt() * rng.normal();
+            if rng.uniform() < self.tare_probability {
+                tare += self.tare_sigma * rng.normal();
+            }
+            *value = self.white_sigma * rng.normal()
+                + correlated
+                + self.drift_rate * hours
+                + self.tidal_amplitude * (TAU * hours / self.tidal_period).sin()
+                + tare;
+        }
+        noisy += &noise;
+
+        let errors = &noisy - &clean;
+        let noise_std = errors.std(0.);
+        let peak = clean.iter().fold(0f64, |peak, v| peak.max(v.abs()));
+        let snr = if noise_std > 0. {
+            peak / noise_std
+        } else {
+            f64::INFINITY
+        };
+        NoisyData {
+            noisy,
+            sigma: noise_std,
+            snr,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.enabled, "Add noise to synthetic data");
+        ui.horizontal(|ui| {
+            ui.label("seed");
+            ui.add(egui::DragValue::new(&mut self.seed));
+            if ui.button("Randomise").clicked() {
+                self.seed = Rng::random_seed();
+            }
+        });
+        ui.collapsing("white", |ui| {
+            ui.label("sigma");
+            ui.add(egui::Slider::new(&mut self.white_sigma, 0.0..=100.).logarithmic(true));
+        });
+        ui.collapsing("correlated", |ui| {
+            ui.label("sigma");
+            ui.add(egui::Slider::new(&mut self.correlated_sigma, 0.0..=100.).logarithmic(true));
+            ui.label("correlation length");
+            ui.add(egui::Slider::new(&mut self.correlation_length, 0.0..=200.).text("stations"));
+        });
+        ui.collapsing("drift", |ui| {
+            ui.label("time between stations");
+            ui.add(egui::Slider::new(&mut self.station_interval, 1.0..=600.).text("s"));
+            ui.label("linear drift");
+            ui.add(egui::Slider::new(&mut self.drift_rate, -100.0..=100.).text("per hour"));
+            ui.label("tidal amplitude");
+            ui.add(egui::Slider::new(&mut self.tidal_amplitude, 0.0..=100.));
+            ui.label("tidal period");
+            ui.add(egui::Slider::new(&mut self.tidal_period, 1.0..=25.).text("hours"));
+        });
+        ui.collapsing("tares", |ui| {
+            ui.label("probability per station");
+            ui.add(egui::Slider::new(&mut self.tare_probability, 0.0..=0.1));
+            ui.label("step sigma");
+            ui.add(egui::Slider::new(&mut self.tare_sigma, 0.0..=100.));
+        });
+        ui.collapsing("positioning", |ui| {
+            ui.label("horizontal sigma");
+            ui.add(egui::Slider::new(&mut self.position_sigma, 0.0..=1.).text("m"));
+            ui.label("height sigma");
+            ui.add(egui::Slider::new(&mut self.height_sigma, 0.0..=1.).text("m"));
+        });
+    }
+}
@@ -0,0 +1,213 @@
+use crate::model::Model;
+use egui::{Id, Rect, Sense, Ui};
+
+/// List of the model objects and groups with selection, visibility, lock, rename and
+/// reordering. Objects are moved between groups by dragging their handle.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct Outliner {
+    pub open: bool,
+    new_group: String,
+    /// Id of the object being renamed.
+    #[serde(skip)]
+    renaming: Option<String>,
+    #[serde(skip)]
+    focus_rename: bool,
+    /// Id of the object being dragged.
+    #[serde(skip)]
+    dragging: Option<String>,
+}
+
+impl Default for Outliner {
+    fn default() -> Self {
+        Self {
+            open: true,
+            new_group: String::new(),
+            renaming: None,
+            focus_rename: false,
+            dragging: None,
+        }
+    }
+}
+
+/// Deferred change to the model from an object row.
+enum RowAction {
+    Select,
+    Move(isize),
+}
+
+impl Outliner {
+    pub fn ui(&mut self, ui: &mut Ui, model: &mut Model) {
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_group);
+            if ui.button("Add group").clicked() {
+                model.add_group(self.new_group.trim());
+                self.new_group.clear();
+            }
+        });
+        ui.label("Double click to rename, drag ☰ into a group.");
+        ui.separator();
+
+        let ids = model.ordered_ids();
+        let mut drop_zones: Vec<(Rect, Option<String>)> = vec![];
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for group in model.group_names() {
+                let members: Vec<String> = ids
+                    .iter()
+                    .filter(|id| model.group_of(id).as_deref() == Some(&group))
+                    .cloned()
+                    .collect();
+                let response = ui.group(|ui| {
+                    ui.set_min_width(ui.available_width());
+                    self.group_header(ui, model, &group, &members);
+                    for id in &members {
+                        self.object_row(ui, model, id);
+                    }
+                    if members.is_empty() {
+                        ui.weak("Drop objects here");
+                    }
+                });
+                drop_zones.push((response.response.rect, Some(group)));
+            }
+
+            let response = ui.group(|ui| {
+                ui.set_min_width(ui.available_width());
+                ui.strong("Ungrouped");
+                let ungrouped: Vec<String> = ids
+                    .iter()
+                    .filter(|id| model.group_of(id).is_none())
+                    .cloned()
+                    .collect();
+                for id in &ungrouped {
+                    self.object_row(ui, model, id);
+                }
+            });
+            drop_zones.push((response.response.rect, None));
+        });
+
+        if let Some(id) = self.dragging.clone() {
+            if let Some(Some(obj)) = model.objects.get(&id) {
+                egui::show_tooltip_at_pointer(ui.ctx(), Id::new("outliner_drag"), |ui| {
+                    ui.label(&obj.name);
+                });
+            }
+            if ui.input().pointer.any_released() {
+                if let Some(pos) = ui.input().pointer.interact_pos() {
+                    if let Some((_, group)) = drop_zones.iter().find(|(rect, _)| rect.contains(pos))
+                    {
+                        model.set_group(&id, group.as_deref());
+                    }
+                }
+                self.dragging = None;
+            }
+        }
+    }
+
+    fn group_header(&mut self, ui: &mut Ui, model: &mut Model, group: &str, members: &[String]) {
+        let objects = || {
+            members
+                .iter()
+                .filter_map(|id| model.objects.get(id))
+                .flatten()
+        };
+        let any_visible = objects().any(|obj| !obj.is_hidden);
+        let all_locked = !members.is_empty() && objects().all(|obj| obj.is_locked);
+        let all_selected = !members.is_empty() && objects().all(|obj| obj.is_selected);
+        ui.horizontal(|ui| {
+            let mut visible = any_visible;
+            let mut locked = all_locked;
+            let visibility = ui.toggle_value(&mut visible, "👁").on_hover_text("Visible");
+            let lock = ui.toggle_value(&mut locked, "🔒").on_hover_text("Locked");
+            let select = ui.selectable_label(all_selected, egui::RichText::new(group).strong());
+            let remove = ui.small_button("✖").on_hover_text("Remove group");
+
+            let additive = ui.input().modifiers.command || ui.input().modifiers.shift;
+            if select.clicked() && !additive {
+                model.deselect_all();
+            }
+            for id in members {
+                let Some(Some(obj)) = model.objects.get_mut(id) else {
+                    continue;
+                };
+                if visibility.changed() {
+                    obj.is_hidden = !visible;
+                }
+                if lock.changed() {
+                    obj.is_locked = locked;
+                }
+                if select.clicked() {
+                    obj.is_selected = !all_selected || !additive;
+                }
+            }
+            if remove.clicked() {
+                model.remove_group(group);
+            }
+        });
+    }
+
+    fn object_row(&mut self, ui: &mut Ui, model: &mut Model, id: &str) {
+        let mut action = None;
+        if let Some(Some(obj)) = model.objects.get_mut(id) {
+            ui.horizontal(|ui| {
+                let handle = ui
+                    .add(egui::Label::new("☰").sense(Sense::drag()))
+                    .on_hover_text("Drag into a group");
+                if handle.drag_started() {
+                    self.dragging = Some(id.to_string());
+                }
+                ui.color_edit_button_srgba(&mut obj.colour);
+                let mut visible = !obj.is_hidden;
+                if ui
+                    .toggle_value(&mut visible, "👁")
+                    .on_hover_text("Visible")
+                    .changed()
+                {
+                    obj.is_hidden = !visible;
+                }
+                ui.toggle_value(&mut obj.is_locked, "🔒")
+                    .on_hover_text("Locked");
+
+                if self.renaming.as_deref() == Some(id) {
+                    let response = ui.text_edit_singleline(&mut obj.name);
+                    if self.focus_rename {
+                        response.request_focus();
+                        self.focus_rename = false;
+                    } else if response.lost_focus() {
+                        self.renaming = None;
+                    }
+                } else {
+                    let response = ui.selectable_label(obj.is_selected, &obj.name);
+                    if response.double_clicked() {
+                        self.renaming = Some(id.to_string());
+                        self.focus_rename = true;
+                    } else if response.clicked() {
+                        action = Some(RowAction::Select);
+                    }
+                }
+
+                let calc = obj.object.as_calc();
+                ui.weak(format!("{:.2} m³, {:.0} kg", calc.volume(), calc.mass()));
+                if ui.small_button("⏶").on_hover_text("Move up").clicked() {
+                    action = Some(RowAction::Move(-1));
+                }
+                if ui.small_button("⏷").on_hover_text("Move down").clicked() {
+                    action = Some(RowAction::Move(1));
+                }
+            });
+        }
+
+        match action {
+            Some(RowAction::Select) => {
+                let additive = ui.input().modifiers.command || ui.input().modifiers.shift;
+                if !additive {
+                    model.deselect_all();
+                }
+                if let Some(Some(obj)) = model.objects.get_mut(id) {
+                    obj.is_selected = !obj.is_selected || !additive;
+                }
+            }
+            Some(RowAction::Move(offset)) => model.move_object(id, offset),
+            None => {}
+        }
+    }
+}
@@ -1,8 +1,505 @@
-use crate::gravity_objects;
+use crate::gravity_objects::{
+    rotation_matrix_x, rotation_matrix_y, rotation_matrix_z, GravityCalc, GravityObject,
+    TriangleMesh,
+};
+use egui::plot::{LinkedAxisGroup, LinkedCursorsGroup};
+use egui::Ui;
+use ndarray::Array2;
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
 
-#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    serde::Deserialize, serde::Serialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug,
+)]
 pub enum PlotView {
     XY,
     XZ,
     YZ,
 }
+
+impl PlotView {
+    /// Model axes shown horizontally and vertically in the view.
+    pub fn axes(&self) -> [usize; 2] {
+        match self {
+            PlotView::XY => [0, 1],
+            PlotView::XZ => [0, 2],
+            PlotView::YZ => [1, 2],
+        }
+    }
+
+    /// Distance of a point towards the viewer. The XY view looks down from +z, XZ looks
+    /// along +y and YZ along -x.
+    pub fn depth(&self, point: &[f64]) -> f64 {
+        match self {
+            PlotView::XY => point[2],
+            PlotView::XZ => -point[1],
+            PlotView::YZ => point[0],
+        }
+    }
+
+    /// Outline of an object projected into the view.
+    pub fn footprint(&self, object: &GravityObject) -> Footprint {
+        let [i, j] = self.axes();
+        match object {
+            GravityObject::Cuboid(cuboid) => {
+                let verts = cuboid.vertices_rotated();
+                let points = verts.rows().into_iter().map(|v| [v[i], v[j]]).collect();
+                Footprint::Polygon(convex_hull(points))
+            }
+            GravityObject::Polyhedron(polyhedron) => {
+                let points = polyhedron
+                    .mesh
+                    .vertices
+                    .iter()
+                    .map(|v| [v[i], v[j]])
+                    .collect();
+                Footprint::Polygon(convex_hull(points))
+            }
+            GravityObject::Sphere(sphere) => {
+                let centre = sphere.centre();
+                Footprint::Circle {
+                    centre: [centre[i], centre[j]],
+                    radius: sphere.radius,
+                }
+            }
+        }
+    }
+
+    /// Rotation by `angle` about the view normal, anticlockwise as seen in the view.
+    pub fn rotation(&self, angle: f64) -> Array2<f64> {
+        match self {
+            PlotView::XY => rotation_matrix_z(angle),
+            PlotView::XZ => rotation_matrix_y(-angle),
+            PlotView::YZ => rotation_matrix_x(angle),
+        }
+    }
+}
+
+/// Projected outline of an object in a model view.
+pub enum Footprint {
+    /// Convex polygon, anticlockwise.
+    Polygon(Vec<[f64; 2]>),
+    Circle {
+        centre: [f64; 2],
+        radius: f64,
+    },
+}
+
+impl Footprint {
+    pub fn contains(&self, point: [f64; 2]) -> bool {
+        match self {
+            Footprint::Polygon(vertices) => {
+                vertices.len() >= 3
+                    && (0..vertices.len()).all(|k| {
+                        cross(vertices[k], vertices[(k + 1) % vertices.len()], point) >= 0.
+                    })
+            }
+            Footprint::Circle { centre, radius } => {
+                (point[0] - centre[0]).hypot(point[1] - centre[1]) <= *radius
+            }
+        }
+    }
+
+    /// Whether the outline lies entirely inside the box from `min` to `max`.
+    pub fn within(&self, min: [f64; 2], max: [f64; 2]) -> bool {
+        let inside = |p: [f64; 2]| (0..2).all(|k| min[k] <= p[k] && p[k] <= max[k]);
+        match self {
+            Footprint::Polygon(vertices) => vertices.iter().all(|v| inside(*v)),
+            Footprint::Circle { centre, radius } => {
+                inside([centre[0] - radius, centre[1] - radius])
+                    && inside([centre[0] + radius, centre[1] + radius])
+            }
+        }
+    }
+}
+
+/// Z component of (b - a) x (p - a), positive when p is left of a -> b.
+fn cross(a: [f64; 2], b: [f64; 2], p: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+/// Anticlockwise convex hull (monotone chain).
+pub fn convex_hull(mut points: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<[f64; 2]> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        for p in points.iter() {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], *p) <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+        hull.pop();
+        if pass == 0 {
+            points.reverse();
+        }
+    }
+    hull
+}
+
+/// Snapping applied while dragging objects in the model views.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct Snap {
+    pub enabled: bool,
+    /// Grid spacing in metres for positions and sizes.
+    pub grid: f64,
+    /// Angle increment in degrees.
+    pub angle: f64,
+}
+
+impl Default for Snap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            grid: 0.5,
+            angle: 15.,
+        }
+    }
+}
+
+impl Snap {
+    pub fn position(&self, value: f64) -> f64 {
+        if self.enabled && self.grid > 0. {
+            (value / self.grid).round() * self.grid
+        } else {
+            value
+        }
+    }
+
+    /// New value after dragging the pointer from `from` to `to`. When snapping, the
+    /// value moves in whole grid steps as the pointer crosses grid lines and lands on
+    /// the grid.
+    pub fn drag(&self, value: f64, from: f64, to: f64) -> f64 {
+        if self.enabled {
+            self.position(value + self.position(to) - self.position(from))
+        } else {
+            value + to - from
+        }
+    }
+
+    /// Rotation in radians for the pointer turning from angle `from` to `to`, in whole
+    /// angle increments when snapping.
+    pub fn angle_drag(&self, from: f64, to: f64) -> f64 {
+        let step = self.angle.to_radians();
+        let delta = if self.enabled && step > 0. {
+            ((to / step).round() - (from / step).round()) * step
+        } else {
+            to - from
+        };
+        (delta + PI).rem_euclid(TAU) - PI
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.enabled, "Snap while dragging");
+        ui.add(egui::Slider::new(&mut self.grid, 0.05..=10.).text("grid m"));
+        ui.add(egui::Slider::new(&mut self.angle, 1.0..=90.).text("angle deg"));
+    }
+}
+
+/// Slicing of the bodies by the vertical plane under the x or y profile, in the XZ and
+/// YZ views.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct SectionSlice {
+    /// Draw the part of each body within the slab instead of its full projection.
+    pub enabled: bool,
+    /// Full width of the slab centred on the profile plane, 0 for a true slice.
+    pub thickness: f64,
+    /// Distance beyond the slab over which bodies outside it fade out.
+    pub fade_distance: f64,
+    /// Leave out bodies that don't reach the slab.
+    pub hide_outside: bool,
+}
+
+impl Default for SectionSlice {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            thickness: 0.,
+            fade_distance: 50.,
+            hide_outside: false,
+        }
+    }
+}
+
+/// A body cut by the section slab.
+pub enum Section {
+    /// Outline of the part of the body within the slab.
+    Inside(Footprint),
+    /// Closed outlines where a surface is cut, not necessarily convex. Concave bodies
+    /// and bodies with several parts give several loops.
+    Cut(Vec<Vec<[f64; 2]>>),
+    /// Full outline of a body that misses the slab, and its distance from the slab.
+    Outside(Footprint, f64),
+}
+
+impl SectionSlice {
+    /// Cut `object` by the slab around the plane at `plane` along the view normal. Spheres
+    /// keep their largest circle within the slab. Cuboids and meshes are cut by the plane
+    /// itself, or for a body within the slab that doesn't reach it, at the body's level
+    /// nearest to the plane.
+    pub fn section(&self, object: &GravityObject, plot_view: &PlotView, plane: f64) -> Section {
+        let [i, j] = plot_view.axes();
+        let n = 3 - i - j;
+        let half = self.thickness.max(0.) / 2.;
+        let [low, high] = [plane - half, plane + half];
+        match object {
+            GravityObject::Sphere(sphere) => {
+                let centre = sphere.centre();
+                let distance = ((centre[n] - plane).abs() - half).max(0.);
+                let centre = [centre[i], centre[j]];
+                if distance < sphere.radius {
+                    Section::Inside(Footprint::Circle {
+                        centre,
+                        radius: (sphere.radius.powi(2) - distance.powi(2)).sqrt(),
+                    })
+                } else {
+                    Section::Outside(
+                        Footprint::Circle {
+                            centre,
+                            radius: sphere.radius,
+                        },
+                        distance - sphere.radius,
+                    )
+                }
+            }
+            _ => {
+                let mesh = object.mesh();
+                let [min, max] = mesh.bounds();
+                if min[n] > high || max[n] < low {
+                    let distance = (min[n] - high).max(low - max[n]);
+                    return Section::Outside(plot_view.footprint(object), distance);
+                }
+                // Just inside the body's extent so that a face lying in the plane is cut.
+                let margin = 1e-6 * (max[n] - min[n]);
+                let level = plane.clamp(min[n] + margin, max[n] - margin);
+                Section::Cut(cut_loops(&mesh, [i, j, n], level))
+            }
+        }
+    }
+
+    /// Opacity of a body `distance` outside the slab, fading to a faint outline.
+    pub fn fade(&self, distance: f64) -> f32 {
+        let t = if self.fade_distance > 0. {
+            (distance / self.fade_distance).clamp(0., 1.)
+        } else {
+            1.
+        };
+        (0.6 - 0.5 * t) as f32
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.enabled, "Slice bodies by the profile plane");
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.add(
+                egui::Slider::new(&mut self.thickness, 0.0..=100.)
+                    .text("slab thickness m")
+                    .clamp_to_range(false),
+            );
+            ui.add(
+                egui::Slider::new(&mut self.fade_distance, 0.0..=500.)
+                    .text("fade distance m")
+                    .clamp_to_range(false),
+            );
+            ui.checkbox(&mut self.hide_outside, "Hide bodies outside the slab");
+        });
+    }
+}
+
+/// Closed loops where the plane at `level` along axis `n` cuts the surface of `mesh`, in
+/// the `i` and `j` coordinates. Each triangle straddling the plane gives a segment between
+/// two of its edges, and the segments are chained through the edges they share. Edges are
+/// keyed by their end points so that meshes with unwelded vertices chain too.
+fn cut_loops(mesh: &TriangleMesh, [i, j, n]: [usize; 3], level: f64) -> Vec<Vec<[f64; 2]>> {
+    type Key = [[u64; 3]; 2];
+    let bits = |v: [f64; 3]| v.map(f64::to_bits);
+    let mut points: HashMap<Key, [f64; 2]> = HashMap::new();
+    let mut segments: Vec<[Key; 2]> = vec![];
+    let mut edges: HashMap<Key, Vec<usize>> = HashMap::new();
+    for triangle in &mesh.triangles {
+        let mut crossings = vec![];
+        for k in 0..3 {
+            let mut a = mesh.vertices[triangle[k]];
+            let mut b = mesh.vertices[triangle[(k + 1) % 3]];
+            if (a[n] > level) == (b[n] > level) {
+                continue;
+            }
+            // The same point from both triangles sharing the edge.
+            if bits(a) > bits(b) {
+                std::mem::swap(&mut a, &mut b);
+            }
+            let key = [bits(a), bits(b)];
+            let t = (level - a[n]) / (b[n] - a[n]);
+            points.insert(key, [a[i] + t * (b[i] - a[i]), a[j] + t * (b[j] - a[j])]);
+            crossings.push(key);
+        }
+        if let [a, b] = crossings[..] {
+            for key in [a, b] {
+                edges.entry(key).or_default().push(segments.len());
+            }
+            segments.push([a, b]);
+        }
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut loops = vec![];
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let [first, mut key] = segments[start];
+        let mut outline = vec![points[&first]];
+        while key != first {
+            outline.push(points[&key]);
+            let next = edges[&key].iter().copied().find(|&s| !used[s]);
+            let Some(next) = next else {
+                // An open surface, leave the chain open.
+                break;
+            };
+            used[next] = true;
+            let [a, b] = segments[next];
+            key = if a == key { b } else { a };
+        }
+        if outline.len() >= 3 {
+            loops.push(outline);
+        }
+    }
+    loops
+}
+
+/// Whether a closed outline is convex, so that it can be filled by the plots.
+pub fn is_convex(outline: &[[f64; 2]]) -> bool {
+    let n = outline.len();
+    // Ignore collinear points, e.g. where the cut crosses the diagonal of a face.
+    let size = outline
+        .iter()
+        .map(|p| p[0].abs().max(p[1].abs()))
+        .fold(0., f64::max);
+    let turns: Vec<f64> = (0..n)
+        .map(|k| cross(outline[k], outline[(k + 1) % n], outline[(k + 2) % n]))
+        .filter(|turn| turn.abs() > 1e-9 * size * size)
+        .collect();
+    turns.iter().all(|&turn| turn > 0.) || turns.iter().all(|&turn| turn < 0.)
+}
+
+/// How the components selected for comparison are laid out.
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ComponentLayout {
+    ProfilesXZ,
+    ProfilesYZ,
+    TensorGrid,
+}
+
+/// Axis and cursor links shared between the data, model and component plots.
+/// Index 0 is the x profile (XZ), index 1 the y profile (YZ).
+pub struct PlotGroup {
+    pub axes: [LinkedAxisGroup; 2],
+    pub cursors: [LinkedCursorsGroup; 2],
+    pub axes_xy: LinkedAxisGroup,
+    pub cursors_xy: LinkedCursorsGroup,
+    /// Links for the windows of the polyline profiles, by profile index.
+    pub polylines: Vec<(LinkedAxisGroup, LinkedCursorsGroup)>,
+}
+
+impl PlotGroup {
+    /// Axis and cursor links for the polyline profile at `index`.
+    pub fn polyline(&mut self, index: usize) -> &(LinkedAxisGroup, LinkedCursorsGroup) {
+        while self.polylines.len() <= index {
+            self.polylines
+                .push((LinkedAxisGroup::new(true, false), LinkedCursorsGroup::x()));
+        }
+        &self.polylines[index]
+    }
+}
+
+impl Default for PlotGroup {
+    fn default() -> Self {
+        Self {
+            axes: [
+                LinkedAxisGroup::new(true, false),
+                LinkedAxisGroup::new(true, false),
+            ],
+            cursors: [LinkedCursorsGroup::x(), LinkedCursorsGroup::x()],
+            axes_xy: LinkedAxisGroup::both(),
+            cursors_xy: LinkedCursorsGroup::both(),
+            polylines: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(outline: &[[f64; 2]]) -> f64 {
+        let n = outline.len();
+        (0..n)
+            .map(|k| cross([0., 0.], outline[k], outline[(k + 1) % n]))
+            .sum::<f64>()
+            .abs()
+            / 2.
+    }
+
+    /// L-shaped outline in x and z, extruded from y = -1 to 1.
+    fn l_prism() -> TriangleMesh {
+        let outline = [[0., 0.], [2., 0.], [2., 1.], [1., 1.], [1., 2.], [0., 2.]];
+        let vertices = [-1., 1.]
+            .iter()
+            .flat_map(|&y| outline.iter().map(move |p| [p[0], y, p[1]]))
+            .collect();
+        let mut triangles = vec![];
+        for k in 0..6 {
+            let next = (k + 1) % 6;
+            triangles.push([k, next, next + 6]);
+            triangles.push([k, next + 6, k + 6]);
+        }
+        // Fan the caps from the reflex corner, which sees the whole L.
+        for k in [4, 5, 0, 1] {
+            let next = (k + 1) % 6;
+            triangles.push([3, k, next]);
+            triangles.push([9, next + 6, k + 6]);
+        }
+        TriangleMesh {
+            vertices,
+            triangles,
+        }
+    }
+
+    #[test]
+    fn concave_cut() {
+        let loops = cut_loops(&l_prism(), [0, 2, 1], 0.3);
+        assert_eq!(loops.len(), 1);
+        assert!((area(&loops[0]) - 3.).abs() < 1e-9);
+        assert!(!is_convex(&loops[0]));
+    }
+
+    #[test]
+    fn separate_pieces() {
+        let mut mesh = l_prism();
+        let mut other = l_prism();
+        for vertex in &mut other.vertices {
+            vertex[0] += 5.;
+        }
+        let offset = mesh.vertices.len();
+        mesh.vertices.extend(other.vertices);
+        mesh.triangles
+            .extend(other.triangles.iter().map(|t| t.map(|v| v + offset)));
+        let loops = cut_loops(&mesh, [0, 2, 1], -0.5);
+        assert_eq!(loops.len(), 2);
+        assert!(loops
+            .iter()
+            .all(|outline| (area(outline) - 3.).abs() < 1e-9));
+
+        let square = [[0., 0.], [1., 0.], [1., 0.5], [1., 1.], [0., 1.]];
+        assert!(is_convex(&square));
+    }
+}
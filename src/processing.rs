@@ -0,0 +1,734 @@
+use crate::background::Regional;
+use crate::export::save_text;
+use crate::import::{pick_file, PickedFile};
+use crate::map::GridData;
+use egui::{Color32, Ui};
+use ndarray::{Array1, Array2};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::f64::consts::TAU;
+
+/// Colour of processed traces in the profile plots.
+pub const COLOUR: Color32 = Color32::from_rgb(220, 60, 40);
+/// Default damping of downward continuation, limiting the gain to about 16.
+const DAMPING: f64 = 1e-3;
+
+fn default_damping() -> f64 {
+    DAMPING
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    X,
+    Y,
+    /// Magnitude of the horizontal gradient.
+    Total,
+}
+
+/// Processing step on a profile or grid. Filters act in the wavenumber domain on the
+/// data mirrored at its ends, which keeps it periodic without edge jumps.
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Clone, Copy)]
+pub enum Operation {
+    /// Subtract the model background, the regional field and layers, from the data.
+    /// Applied by the batch, which is given the background at the stations.
+    RemoveBackground,
+    /// Subtract the least-squares polynomial of the given order.
+    RemoveTrend {
+        order: usize,
+    },
+    /// Continue the field upwards (positive) or downwards (negative) by `height` metres.
+    /// Downward continuation amplifies noise, so its response e^(k|h|) is damped
+    /// Tikhonov-style to e^(k|h|) / (1 + damping e^(2k|h|)), which peaks at
+    /// 1 / (2 √damping) instead of growing without bound.
+    Continuation {
+        height: f64,
+        #[serde(default = "default_damping")]
+        damping: f64,
+    },
+    /// Derivative with z positive down, i.e. towards the sources.
+    VerticalDerivative {
+        order: u32,
+    },
+    /// Along the line for profiles.
+    HorizontalDerivative {
+        direction: Direction,
+    },
+    /// Reduction to the pole of total-field magnetic data with induced magnetisation,
+    /// grids only. Declination is clockwise from y, unstable at low inclinations.
+    ReductionToPole {
+        inclination: f64,
+        declination: f64,
+    },
+    /// Butterworth filters with the cut-off at `wavelength` metres.
+    LowPass {
+        wavelength: f64,
+    },
+    HighPass {
+        wavelength: f64,
+    },
+}
+
+impl Operation {
+    pub const ALL: [Operation; 8] = [
+        Operation::RemoveBackground,
+        Operation::RemoveTrend { order: 1 },
+        Operation::Continuation {
+            height: 1.,
+            damping: DAMPING,
+        },
+        Operation::VerticalDerivative { order: 1 },
+        Operation::HorizontalDerivative {
+            direction: Direction::Total,
+        },
+        Operation::ReductionToPole {
+            inclination: 65.,
+            declination: 0.,
+        },
+        Operation::LowPass { wavelength: 5. },
+        Operation::HighPass { wavelength: 5. },
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Operation::RemoveBackground => "Remove background",
+            Operation::RemoveTrend { .. } => "Remove trend",
+            Operation::Continuation { .. } => "Continuation",
+            Operation::VerticalDerivative { .. } => "Vertical derivative",
+            Operation::HorizontalDerivative { .. } => "Horizontal derivative",
+            Operation::ReductionToPole { .. } => "Reduction to pole",
+            Operation::LowPass { .. } => "Low-pass",
+            Operation::HighPass { .. } => "High-pass",
+        }
+    }
+
+    /// Wavenumber response, `None` for operations that aren't a linear filter.
+    fn response(&self, kx: f64, ky: f64) -> Option<Complex<f64>> {
+        let k = kx.hypot(ky);
+        let butterworth = |wavelength: f64| 1. / (1. + (k * wavelength / TAU).powi(8));
+        let response = match *self {
+            Operation::Continuation { height, damping } => {
+                let gain = (-k * height).exp();
+                if height < 0. {
+                    Complex::from(gain / (1. + damping * gain * gain))
+                } else {
+                    Complex::from(gain)
+                }
+            }
+            Operation::VerticalDerivative { order } => Complex::from(k.powi(order as i32)),
+            Operation::HorizontalDerivative {
+                direction: Direction::X,
+            } => Complex::new(0., kx),
+            Operation::HorizontalDerivative {
+                direction: Direction::Y,
+            } => Complex::new(0., ky),
+            Operation::ReductionToPole {
+                inclination,
+                declination,
+            } => {
+                if k == 0. {
+                    return Some(Complex::from(1.));
+                }
+                let (inclination, declination) =
+                    (inclination.to_radians(), declination.to_radians());
+                let direction = Complex::new(
+                    k * inclination.sin(),
+                    inclination.cos() * (kx * declination.sin() + ky * declination.cos()),
+                );
+                Complex::from(k * k) / (direction * direction)
+            }
+            Operation::LowPass { wavelength } => Complex::from(butterworth(wavelength)),
+            Operation::HighPass { wavelength } => Complex::from(1. - butterworth(wavelength)),
+            Operation::RemoveBackground
+            | Operation::RemoveTrend { .. }
+            | Operation::HorizontalDerivative {
+                direction: Direction::Total,
+            } => return None,
+        };
+        Some(response)
+    }
+
+    /// Apply to evenly spaced profile values.
+    pub fn apply_profile(&self, spacing: f64, values: &Array1<f64>) -> Array1<f64> {
+        let n = values.len();
+        if n < 2 {
+            return values.clone();
+        }
+        match *self {
+            Operation::RemoveTrend { order } => {
+                let t = |i: usize| 2. * i as f64 / (n - 1) as f64 - 1.;
+                let rows: Vec<Vec<f64>> = (0..n)
+                    .map(|i| (0..=order as i32).map(|p| t(i).powi(p)).collect())
+                    .collect();
+                let coefficients = least_squares(&rows, &values.to_vec());
+                Array1::from_iter(
+                    rows.iter()
+                        .zip(values)
+                        .map(|(row, v)| v - dot(row, &coefficients)),
+                )
+            }
+            // Along the line, both are the same derivative.
+            Operation::HorizontalDerivative { direction } => {
+                let derivative = filter_1d(values, spacing, |k| Complex::new(0., k));
+                if direction == Direction::Total {
+                    derivative.mapv(f64::abs)
+                } else {
+                    derivative
+                }
+            }
+            Operation::RemoveBackground => values.clone(),
+            // Needs both horizontal wavenumbers, skipped with a note in the batch editor.
+            Operation::ReductionToPole { .. } => values.clone(),
+            _ => filter_1d(values, spacing, |k| self.response(k, 0.).unwrap()),
+        }
+    }
+
+    pub fn apply_grid(&self, grid: &GridData) -> GridData {
+        let (nx, ny) = grid.values.dim();
+        if nx < 2 || ny < 2 {
+            return grid.clone();
+        }
+        let spacing = [grid.x[1] - grid.x[0], grid.y[1] - grid.y[0]];
+        let values = match *self {
+            Operation::RemoveBackground => return grid.clone(),
+            Operation::RemoveTrend { order } => {
+                let t = |i: usize, n: usize| 2. * i as f64 / (n - 1) as f64 - 1.;
+                let terms = Regional::terms(order);
+                let rows: Vec<Vec<f64>> = grid
+                    .values
+                    .indexed_iter()
+                    .map(|((i, j), _)| {
+                        terms
+                            .iter()
+                            .map(|(p, q)| t(i, nx).powi(*p) * t(j, ny).powi(*q))
+                            .collect()
+                    })
+                    .collect();
+                let values: Vec<f64> = grid.values.iter().copied().collect();
+                let coefficients = least_squares(&rows, &values);
+                Array2::from_shape_fn((nx, ny), |(i, j)| {
+                    grid.values[[i, j]] - dot(&rows[i * ny + j], &coefficients)
+                })
+            }
+            Operation::HorizontalDerivative {
+                direction: Direction::Total,
+            } => {
+                let dx = filter_2d(&grid.values, spacing, |kx, _| Complex::new(0., kx));
+                let dy = filter_2d(&grid.values, spacing, |_, ky| Complex::new(0., ky));
+                (dx.mapv(|v| v * v) + dy.mapv(|v| v * v)).mapv(f64::sqrt)
+            }
+            _ => filter_2d(&grid.values, spacing, |kx, ky| {
+                self.response(kx, ky).unwrap()
+            }),
+        };
+        GridData {
+            x: grid.x.clone(),
+            y: grid.y.clone(),
+            values,
+        }
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        match self {
+            Operation::RemoveBackground => {}
+            Operation::RemoveTrend { order } => {
+                ui.add(egui::DragValue::new(order).clamp_range(0..=Regional::MAX_ORDER))
+                    .on_hover_text("Polynomial order");
+            }
+            Operation::Continuation { height, damping } => {
+                ui.add(egui::DragValue::new(height).speed(0.05).suffix(" m"))
+                    .on_hover_text("Positive is upwards");
+                if *height < 0. {
+                    ui.add(egui::Slider::new(damping, 1e-8..=1.).logarithmic(true))
+                        .on_hover_text("Damping of downward continuation, larger is smoother");
+                }
+            }
+            Operation::VerticalDerivative { order } => {
+                ui.add(egui::DragValue::new(order).clamp_range(1..=2))
+                    .on_hover_text("Order");
+            }
+            Operation::HorizontalDerivative { direction } => {
+                for (value, label) in [
+                    (Direction::X, "x"),
+                    (Direction::Y, "y"),
+                    (Direction::Total, "total"),
+                ] {
+                    ui.radio_value(direction, value, label);
+                }
+            }
+            Operation::ReductionToPole {
+                inclination,
+                declination,
+            } => {
+                ui.add(
+                    egui::DragValue::new(inclination)
+                        .clamp_range(-90.0..=90.0)
+                        .prefix("I ")
+                        .suffix("°"),
+                );
+                ui.add(egui::DragValue::new(declination).prefix("D ").suffix("°"));
+            }
+            Operation::LowPass { wavelength } | Operation::HighPass { wavelength } => {
+                ui.add(
+                    egui::DragValue::new(wavelength)
+                        .speed(0.1)
+                        .clamp_range(0.01..=f64::INFINITY)
+                        .suffix(" m"),
+                )
+                .on_hover_text("Cut-off wavelength");
+            }
+        }
+    }
+}
+
+/// Named sequence of operations, applied in order.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub name: String,
+    pub operations: Vec<Operation>,
+}
+
+impl Batch {
+    /// `background` holds the model background at the stations.
+    pub fn apply_profile(
+        &self,
+        spacing: f64,
+        values: &Array1<f64>,
+        background: &Array1<f64>,
+    ) -> Array1<f64> {
+        self.operations
+            .iter()
+            .fold(values.clone(), |values, operation| match operation {
+                Operation::RemoveBackground => values - background,
+                _ => operation.apply_profile(spacing, &values),
+            })
+    }
+
+    /// `background` holds the model background at the grid nodes.
+    pub fn apply_grid(&self, grid: &GridData, background: &Array2<f64>) -> GridData {
+        self.operations
+            .iter()
+            .fold(grid.clone(), |grid, operation| match operation {
+                Operation::RemoveBackground => GridData {
+                    values: grid.values - background,
+                    ..grid
+                },
+                _ => operation.apply_grid(&grid),
+            })
+    }
+}
+
+/// Batches of processing operations and the one applied to the data in the plots.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct Processing {
+    pub enabled: bool,
+    pub batches: Vec<Batch>,
+    pub active: usize,
+    /// Show the processed grid on the map instead of the data.
+    pub map_processed: bool,
+    #[serde(skip)]
+    picked: PickedFile,
+    #[serde(skip)]
+    status: String,
+}
+
+impl Default for Processing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batches: vec![Batch {
+                name: "Residual".to_string(),
+                operations: vec![Operation::RemoveTrend { order: 1 }],
+            }],
+            active: 0,
+            map_processed: false,
+            picked: PickedFile::default(),
+            status: String::new(),
+        }
+    }
+}
+
+impl Processing {
+    fn batch(&self) -> Option<&Batch> {
+        self.batches
+            .get(self.active)
+            .filter(|batch| self.enabled && !batch.operations.is_empty())
+    }
+
+    /// Processed values of a profile with stations at `positions`, if processing is on.
+    /// `background` is the model background there, for removing it.
+    pub fn profile(
+        &self,
+        positions: &[f64],
+        values: &Array1<f64>,
+        background: &Array1<f64>,
+    ) -> Option<Array1<f64>> {
+        let spacing = match positions {
+            [first, second, ..] => second - first,
+            _ => 1.,
+        };
+        Some(self.batch()?.apply_profile(spacing, values, background))
+    }
+
+    /// Processed grid, if processing is on, with the model background at its nodes.
+    pub fn grid(&self, grid: &GridData, background: &Array2<f64>) -> Option<GridData> {
+        Some(self.batch()?.apply_grid(grid, background))
+    }
+
+    pub fn is_map_processed(&self) -> bool {
+        self.map_processed && self.batch().is_some()
+    }
+
+    /// Grid shown on the map, processed when chosen.
+    pub fn map_grid(&self, grid: GridData, background: &Array2<f64>) -> GridData {
+        match self.grid(&grid, background) {
+            Some(processed) if self.map_processed => processed,
+            _ => grid,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.enabled, "Process data")
+            .on_hover_text("Profiles show the processed data next to the data");
+        ui.checkbox(&mut self.map_processed, "Show processed grid on the map");
+
+        let names: Vec<String> = self
+            .batches
+            .iter()
+            .map(|batch| batch.name.clone())
+            .collect();
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("processing_batch")
+                .selected_text(names.get(self.active).map_or("", String::as_str))
+                .show_ui(ui, |ui| {
+                    for (k, name) in names.iter().enumerate() {
+                        ui.selectable_value(&mut self.active, k, name);
+                    }
+                });
+            if ui.button("New batch").clicked() {
+                self.batches.push(Batch {
+                    name: format!("Batch {}", self.batches.len() + 1),
+                    operations: vec![],
+                });
+                self.active = self.batches.len() - 1;
+            }
+            if ui.button("🗑").clicked() && self.active < self.batches.len() {
+                self.batches.remove(self.active);
+                self.active = self.active.saturating_sub(1);
+            }
+        });
+
+        if let Some(batch) = self.batches.get_mut(self.active) {
+            ui.add(egui::TextEdit::singleline(&mut batch.name).desired_width(120.));
+            let mut remove = None;
+            let mut swap = None;
+            let n = batch.operations.len();
+            for (k, operation) in batch.operations.iter_mut().enumerate() {
+                ui.push_id(k, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}. {}", k + 1, operation.label()));
+                        operation.ui(ui);
+                        if ui
+                            .add_enabled(k > 0, egui::Button::new("⏶").small())
+                            .clicked()
+                        {
+                            swap = Some(k - 1);
+                        }
+                        if ui
+                            .add_enabled(k + 1 < n, egui::Button::new("⏷").small())
+                            .clicked()
+                        {
+                            swap = Some(k);
+                        }
+                        if ui.small_button("🗑").clicked() {
+                            remove = Some(k);
+                        }
+                    });
+                });
+            }
+            if let Some(k) = swap {
+                batch.operations.swap(k, k + 1);
+            }
+            if let Some(k) = remove {
+                batch.operations.remove(k);
+            }
+            if batch
+                .operations
+                .iter()
+                .any(|operation| matches!(operation, Operation::ReductionToPole { .. }))
+            {
+                ui.label("Reduction to pole is skipped on profiles, it needs a grid");
+            }
+            ui.menu_button("Add operation", |ui| {
+                for operation in Operation::ALL {
+                    if ui.button(operation.label()).clicked() {
+                        batch.operations.push(operation);
+                        ui.close_menu();
+                    }
+                }
+            });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Save batches…").clicked() {
+                let json = serde_json::to_string_pretty(&self.batches).unwrap();
+                self.status = match save_text("processing.json", &json) {
+                    Ok(true) => "Saved".to_string(),
+                    Ok(false) => String::new(),
+                    Err(err) => format!("Save failed: {err}"),
+                };
+            }
+            if ui.button("Load batches…").clicked() {
+                pick_file(ui.ctx(), self.picked.clone(), "Processing", &["json"]);
+            }
+        });
+        let picked = self.picked.lock().unwrap().take();
+        if let Some((file_name, bytes)) = picked {
+            let batches = bytes.and_then(|bytes| {
+                serde_json::from_slice::<Vec<Batch>>(&bytes).map_err(|err| err.to_string())
+            });
+            self.status = match batches {
+                Ok(batches) => {
+                    let n = batches.len();
+                    self.batches.extend(batches);
+                    format!("Loaded {n} batches from {file_name}")
+                }
+                Err(err) => format!("Could not read {file_name}: {err}"),
+            };
+        }
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Least-squares coefficients of `rows` fitting `values`, from the normal equations by
+/// Gaussian elimination with partial pivoting.
+pub fn least_squares(rows: &[Vec<f64>], values: &[f64]) -> Vec<f64> {
+    let m = rows.first().map_or(0, Vec::len);
+    let mut a = vec![vec![0.; m + 1]; m];
+    for (row, value) in rows.iter().zip(values) {
+        for (a_i, r_i) in a.iter_mut().zip(row) {
+            for (a_ij, r_j) in a_i.iter_mut().zip(row) {
+                *a_ij += r_i * r_j;
+            }
+            a_i[m] += r_i * value;
+        }
+    }
+    for col in 0..m {
+        let pivot = (col..m)
+            .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+            .unwrap();
+        a.swap(col, pivot);
+        if a[col][col].abs() < 1e-12 {
+            continue;
+        }
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for row in lower {
+            let factor = row[col] / pivot_row[col];
+            for (v, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *v -= factor * p;
+            }
+        }
+    }
+    let mut coefficients = vec![0.; m];
+    for i in (0..m).rev() {
+        if a[i][i].abs() < 1e-12 {
+            continue;
+        }
+        let sum = dot(&a[i][i + 1..m], &coefficients[i + 1..]);
+        coefficients[i] = (a[i][m] - sum) / a[i][i];
+    }
+    coefficients
+}
+
+/// Angular wavenumbers of an FFT of length `n` with sample `spacing`.
+fn wavenumbers(n: usize, spacing: f64) -> Vec<f64> {
+    (0..n)
+        .map(|j| {
+            let j = if j <= n / 2 {
+                j as f64
+            } else {
+                j as f64 - n as f64
+            };
+            TAU * j / (n as f64 * spacing)
+        })
+        .collect()
+}
+
+/// Index into the data of position `i` of its even extension to length 2n.
+fn mirror(i: usize, n: usize) -> usize {
+    if i < n {
+        i
+    } else {
+        2 * n - 1 - i
+    }
+}
+
+fn filter_1d(
+    values: &Array1<f64>,
+    spacing: f64,
+    response: impl Fn(f64) -> Complex<f64>,
+) -> Array1<f64> {
+    let n = values.len();
+    let mut buffer: Vec<Complex<f64>> = (0..2 * n)
+        .map(|i| Complex::from(values[mirror(i, n)]))
+        .collect();
+    let mut planner = FftPlanner::new();
+    planner.plan_fft_forward(2 * n).process(&mut buffer);
+    for (value, k) in buffer.iter_mut().zip(wavenumbers(2 * n, spacing)) {
+        *value *= response(k);
+    }
+    planner.plan_fft_inverse(2 * n).process(&mut buffer);
+    Array1::from_iter(buffer[..n].iter().map(|v| v.re / (2 * n) as f64))
+}
+
+fn filter_2d(
+    values: &Array2<f64>,
+    spacing: [f64; 2],
+    response: impl Fn(f64, f64) -> Complex<f64>,
+) -> Array2<f64> {
+    let (nx, ny) = values.dim();
+    let (mx, my) = (2 * nx, 2 * ny);
+    // Row-major with y varying fastest, like `GridData::values`.
+    let mut buffer: Vec<Complex<f64>> = (0..mx * my)
+        .map(|index| Complex::from(values[[mirror(index / my, nx), mirror(index % my, ny)]]))
+        .collect();
+    let mut planner = FftPlanner::new();
+    let transform = |buffer: &mut Vec<Complex<f64>>, planner: &mut FftPlanner<f64>, inverse| {
+        let (fft_y, fft_x) = if inverse {
+            (planner.plan_fft_inverse(my), planner.plan_fft_inverse(mx))
+        } else {
+            (planner.plan_fft_forward(my), planner.plan_fft_forward(mx))
+        };
+        fft_y.process(buffer);
+        let mut column = vec![Complex::default(); mx];
+        for j in 0..my {
+            for i in 0..mx {
+                column[i] = buffer[i * my + j];
+            }
+            fft_x.process(&mut column);
+            for i in 0..mx {
+                buffer[i * my + j] = column[i];
+            }
+        }
+    };
+    transform(&mut buffer, &mut planner, false);
+    let (kx, ky) = (wavenumbers(mx, spacing[0]), wavenumbers(my, spacing[1]));
+    for (index, value) in buffer.iter_mut().enumerate() {
+        *value *= response(kx[index / my], ky[index % my]);
+    }
+    transform(&mut buffer, &mut planner, true);
+    let scale = (mx * my) as f64;
+    Array2::from_shape_fn((nx, ny), |(i, j)| buffer[i * my + j].re / scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gravity_objects::Sphere;
+    use crate::testing::sphere_grid;
+
+    #[test]
+    fn upward_continuation_of_sphere() {
+        let sphere = Sphere {
+            z_centroid: -8.,
+            ..Sphere::default()
+        };
+        let (_, ground) = sphere_grid(&sphere, 128, 1., 0.);
+        let (_, above) = sphere_grid(&sphere, 128, 1., 4.);
+        let continued = Operation::Continuation {
+            height: 4.,
+            damping: DAMPING,
+        }
+        .apply_grid(&ground);
+        let peak = above.values.iter().fold(0., |max: f64, v| max.max(v.abs()));
+        for i in 48..80 {
+            for j in 48..80 {
+                let error = continued.values[[i, j]] - above.values[[i, j]];
+                assert!(error.abs() < 0.02 * peak, "{i} {j}: {error} of {peak}");
+            }
+        }
+    }
+
+    #[test]
+    fn background_removal_before_continuation() {
+        let sphere = Sphere {
+            z_centroid: -8.,
+            ..Sphere::default()
+        };
+        let (_, ground) = sphere_grid(&sphere, 128, 1., 0.);
+        let regional = Array2::from_shape_fn((128, 128), |(i, j)| 40. + 0.3 * i as f64 - j as f64);
+        let observed = GridData {
+            values: &ground.values + &regional,
+            ..ground.clone()
+        };
+        let batch = Batch {
+            name: "Residual".to_string(),
+            operations: vec![
+                Operation::RemoveBackground,
+                Operation::Continuation {
+                    height: 4.,
+                    damping: DAMPING,
+                },
+            ],
+        };
+        let processed = batch.apply_grid(&observed, &regional);
+        let expected = batch.operations[1].apply_grid(&ground);
+        assert!(processed
+            .values
+            .iter()
+            .zip(&expected.values)
+            .all(|(a, b)| (a - b).abs() < 1e-9));
+
+        let profile = ground.values.row(64).to_owned();
+        let background = regional.row(64).to_owned();
+        let processed = Batch {
+            operations: vec![Operation::RemoveBackground],
+            ..batch
+        }
+        .apply_profile(1., &(&profile + &background), &background);
+        assert!(processed
+            .iter()
+            .zip(&profile)
+            .all(|(a, b)| (a - b).abs() < 1e-9));
+    }
+
+    #[test]
+    fn derivative_of_sinusoid() {
+        let (spacing, wavelength) = (0.5, 20.);
+        let k = TAU / wavelength;
+        let values = Array1::from_shape_fn(400, |i| (k * i as f64 * spacing).sin());
+        let derivative = Operation::HorizontalDerivative {
+            direction: Direction::X,
+        }
+        .apply_profile(spacing, &values);
+        for i in 100..300 {
+            let expected = k * (k * i as f64 * spacing).cos();
+            assert!((derivative[i] - expected).abs() < 0.02 * k, "{i}");
+        }
+    }
+
+    #[test]
+    fn trend_removal_of_plane() {
+        let x = Array1::linspace(-10., 10., 21);
+        let y = Array1::linspace(0., 30., 16);
+        let grid = GridData {
+            values: Array2::from_shape_fn((21, 16), |(i, j)| 3. + 0.5 * x[i] - 0.2 * y[j]),
+            x,
+            y,
+        };
+        let residual = Operation::RemoveTrend { order: 1 }.apply_grid(&grid);
+        assert!(residual.values.iter().all(|v| v.abs() < 1e-9));
+
+        let values = Array1::linspace(-4., 8., 50);
+        let residual = Operation::RemoveTrend { order: 1 }.apply_profile(1., &values);
+        assert!(residual.iter().all(|v| v.abs() < 1e-9));
+    }
+}
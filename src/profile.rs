@@ -0,0 +1,214 @@
+use crate::gravity_objects::{GravityCalc, GravityObject};
+use crate::plot::{convex_hull, Footprint};
+use egui::plot::{Line, PlotPoint, PlotUi, Points, Text};
+use egui::{Color32, Context, Key, Ui};
+use ndarray::Array2;
+
+const COLOURS: [Color32; 6] = [
+    Color32::from_rgb(230, 120, 20),
+    Color32::from_rgb(200, 40, 160),
+    Color32::from_rgb(20, 150, 150),
+    Color32::from_rgb(120, 80, 200),
+    Color32::from_rgb(150, 150, 20),
+    Color32::from_rgb(200, 60, 60),
+];
+
+/// Profile along a polyline drawn on the XY map, sampled at evenly spaced stations on
+/// the observation surface.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct PolylineProfile {
+    pub name: String,
+    pub colour: Color32,
+    pub vertices: Vec<[f64; 2]>,
+    /// Number of stations along the whole line.
+    pub n: usize,
+    /// Whether the data and section window is shown.
+    pub open: bool,
+}
+
+impl PolylineProfile {
+    /// Distance along the line of each vertex.
+    pub fn vertex_distances(&self) -> Vec<f64> {
+        let mut distance = 0.;
+        let mut distances = vec![0.];
+        for pair in self.vertices.windows(2) {
+            distance += (pair[1][0] - pair[0][0]).hypot(pair[1][1] - pair[0][1]);
+            distances.push(distance);
+        }
+        distances
+    }
+
+    pub fn length(&self) -> f64 {
+        self.vertex_distances().last().copied().unwrap_or_default()
+    }
+
+    /// Point at `distance` along the line.
+    pub fn position(&self, distance: f64) -> [f64; 2] {
+        let distances = self.vertex_distances();
+        for (k, pair) in self.vertices.windows(2).enumerate() {
+            let segment = distances[k + 1] - distances[k];
+            if distance <= distances[k + 1] || k + 2 == self.vertices.len() {
+                let t = if segment > 0. {
+                    ((distance - distances[k]) / segment).clamp(0., 1.)
+                } else {
+                    0.
+                };
+                return [
+                    pair[0][0] + t * (pair[1][0] - pair[0][0]),
+                    pair[0][1] + t * (pair[1][1] - pair[0][1]),
+                ];
+            }
+        }
+        self.vertices.first().copied().unwrap_or_default()
+    }
+
+    /// Stations as rows of x, y, z on the surface z + gradient · (x, y), and their
+    /// distance along the line.
+    pub fn points(&self, z: f64, gradient: [f64; 2]) -> (Array2<f64>, Vec<f64>) {
+        let n = self.n.max(2);
+        let length = self.length();
+        let distances: Vec<f64> = (0..n).map(|i| length * i as f64 / (n - 1) as f64).collect();
+        let mut points = Array2::zeros((n, 3));
+        for (i, distance) in distances.iter().enumerate() {
+            let [x, y] = self.position(*distance);
+            points[[i, 0]] = x;
+            points[[i, 1]] = y;
+            points[[i, 2]] = z + gradient[0] * x + gradient[1] * y;
+        }
+        // Same offset as the x and y profiles, to keep stations off body edges.
+        (points * 1.0001, distances)
+    }
+
+    /// Distance along the line of the closest point on it to `point`, and the distance
+    /// from the line.
+    pub fn project(&self, point: [f64; 2]) -> (f64, f64) {
+        let distances = self.vertex_distances();
+        let mut best = (0., f64::INFINITY);
+        for (k, pair) in self.vertices.windows(2).enumerate() {
+            let [a, b] = [pair[0], pair[1]];
+            let d = [b[0] - a[0], b[1] - a[1]];
+            let length_sq = d[0] * d[0] + d[1] * d[1];
+            let t = if length_sq > 0. {
+                (((point[0] - a[0]) * d[0] + (point[1] - a[1]) * d[1]) / length_sq).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let offset = (point[0] - a[0] - t * d[0]).hypot(point[1] - a[1] - t * d[1]);
+            if offset < best.1 {
+                best = (distances[k] + t * length_sq.sqrt(), offset);
+            }
+        }
+        best
+    }
+
+    /// Outline of an object projected onto the vertical section under the line, in
+    /// distance along the line and z.
+    pub fn footprint(&self, object: &GravityObject) -> Footprint {
+        match object {
+            GravityObject::Sphere(sphere) => {
+                let centre = sphere.centre();
+                Footprint::Circle {
+                    centre: [self.project([centre[0], centre[1]]).0, centre[2]],
+                    radius: sphere.radius,
+                }
+            }
+            _ => {
+                let points = object
+                    .mesh()
+                    .vertices
+                    .iter()
+                    .map(|v| [self.project([v[0], v[1]]).0, v[2]])
+                    .collect();
+                Footprint::Polygon(convex_hull(points))
+            }
+        }
+    }
+}
+
+/// Polyline profiles drawn on the XY map. Hold D and click to add vertices, releasing D
+/// finishes the line.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Profiles {
+    pub profiles: Vec<PolylineProfile>,
+    /// Vertices of the line being drawn.
+    #[serde(skip)]
+    drawing: Vec<[f64; 2]>,
+    /// Counter for default names.
+    n_drawn: usize,
+}
+
+impl Profiles {
+    /// Draw the profiles on the map and add vertices while D is held.
+    pub fn show(&mut self, ctx: &Context, plot_ui: &mut PlotUi) {
+        for profile in &self.profiles {
+            plot_ui.line(
+                Line::new(profile.vertices.clone())
+                    .name(&profile.name)
+                    .color(profile.colour)
+                    .width(2.),
+            );
+            if let Some(start) = profile.vertices.first() {
+                plot_ui.text(
+                    Text::new(PlotPoint::new(start[0], start[1]), &profile.name)
+                        .color(profile.colour)
+                        .anchor(egui::Align2::RIGHT_BOTTOM),
+                );
+            }
+        }
+
+        if ctx.input().key_down(Key::D) {
+            if plot_ui.plot_hovered() && plot_ui.plot_clicked() {
+                if let Some(p) = plot_ui.pointer_coordinate() {
+                    self.drawing.push([p.x, p.y]);
+                }
+            }
+            if !self.drawing.is_empty() {
+                let mut vertices = self.drawing.clone();
+                if let Some(p) = plot_ui.pointer_coordinate() {
+                    vertices.push([p.x, p.y]);
+                }
+                let colour = COLOURS[self.n_drawn % COLOURS.len()];
+                plot_ui.points(Points::new(self.drawing.clone()).color(colour).radius(3.));
+                plot_ui.line(Line::new(vertices).color(colour).width(2.));
+            }
+        } else if !self.drawing.is_empty() {
+            let vertices = std::mem::take(&mut self.drawing);
+            if vertices.len() >= 2 {
+                self.profiles.push(PolylineProfile {
+                    name: format!("P{}", self.n_drawn + 1),
+                    colour: COLOURS[self.n_drawn % COLOURS.len()],
+                    vertices,
+                    n: 100,
+                    open: true,
+                });
+                self.n_drawn += 1;
+            }
+        }
+    }
+
+    /// List of the profiles with their settings.
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.label("Hold D and click on the map to draw a profile, release D to finish.");
+        let mut remove = None;
+        for (i, profile) in self.profiles.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut profile.colour);
+                ui.add(egui::TextEdit::singleline(&mut profile.name).desired_width(60.));
+                ui.add(
+                    egui::DragValue::new(&mut profile.n)
+                        .clamp_range(2..=2000)
+                        .suffix(" stations"),
+                );
+                ui.label(format!("{:.1} m", profile.length()));
+                ui.checkbox(&mut profile.open, "Show");
+                if ui.button("🗑").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.profiles.remove(i);
+        }
+    }
+}
@@ -0,0 +1,603 @@
+use crate::gravity_objects::{DataType, Parameter};
+use crate::map::{colour_bar, Colormap, GridData, MapView};
+use crate::model::Model;
+use egui::plot::{Legend, Line, Plot};
+use egui::{Color32, Context, Ui};
+use ndarray::{Array2, Axis};
+
+const COLOURS: [Color32; 6] = [
+    Color32::from_rgb(31, 119, 180),
+    Color32::from_rgb(255, 127, 14),
+    Color32::from_rgb(44, 160, 44),
+    Color32::from_rgb(214, 39, 40),
+    Color32::from_rgb(148, 103, 189),
+    Color32::from_rgb(140, 86, 75),
+];
+
+/// Finite-difference step for a parameter at `value`, small against the value but above
+/// rounding in the forward calculation.
+fn step(parameter: Parameter, value: f64) -> f64 {
+    match parameter {
+        Parameter::XRotation | Parameter::YRotation | Parameter::ZRotation => 0.1,
+        Parameter::Density => (value.abs() * 1e-3).max(1.),
+        _ => (value.abs() * 1e-3).max(1e-3),
+    }
+}
+
+/// Derivatives of the model response at `points` with respect to each parameter of the
+/// object `id`, one column per parameter, by central differences through the whole
+/// model so derived data types and overlapping objects are handled.
+pub fn jacobian(
+    model: &Model,
+    id: &str,
+    parameters: &[Parameter],
+    data_type: &DataType,
+    points: &Array2<f64>,
+) -> Array2<f64> {
+    let mut jacobian = Array2::zeros((points.len_of(Axis(0)), parameters.len()));
+    let Some(Some(obj)) = model.objects.get(id) else {
+        return jacobian;
+    };
+    for (k, parameter) in parameters.iter().enumerate() {
+        let Some(value) = parameter.get(&obj.object) else {
+            continue;
+        };
+        let h = step(*parameter, value);
+        let response = |value: f64| {
+            let mut model = model.clone();
+            if let Some(Some(obj)) = model.objects.get_mut(id) {
+                parameter.set(&mut obj.object, value);
+            }
+            model.calculate(data_type, points)
+        };
+        let derivative = (response(value + h) - response(value - h)) / (2. * h);
+        jacobian.column_mut(k).assign(&derivative);
+    }
+    jacobian
+}
+
+/// Inverse by Gauss-Jordan elimination, `None` if singular.
+fn invert(matrix: &Array2<f64>) -> Option<Array2<f64>> {
+    let n = matrix.nrows();
+    let mut a = matrix.clone();
+    let mut inverse = Array2::eye(n);
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[[i, col]].abs().total_cmp(&a[[j, col]].abs()))?;
+        if a[[pivot, col]].abs() < 1e-8 {
+            return None;
+        }
+        for m in [&mut a, &mut inverse] {
+            for j in 0..n {
+                m.swap([col, j], [pivot, j]);
+            }
+        }
+        let scale = a[[col, col]];
+        a.row_mut(col).mapv_inplace(|v| v / scale);
+        inverse.row_mut(col).mapv_inplace(|v| v / scale);
+        for i in (0..n).filter(|&i| i != col) {
+            let factor = a[[i, col]];
+            if factor != 0. {
+                let (a_row, inverse_row) = (a.row(col).to_owned(), inverse.row(col).to_owned());
+                a.row_mut(i).scaled_add(-factor, &a_row);
+                inverse.row_mut(i).scaled_add(-factor, &inverse_row);
+            }
+        }
+    }
+    Some(inverse)
+}
+
+/// Profile or grid of stations the sensitivities are computed on.
+pub struct Survey<'a> {
+    pub name: &'a str,
+    pub points: Array2<f64>,
+    /// Column of `points` to plot profiles against, or the grid size in x and y.
+    pub layout: Layout,
+}
+
+pub enum Layout {
+    Profile(usize),
+    Grid(usize, usize),
+}
+
+/// Sensitivities of one object and what the data resolves.
+struct Analysis {
+    object: String,
+    parameters: Vec<Parameter>,
+    /// Per survey: name, positions for profiles, and the Jacobian rows at its stations.
+    surveys: Vec<(String, Option<Vec<f64>>, Array2<f64>)>,
+    /// Grids of each parameter's sensitivity.
+    grids: Vec<GridData>,
+    /// Parameter correlations, NaN for unresolved parameters.
+    correlation: Array2<f64>,
+    /// Standard deviations of the parameters for the data error, infinite if unresolved.
+    deviations: Vec<f64>,
+    /// Depth of the object below the stations, best-fit density and RMS misfit.
+    trade_off: Vec<[f64; 3]>,
+    trade_off_data: DataType,
+}
+
+/// Jacobian of the survey response with respect to an object's parameters: sensitivity
+/// profiles and maps, the parameter correlation matrix and the depth–density trade-off.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Sensitivity {
+    pub open: bool,
+    /// Id of the object analysed.
+    pub object: Option<String>,
+    /// Scale each sensitivity profile to a peak of one, to compare shapes.
+    pub normalised: bool,
+    pub map_parameter: usize,
+    /// Depths tried for the trade-off as multiples of the current depth.
+    pub depth_factors: [f64; 2],
+    #[serde(skip)]
+    map_view: MapView,
+    #[serde(skip)]
+    analysis: Option<Analysis>,
+}
+
+impl Default for Sensitivity {
+    fn default() -> Self {
+        let mut map_view = MapView::default();
+        map_view.style.colormap = Colormap::RedBlue;
+        map_view.style.symmetric = true;
+        Self {
+            open: false,
+            object: None,
+            normalised: true,
+            map_parameter: 0,
+            depth_factors: [0.5, 2.],
+            map_view,
+            analysis: None,
+        }
+    }
+}
+
+impl Sensitivity {
+    /// Analyse the chosen object on the surveys. `sigma` is the standard deviation of the
+    /// data errors, one data unit if `None`.
+    pub fn compute(
+        &mut self,
+        model: &Model,
+        data_type: &DataType,
+        surveys: &[Survey<'_>],
+        sigma: Option<f64>,
+    ) {
+        self.analysis = None;
+        let Some(id) = &self.object else {
+            return;
+        };
+        let Some(Some(obj)) = model.objects.get(id) else {
+            return;
+        };
+        let parameters: Vec<Parameter> = Parameter::ALL
+            .into_iter()
+            .filter(|parameter| parameter.get(&obj.object).is_some())
+            .collect();
+
+        let mut analysed = vec![];
+        let mut grids = vec![];
+        for survey in surveys {
+            let jacobian = jacobian(model, id, &parameters, data_type, &survey.points);
+            let positions = match survey.layout {
+                Layout::Profile(axis) => Some(survey.points.column(axis).to_vec()),
+                Layout::Grid(nx, ny) => {
+                    grids = (0..parameters.len())
+                        .map(|k| {
+                            GridData::from_points(
+                                &survey.points,
+                                &jacobian.column(k).to_owned(),
+                                nx,
+                                ny,
+                            )
+                        })
+                        .collect();
+                    None
+                }
+            };
+            analysed.push((survey.name.to_string(), positions, jacobian));
+        }
+        if analysed.is_empty() {
+            return;
+        }
+        let views: Vec<_> = analysed.iter().map(|(_, _, rows)| rows.view()).collect();
+        let jacobian = ndarray::concatenate(Axis(0), &views).unwrap();
+
+        // Scale the columns to unit norm so parameters of different units can be
+        // inverted together, correlations don't depend on the scaling.
+        let norms: Vec<f64> = jacobian
+            .columns()
+            .into_iter()
+            .map(|column| column.dot(&column).sqrt())
+            .collect();
+        let mut scaled = jacobian.clone();
+        for (mut column, norm) in scaled.columns_mut().into_iter().zip(&norms) {
+            if *norm > 0. {
+                column /= *norm;
+            }
+        }
+        // Keep each parameter the data resolve given those before it, leaving out ones
+        // with no effect or traded off exactly against others, e.g. radius and density.
+        let normal = scaled.t().dot(&scaled);
+        let mut resolved: Vec<usize> = vec![];
+        let mut covariance = None;
+        for k in (0..parameters.len()).filter(|&k| norms[k] > 0.) {
+            let mut trial = resolved.clone();
+            trial.push(k);
+            let block = normal.select(Axis(0), &trial).select(Axis(1), &trial);
+            if let Some(c) = invert(&block) {
+                resolved = trial;
+                covariance = Some(c);
+            }
+        }
+        let sigma = sigma.unwrap_or(1.);
+        let n = parameters.len();
+        let mut correlation = Array2::from_elem((n, n), f64::NAN);
+        let mut deviations = vec![f64::INFINITY; n];
+        if let Some(c) = &covariance {
+            for (a, &i) in resolved.iter().enumerate() {
+                deviations[i] = sigma * c[[a, a]].sqrt() / norms[i];
+                for (b, &j) in resolved.iter().enumerate() {
+                    correlation[[i, j]] = c[[a, b]] / (c[[a, a]] * c[[b, b]]).sqrt();
+                }
+            }
+        }
+
+        let trade_off_data = if data_type.is_linear() {
+            *data_type
+        } else {
+            DataType::Gz
+        };
+        let trade_off = self.trade_off(model, id, &trade_off_data, &jacobian_points(surveys));
+        self.map_parameter = self.map_parameter.min(parameters.len().saturating_sub(1));
+        self.analysis = Some(Analysis {
+            object: obj.name.to_string(),
+            parameters,
+            surveys: analysed,
+            grids,
+            correlation,
+            deviations,
+            trade_off,
+            trade_off_data,
+        });
+    }
+
+    /// Best-fitting density and misfit of the object moved to other depths, fitting the
+    /// current response. The object's contribution is linear in its density, so the best
+    /// density at each depth is a least-squares scaling of its response.
+    fn trade_off(
+        &self,
+        model: &Model,
+        id: &str,
+        data_type: &DataType,
+        points: &Array2<f64>,
+    ) -> Vec<[f64; 3]> {
+        let Some(Some(obj)) = model.objects.get(id) else {
+            return vec![];
+        };
+        let (Some(z), Some(density)) = (
+            Parameter::ZCentroid.get(&obj.object),
+            Parameter::Density.get(&obj.object),
+        ) else {
+            return vec![];
+        };
+        let stations = points.column(2).mean().unwrap_or_default();
+        let depth = stations - z;
+        let target = obj.object.as_calc().calculate(data_type, points);
+        let n = 40;
+        (0..=n)
+            .filter_map(|i| {
+                let [low, high] = self.depth_factors;
+                let trial_depth = depth * (low + (high - low) * i as f64 / n as f64);
+                let mut object = obj.object.clone();
+                Parameter::ZCentroid.set(&mut object, stations - trial_depth);
+                let response = object.as_calc().calculate(data_type, points);
+                let power = response.dot(&response);
+                if power <= 0. {
+                    return None;
+                }
+                let scale = target.dot(&response) / power;
+                let residual = &target - &(response * scale);
+                let rms = (residual.dot(&residual) / target.len() as f64).sqrt();
+                Some([trial_depth, density * scale, rms])
+            })
+            .collect()
+    }
+
+    /// Object choice, returning whether to compute.
+    pub fn ui(&mut self, ui: &mut Ui, model: &Model) -> bool {
+        let name = |id: &String| {
+            model
+                .objects
+                .get(id)
+                .and_then(|obj| obj.as_ref())
+                .map_or("None".to_string(), |obj| {
+                    format!("{}: {}", obj.id, obj.name)
+                })
+        };
+        if self
+            .object
+            .as_ref()
+            .map_or(true, |id| !matches!(model.objects.get(id), Some(Some(_))))
+        {
+            self.object = model
+                .selected_object_ids()
+                .into_iter()
+                .next()
+                .or_else(|| model.ordered_ids().into_iter().next());
+        }
+        let mut compute = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Object")
+                .selected_text(self.object.as_ref().map_or("None".to_string(), name))
+                .show_ui(ui, |ui| {
+                    for id in model.ordered_ids() {
+                        let label = name(&id);
+                        ui.selectable_value(&mut self.object, Some(id), label);
+                    }
+                });
+            compute = ui.button("Compute").clicked();
+        });
+        let [low, high] = self.depth_factors;
+        ui.horizontal(|ui| {
+            ui.label("Trade-off depths");
+            ui.add(
+                egui::DragValue::new(&mut self.depth_factors[0])
+                    .speed(0.05)
+                    .clamp_range(0.05..=high)
+                    .suffix("×"),
+            );
+            ui.label("to");
+            ui.add(
+                egui::DragValue::new(&mut self.depth_factors[1])
+                    .speed(0.05)
+                    .clamp_range(low..=20.)
+                    .suffix("×"),
+            );
+        });
+        compute
+    }
+
+    /// Plots and tables of the last analysis.
+    pub fn show(&mut self, ctx: &Context, ui: &mut Ui, data_type: &DataType, width: f32) {
+        let Some(analysis) = &self.analysis else {
+            ui.label("Choose an object and press Compute.");
+            return;
+        };
+        let units = |parameter: &Parameter| format!("{}/{}", data_type.units(), parameter.units());
+        ui.heading(&analysis.object);
+
+        ui.checkbox(&mut self.normalised, "Normalise profiles");
+        for (name, positions, jacobian) in &analysis.surveys {
+            let Some(positions) = positions else {
+                continue;
+            };
+            ui.label(format!("Sensitivity along the {name}"));
+            Plot::new(format!("sensitivity_{name}"))
+                .view_aspect(2.5)
+                .width(width)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    for (k, parameter) in analysis.parameters.iter().enumerate() {
+                        let column = jacobian.column(k);
+                        let peak = column.iter().fold(0., |max: f64, v| max.max(v.abs()));
+                        let scale = if self.normalised && peak > 0. {
+                            1. / peak
+                        } else {
+                            1.
+                        };
+                        let points: Vec<[f64; 2]> = positions
+                            .iter()
+                            .zip(column)
+                            .map(|(p, v)| [*p, v * scale])
+                            .collect();
+                        plot_ui.line(
+                            Line::new(points)
+                                .name(format!("{} ({})", parameter.name(), units(parameter)))
+                                .color(COLOURS[k % COLOURS.len()]),
+                        );
+                    }
+                });
+        }
+
+        if !analysis.grids.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label("Map of");
+                let parameter = analysis.parameters[self.map_parameter];
+                egui::ComboBox::from_id_source("sensitivity_map_parameter")
+                    .selected_text(parameter.name())
+                    .show_ui(ui, |ui| {
+                        for (k, parameter) in analysis.parameters.iter().enumerate() {
+                            ui.selectable_value(&mut self.map_parameter, k, parameter.name());
+                        }
+                    });
+            });
+            let grid = &analysis.grids[self.map_parameter];
+            let range = self.map_view.style.colour_range(&grid.values);
+            Plot::new("sensitivity_map")
+                .data_aspect(1.0)
+                .view_aspect(1.0)
+                .width(width)
+                .show(ui, |plot_ui| {
+                    self.map_view.show(ctx, plot_ui, grid, range);
+                });
+            let parameter = &analysis.parameters[self.map_parameter];
+            colour_bar(
+                ui,
+                &self.map_view.style.colormap,
+                range,
+                &format!("{} sensitivity ({})", parameter.name(), units(parameter)),
+                width,
+            );
+        }
+
+        ui.separator();
+        ui.label("Parameter correlations and standard deviations for the data errors");
+        egui::Grid::new("sensitivity_correlation").show(ui, |ui| {
+            ui.label("");
+            for parameter in &analysis.parameters {
+                ui.label(parameter.name());
+            }
+            ui.label("σ");
+            ui.end_row();
+            for (i, parameter) in analysis.parameters.iter().enumerate() {
+                ui.label(parameter.name());
+                for j in 0..analysis.parameters.len() {
+                    let r = analysis.correlation[[i, j]];
+                    if r.is_nan() {
+                        ui.label("–");
+                    } else {
+                        let colour = Colormap::RedBlue.colour((r + 1.) / 2.);
+                        let text = egui::RichText::new(format!("{r:+.2}"))
+                            .background_color(colour.linear_multiply(0.6));
+                        ui.label(text);
+                    }
+                }
+                if analysis.deviations[i].is_finite() {
+                    ui.label(format!(
+                        "{:.3} {}",
+                        analysis.deviations[i],
+                        parameter.units()
+                    ));
+                } else {
+                    ui.label("unresolved");
+                }
+                ui.end_row();
+            }
+        });
+        if analysis.deviations.iter().any(|d| !d.is_finite()) {
+            ui.colored_label(
+                Color32::from_rgb(200, 60, 60),
+                "Unresolved parameters have no effect on the data or trade off exactly against \
+                 those above them, e.g. the radius and density of a sphere.",
+            );
+        }
+
+        ui.separator();
+        ui.label(format!(
+            "Depth–density trade-off fitting the object's {}",
+            analysis.trade_off_data.label()
+        ));
+        let density: Vec<[f64; 2]> = analysis.trade_off.iter().map(|t| [t[0], t[1]]).collect();
+        let misfit: Vec<[f64; 2]> = analysis.trade_off.iter().map(|t| [t[0], t[2]]).collect();
+        for (id, name, points) in [
+            ("trade_off_density", "Best density (kg/m^3)", density),
+            ("trade_off_misfit", "RMS misfit", misfit),
+        ] {
+            Plot::new(id)
+                .view_aspect(3.)
+                .width(width)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(points).name(name));
+                });
+        }
+        ui.label("Depth below the stations (m)");
+    }
+}
+
+/// Stations of all surveys stacked.
+fn jacobian_points(surveys: &[Survey<'_>]) -> Array2<f64> {
+    let views: Vec<_> = surveys.iter().map(|survey| survey.points.view()).collect();
+    ndarray::concatenate(Axis(0), &views).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gravity_objects::Sphere;
+    use crate::testing::sphere_model;
+    use ndarray::array;
+
+    fn model() -> Model {
+        sphere_model(&[Sphere {
+            x_centroid: 1.,
+            y_centroid: -2.,
+            z_centroid: -6.,
+            radius: 2.,
+            density: 500.,
+        }])
+    }
+
+    fn grid_points(n: usize) -> Array2<f64> {
+        Array2::from_shape_fn((n * n, 3), |(k, c)| match c {
+            0 => (k / n) as f64 - (n / 2) as f64,
+            1 => (k % n) as f64 - (n / 2) as f64,
+            _ => 0.,
+        })
+    }
+
+    #[test]
+    fn jacobian_of_sphere() {
+        let model = model();
+        let points = grid_points(11);
+        let parameters = [Parameter::Radius, Parameter::Density];
+        let jacobian = jacobian(&model, "0", &parameters, &DataType::Gz, &points);
+        // Gz is proportional to the density and the cube of the radius.
+        let gz = model.calculate(&DataType::Gz, &points);
+        let scale = gz.iter().fold(0., |max: f64, v| max.max(v.abs()));
+        for (row, gz) in jacobian.rows().into_iter().zip(&gz) {
+            assert!((row[0] - 3. * gz / 2.).abs() < 1e-5 * scale);
+            assert!((row[1] - gz / 500.).abs() < 1e-8 * scale);
+        }
+
+        let unknown = super::jacobian(&model, "1", &parameters, &DataType::Gz, &points);
+        assert!(unknown.iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn inverse() {
+        let matrix = array![[4., 1., 0.], [1., 3., -1.], [0., -1., 2.]];
+        let inverse = invert(&matrix).unwrap();
+        let identity = matrix.dot(&inverse) - Array2::<f64>::eye(3);
+        assert!(identity.iter().all(|v| v.abs() < 1e-12));
+        assert!(invert(&array![[1., 2.], [2., 4.]]).is_none());
+    }
+
+    #[test]
+    fn covariance_of_sphere() {
+        let model = model();
+        let survey = |points| Survey {
+            name: "grid",
+            points,
+            layout: Layout::Grid(21, 21),
+        };
+        let mut sensitivity = Sensitivity {
+            object: Some("0".to_string()),
+            ..Sensitivity::default()
+        };
+        sensitivity.compute(&model, &DataType::Gz, &[survey(grid_points(21))], Some(1.));
+        let analysis = sensitivity.analysis.take().unwrap();
+        let k = |parameter| {
+            analysis
+                .parameters
+                .iter()
+                .position(|p| *p == parameter)
+                .unwrap()
+        };
+        let (z, radius, density) = (
+            k(Parameter::ZCentroid),
+            k(Parameter::Radius),
+            k(Parameter::Density),
+        );
+        // Only the mass is resolved, the density trades off exactly against the radius.
+        assert!(analysis.deviations[radius].is_finite());
+        assert!(analysis.deviations[density].is_infinite());
+        assert!(analysis.correlation[[density, density]].is_nan());
+        // Deeper spheres need more mass, so depth and radius are anticorrelated.
+        assert!(analysis.correlation[[z, radius]] < -0.5);
+        for i in [k(Parameter::XCentroid), k(Parameter::YCentroid), z, radius] {
+            assert!((analysis.correlation[[i, i]] - 1.).abs() < 1e-9);
+            for j in [z, radius] {
+                let (a, b) = (analysis.correlation[[i, j]], analysis.correlation[[j, i]]);
+                assert!((a - b).abs() < 1e-9 && a.abs() <= 1. + 1e-9);
+            }
+        }
+
+        // Deviations scale with the data error.
+        sensitivity.compute(&model, &DataType::Gz, &[survey(grid_points(21))], Some(2.));
+        let doubled = sensitivity.analysis.take().unwrap();
+        for (a, b) in analysis.deviations.iter().zip(&doubled.deviations) {
+            assert!(a.is_infinite() && b.is_infinite() || (b - 2. * a).abs() < 1e-9 * a);
+        }
+    }
+}
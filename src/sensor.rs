@@ -0,0 +1,95 @@
+use crate::model::Model;
+use egui::Ui;
+use ndarray::{array, Array1, Array2, Axis};
+use std::f64::consts::PI;
+
+/// Converts a difference of the displayed gravity vector (µGal, positive downwards)
+/// over a baseline in metres to Eötvös along the upward axis convention of the tensor.
+const MICROGAL_PER_METRE_TO_EOTVOS: f64 = -10.;
+
+/// A two-sensor gradiometer measuring the difference in g over a finite baseline.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct GradiometerSensor {
+    pub enabled: bool,
+    /// Separation of the two sensors in metres.
+    pub baseline: f64,
+    /// Angle of the baseline from vertical in radians.
+    pub inclination: f64,
+    /// Azimuth of the baseline from the x axis in radians.
+    pub azimuth: f64,
+    /// Height of the baseline mid-point above the survey station.
+    pub mid_height: f64,
+}
+
+impl Default for GradiometerSensor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            baseline: 1.,
+            inclination: 0.,
+            azimuth: 0.,
+            mid_height: 0.,
+        }
+    }
+}
+
+impl GradiometerSensor {
+    /// Unit vector pointing from the bottom sensor to the top sensor.
+    pub fn axis(&self) -> Array1<f64> {
+        array![
+            self.inclination.sin() * self.azimuth.cos(),
+            self.inclination.sin() * self.azimuth.sin(),
+            self.inclination.cos()
+        ]
+    }
+
+    /// Positions of the baseline mid-points for each survey station.
+    pub fn mid_points(&self, points: &Array2<f64>) -> Array2<f64> {
+        let mut mid_points = points.clone();
+        mid_points
+            .column_mut(2)
+            .mapv_inplace(|z| z + self.mid_height);
+        mid_points
+    }
+
+    /// Simulated reading (g_top - g_bottom) / baseline along the sensor axis, in E.
+    pub fn calculate(&self, model: &Model, points: &Array2<f64>) -> Array1<f64> {
+        let axis = self.axis();
+        let mid_points = self.mid_points(points);
+        let offset = &axis * (self.baseline / 2.);
+        let top = &mid_points + &offset;
+        let bottom = &mid_points - &offset;
+        let g_top = model.field(&top).g.dot(&axis);
+        let g_bottom = model.field(&bottom).g.dot(&axis);
+        (g_top - g_bottom) / self.baseline * MICROGAL_PER_METRE_TO_EOTVOS
+    }
+
+    /// Point derivative of g along the sensor axis at the baseline mid-point, in E.
+    /// Equal to Gzz for a vertical baseline.
+    pub fn point_gradient(&self, model: &Model, points: &Array2<f64>) -> Array1<f64> {
+        let axis = self.axis();
+        let field = model.field(&self.mid_points(points));
+        Array1::from_iter(
+            field
+                .gg
+                .axis_iter(Axis(0))
+                .map(|gg| axis.dot(&gg.dot(&axis))),
+        )
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.label("baseline");
+        ui.add(egui::Slider::new(&mut self.baseline, 0.01..=5.).text("m"));
+        ui.label("inclination from vertical");
+        ui.add(egui::Slider::new(&mut self.inclination, 0.0..=PI).text("rad"));
+        ui.label("azimuth");
+        ui.add(egui::Slider::new(&mut self.azimuth, -PI..=PI).text("rad"));
+        ui.label("mid-point height above station");
+        ui.add(egui::Slider::new(&mut self.mid_height, -5.0..=5.).text("m"));
+        if ui.button("Vertical").clicked() {
+            self.inclination = 0.;
+            self.azimuth = 0.;
+        }
+    }
+}
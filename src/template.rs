@@ -0,0 +1,867 @@
+use crate::export::save_text;
+use crate::gravity_objects::{Cuboid, GravityModelObject, GravityObject, Sphere};
+use crate::import::{pick_file, PickedFile};
+use crate::model::Model;
+use egui::{Color32, Ui};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Shape {
+    Cuboid,
+    Sphere,
+}
+
+/// Input of a template, referred to by name in the body expressions.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct TemplateInput {
+    pub name: String,
+    /// Default value.
+    pub value: f64,
+    pub units: String,
+}
+
+fn zeros() -> [String; 3] {
+    ["0".to_string(), "0".to_string(), "0".to_string()]
+}
+
+fn one() -> String {
+    "1".to_string()
+}
+
+/// Body generated by a template. Each field is an arithmetic expression of the template
+/// inputs, `pi` and the copy index `i`, e.g. `-(cover + diameter / 2)`.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct BodyRecipe {
+    pub name: String,
+    pub shape: Shape,
+    /// Number of copies, `i` counts them from 0.
+    #[serde(default = "one")]
+    pub count: String,
+    /// Centre in metres, z positive up from the template origin.
+    pub centre: [String; 3],
+    /// Lengths along x, y and z in metres, or the radius first for spheres.
+    pub size: [String; 3],
+    /// Rotation about the x, y and z axes in degrees, cuboids only.
+    #[serde(default = "zeros")]
+    pub rotation: [String; 3],
+    /// Density contrast in kg/m^3.
+    pub density: String,
+    pub colour: Color32,
+}
+
+/// Parameterised recipe for a group of bodies, e.g. a lined tunnel.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Template {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub inputs: Vec<TemplateInput>,
+    pub bodies: Vec<BodyRecipe>,
+}
+
+/// Most copies of one body, so a runaway count is an error instead of hanging the app.
+const MAX_COUNT: usize = 1000;
+
+/// Side of the square with the area of a circle of the given diameter, for round sections
+/// built from cuboids.
+const ROUND: &str = "sqrt(pi) / 2";
+
+fn input(name: &str, value: f64, units: &str) -> TemplateInput {
+    TemplateInput {
+        name: name.to_string(),
+        value,
+        units: units.to_string(),
+    }
+}
+
+fn cuboid(
+    name: &str,
+    centre: [&str; 3],
+    size: [&str; 3],
+    density: &str,
+    colour: Color32,
+) -> BodyRecipe {
+    BodyRecipe {
+        name: name.to_string(),
+        shape: Shape::Cuboid,
+        count: one(),
+        centre: centre.map(String::from),
+        size: size.map(String::from),
+        rotation: zeros(),
+        density: density.to_string(),
+        colour,
+    }
+}
+
+/// Outer body of the lining and the void inside it along x, with round sections as
+/// squares of equal area. The void density removes the lining, leaving the contents.
+fn lined(
+    name: &str,
+    outer: &str,
+    inner: &str,
+    depth: &str,
+    [wall, contents]: [&str; 2],
+) -> Vec<BodyRecipe> {
+    let outer_side = format!("({outer}) * {ROUND}");
+    let inner_side = format!("({inner}) * {ROUND}");
+    vec![
+        cuboid(
+            &format!("{name} lining"),
+            ["0", "0", depth],
+            ["length", &outer_side, &outer_side],
+            &format!("{wall} - ground"),
+            Color32::from_rgb(150, 150, 150),
+        ),
+        cuboid(
+            &format!("{name} void"),
+            ["0", "0", depth],
+            ["length", &inner_side, &inner_side],
+            &format!("{contents} - {wall}"),
+            Color32::from_rgb(80, 120, 200),
+        ),
+    ]
+}
+
+impl Template {
+    /// Templates shipped with the app. Densities are contrasts against the host `ground`.
+    pub fn builtin() -> Vec<Template> {
+        let brown = Color32::from_rgb(160, 110, 60);
+        vec![
+            Template {
+                name: "Mine shaft".to_string(),
+                description: "Vertical shaft, open or backfilled.".to_string(),
+                inputs: vec![
+                    input("diameter", 4., "m"),
+                    input("depth", 30., "m"),
+                    input("top", 0., "m"),
+                    input("ground", 2000., "kg/m^3"),
+                    input("fill", 0., "kg/m^3"),
+                ],
+                bodies: vec![cuboid(
+                    "Shaft",
+                    ["0", "0", "-(top + depth / 2)"],
+                    [
+                        &format!("diameter * {ROUND}"),
+                        &format!("diameter * {ROUND}"),
+                        "depth",
+                    ],
+                    "fill - ground",
+                    brown,
+                )],
+            },
+            Template {
+                name: "Lined tunnel".to_string(),
+                description: "Tunnel along the azimuth with a concrete lining.".to_string(),
+                inputs: vec![
+                    input("diameter", 6., "m"),
+                    input("lining", 0.4, "m"),
+                    input("length", 50., "m"),
+                    input("cover", 10., "m"),
+                    input("ground", 2000., "kg/m^3"),
+                    input("concrete", 2400., "kg/m^3"),
+                ],
+                bodies: lined(
+                    "Tunnel",
+                    "diameter + 2 * lining",
+                    "diameter",
+                    "-(cover + lining + diameter / 2)",
+                    ["concrete", "0"],
+                ),
+            },
+            Template {
+                name: "Buried pipe".to_string(),
+                description: "Pipe along the azimuth, empty or full.".to_string(),
+                inputs: vec![
+                    input("diameter", 1., "m"),
+                    input("wall", 0.05, "m"),
+                    input("length", 40., "m"),
+                    input("cover", 1.5, "m"),
+                    input("ground", 1900., "kg/m^3"),
+                    input("pipe", 7850., "kg/m^3"),
+                    input("contents", 0., "kg/m^3"),
+                ],
+                bodies: lined(
+                    "Pipe",
+                    "diameter",
+                    "diameter - 2 * wall",
+                    "-(cover + diameter / 2)",
+                    ["pipe", "contents"],
+                ),
+            },
+            Template {
+                name: "Box culvert".to_string(),
+                description: "Rectangular concrete culvert along the azimuth.".to_string(),
+                inputs: vec![
+                    input("width", 3., "m"),
+                    input("height", 2., "m"),
+                    input("wall", 0.3, "m"),
+                    input("length", 20., "m"),
+                    input("cover", 1., "m"),
+                    input("ground", 2000., "kg/m^3"),
+                    input("concrete", 2400., "kg/m^3"),
+                ],
+                bodies: vec![
+                    cuboid(
+                        "Culvert walls",
+                        ["0", "0", "-(cover + wall + height / 2)"],
+                        ["length", "width + 2 * wall", "height + 2 * wall"],
+                        "concrete - ground",
+                        Color32::from_rgb(150, 150, 150),
+                    ),
+                    cuboid(
+                        "Culvert void",
+                        ["0", "0", "-(cover + wall + height / 2)"],
+                        ["length", "width", "height"],
+                        "-concrete",
+                        Color32::from_rgb(80, 120, 200),
+                    ),
+                ],
+            },
+            Template {
+                name: "Basement".to_string(),
+                description: "Open basement with a floor slab.".to_string(),
+                inputs: vec![
+                    input("length", 15., "m"),
+                    input("width", 10., "m"),
+                    input("depth", 3., "m"),
+                    input("slab", 0.3, "m"),
+                    input("ground", 1900., "kg/m^3"),
+                    input("concrete", 2400., "kg/m^3"),
+                ],
+                bodies: vec![
+                    cuboid(
+                        "Basement",
+                        ["0", "0", "-depth / 2"],
+                        ["length", "width", "depth"],
+                        "-ground",
+                        Color32::from_rgb(80, 120, 200),
+                    ),
+                    cuboid(
+                        "Floor slab",
+                        ["0", "0", "-(depth + slab / 2)"],
+                        ["length", "width", "slab"],
+                        "concrete - ground",
+                        Color32::from_rgb(150, 150, 150),
+                    ),
+                ],
+            },
+            Template {
+                name: "Sinkhole".to_string(),
+                description: "Cone of loosened fill narrowing with depth, in steps.".to_string(),
+                inputs: vec![
+                    input("diameter", 10., "m"),
+                    input("depth", 8., "m"),
+                    input("steps", 4., ""),
+                    input("ground", 2000., "kg/m^3"),
+                    input("fill", 1600., "kg/m^3"),
+                ],
+                bodies: vec![BodyRecipe {
+                    count: "steps".to_string(),
+                    ..cuboid(
+                        "Sinkhole",
+                        ["0", "0", "-(i + 0.5) * depth / steps"],
+                        [
+                            &format!("diameter * {ROUND} * (1 - i / steps)"),
+                            &format!("diameter * {ROUND} * (1 - i / steps)"),
+                            "depth / steps",
+                        ],
+                        "fill - ground",
+                        brown,
+                    )
+                }],
+            },
+            Template {
+                name: "Cavity".to_string(),
+                description: "Air-filled spherical void.".to_string(),
+                inputs: vec![
+                    input("diameter", 5., "m"),
+                    input("depth", 10., "m"),
+                    input("ground", 2000., "kg/m^3"),
+                ],
+                bodies: vec![BodyRecipe {
+                    shape: Shape::Sphere,
+                    ..cuboid(
+                        "Cavity",
+                        ["0", "0", "-depth"],
+                        ["diameter / 2", "0", "0"],
+                        "-ground",
+                        Color32::from_rgb(80, 120, 200),
+                    )
+                }],
+            },
+            Template {
+                name: "Layered ground".to_string(),
+                description: "Wide flat layers with density increasing with depth.".to_string(),
+                inputs: vec![
+                    input("layers", 3., ""),
+                    input("thickness", 5., "m"),
+                    input("extent", 200., "m"),
+                    input("top", 50., "kg/m^3"),
+                    input("step", 50., "kg/m^3"),
+                ],
+                bodies: vec![BodyRecipe {
+                    count: "layers".to_string(),
+                    ..cuboid(
+                        "Layer",
+                        ["0", "0", "-(i + 0.5) * thickness"],
+                        ["extent", "extent", "thickness"],
+                        "top + i * step",
+                        brown,
+                    )
+                }],
+            },
+        ]
+    }
+
+    /// Bodies of the template for the input `values`, turned by `azimuth` degrees
+    /// anticlockwise about the vertical and placed at `origin`.
+    pub fn generate(
+        &self,
+        values: &[f64],
+        origin: [f64; 3],
+        azimuth: f64,
+    ) -> Result<Vec<(String, Color32, GravityObject)>, String> {
+        let mut variables: BTreeMap<String, f64> = self
+            .inputs
+            .iter()
+            .zip(values)
+            .map(|(input, value)| (input.name.to_string(), *value))
+            .collect();
+        variables.insert("pi".to_string(), PI);
+        let (sin, cos) = azimuth.to_radians().sin_cos();
+
+        let mut bodies = vec![];
+        for recipe in &self.bodies {
+            let context = |field: &str, err: String| format!("{} {field}: {err}", recipe.name);
+            variables.insert("i".to_string(), 0.);
+            let count = evaluate(&recipe.count, &variables)
+                .map_err(|err| context("count", err))?
+                .round()
+                .max(0.);
+            if count > MAX_COUNT as f64 {
+                return Err(context(
+                    "count",
+                    format!("{count} is more than {MAX_COUNT}"),
+                ));
+            }
+            let count = count as usize;
+            for i in 0..count {
+                variables.insert("i".to_string(), i as f64);
+                let eval3 = |field: &str, expressions: &[String; 3]| {
+                    let mut values = [0.; 3];
+                    for k in 0..3 {
+                        values[k] = evaluate(&expressions[k], &variables)
+                            .map_err(|err| context(field, err))?;
+                    }
+                    Ok::<_, String>(values)
+                };
+                let [x, y, z] = eval3("centre", &recipe.centre)?;
+                let size = eval3("size", &recipe.size)?;
+                let rotation = eval3("rotation", &recipe.rotation)?;
+                let density =
+                    evaluate(&recipe.density, &variables).map_err(|err| context("density", err))?;
+                let centre = [
+                    origin[0] + x * cos - y * sin,
+                    origin[1] + x * sin + y * cos,
+                    origin[2] + z,
+                ];
+                let object = match recipe.shape {
+                    Shape::Cuboid => GravityObject::Cuboid(Cuboid {
+                        x_length: size[0],
+                        y_length: size[1],
+                        z_length: size[2],
+                        x_centroid: centre[0],
+                        y_centroid: centre[1],
+                        z_centroid: centre[2],
+                        x_rotation: rotation[0].to_radians(),
+                        y_rotation: rotation[1].to_radians(),
+                        z_rotation: (rotation[2] + azimuth).to_radians(),
+                        density,
+                    }),
+                    Shape::Sphere => GravityObject::Sphere(Sphere {
+                        x_centroid: centre[0],
+                        y_centroid: centre[1],
+                        z_centroid: centre[2],
+                        radius: size[0],
+                        density,
+                    }),
+                };
+                let name = if count > 1 {
+                    format!("{} {}", recipe.name, i + 1)
+                } else {
+                    recipe.name.to_string()
+                };
+                bodies.push((name, recipe.colour, object));
+            }
+        }
+        Ok(bodies)
+    }
+
+    /// Template reproducing the selected cuboids and spheres about their mean centre, as
+    /// a starting point for editing the JSON by hand. Imported meshes are left out.
+    pub fn from_selection(model: &Model, name: &str) -> Template {
+        let selected: Vec<&GravityModelObject> = model
+            .objects
+            .values()
+            .flatten()
+            .filter(|obj| obj.is_selected)
+            .collect();
+        let mut mean = [0.; 3];
+        for obj in &selected {
+            let centre = obj.object.as_calc().centre();
+            for k in 0..3 {
+                mean[k] += centre[k] / selected.len() as f64;
+            }
+        }
+        let number = |value: f64| format!("{value}");
+        let bodies = selected
+            .iter()
+            .filter_map(|obj| {
+                let (shape, size, rotation, density) = match &obj.object {
+                    GravityObject::Sphere(sphere) => (
+                        Shape::Sphere,
+                        [sphere.radius, 0., 0.].map(number),
+                        zeros(),
+                        sphere.density,
+                    ),
+                    GravityObject::Cuboid(c) => (
+                        Shape::Cuboid,
+                        [c.x_length, c.y_length, c.z_length].map(number),
+                        [c.x_rotation, c.y_rotation, c.z_rotation].map(|r| number(r.to_degrees())),
+                        c.density,
+                    ),
+                    GravityObject::Polyhedron(_) => return None,
+                };
+                let centre = obj.object.as_calc().centre();
+                Some(BodyRecipe {
+                    name: obj.name.to_string(),
+                    shape,
+                    count: one(),
+                    centre: [0, 1, 2].map(|k| number(centre[k] - mean[k])),
+                    size,
+                    rotation,
+                    density: number(density),
+                    colour: obj.colour,
+                })
+            })
+            .collect();
+        Template {
+            name: name.to_string(),
+            description: String::new(),
+            inputs: vec![],
+            bodies,
+        }
+    }
+}
+
+/// Value of an arithmetic expression with `+ - * / ^`, brackets, numbers, `variables`
+/// and the functions sqrt, abs, sin and cos (radians).
+pub fn evaluate(expression: &str, variables: &BTreeMap<String, f64>) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        position: 0,
+        variables,
+    };
+    let value = parser.sum()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected '{c}' in \"{expression}\"")),
+    }
+}
+
+/// Recursive descent over the expression grammar, lowest precedence first.
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    variables: &'a BTreeMap<String, f64>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let rhs = self.product()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(op @ ('*' | '/')) = self.peek() {
+            self.position += 1;
+            let rhs = self.unary()?;
+            value = if op == '*' { value * rhs } else { value / rhs };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('-') => {
+                self.position += 1;
+                Ok(-self.unary()?)
+            }
+            Some('+') => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// Right associative, binding tighter than unary minus on its left.
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+        if self.peek() == Some('^') {
+            self.position += 1;
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        let start = self.position;
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let value = self.sum()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                while let Some(c) = self.peek() {
+                    let exponent_sign = matches!(c, '+' | '-')
+                        && matches!(self.chars.get(self.position - 1), Some('e' | 'E'));
+                    if c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E') || exponent_sign {
+                        self.position += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let text: String = self.chars[start..self.position].iter().collect();
+                text.parse().map_err(|_| format!("invalid number '{text}'"))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_') {
+                    self.position += 1;
+                }
+                let name: String = self.chars[start..self.position].iter().collect();
+                if self.peek() == Some('(') {
+                    self.position += 1;
+                    let argument = self.sum()?;
+                    self.expect(')')?;
+                    match name.as_str() {
+                        "sqrt" => Ok(argument.sqrt()),
+                        "abs" => Ok(argument.abs()),
+                        "sin" => Ok(argument.sin()),
+                        "cos" => Ok(argument.cos()),
+                        _ => Err(format!("unknown function '{name}'")),
+                    }
+                } else {
+                    self.variables
+                        .get(&name)
+                        .copied()
+                        .ok_or_else(|| format!("unknown input '{name}'"))
+                }
+            }
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{c}'"))
+        }
+    }
+}
+
+/// Built-in and user templates, and the inputs for placing one in the model.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct TemplateLibrary {
+    /// Templates loaded or saved by the user, kept with the app state.
+    pub user: Vec<Template>,
+    /// Index into the built-in templates followed by the user templates.
+    selected: usize,
+    values: Vec<f64>,
+    origin: [f64; 3],
+    /// Degrees anticlockwise from the x axis.
+    azimuth: f64,
+    /// Number of templates placed, for naming their groups.
+    n_placed: usize,
+    new_name: String,
+    #[serde(skip)]
+    builtin: Vec<Template>,
+    #[serde(skip)]
+    picked: PickedFile,
+    #[serde(skip)]
+    status: String,
+}
+
+impl Default for TemplateLibrary {
+    fn default() -> Self {
+        Self {
+            user: vec![],
+            selected: 0,
+            values: vec![],
+            origin: [0.; 3],
+            azimuth: 0.,
+            n_placed: 0,
+            new_name: String::new(),
+            builtin: Template::builtin(),
+            picked: PickedFile::default(),
+            status: String::new(),
+        }
+    }
+}
+
+impl TemplateLibrary {
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.values = self.template().map_or(vec![], |template| {
+            template.inputs.iter().map(|input| input.value).collect()
+        });
+    }
+
+    fn template(&self) -> Option<&Template> {
+        self.builtin.iter().chain(&self.user).nth(self.selected)
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, model: &mut Model) {
+        if self.builtin.is_empty() {
+            self.builtin = Template::builtin();
+        }
+        let names: Vec<String> = self
+            .builtin
+            .iter()
+            .map(|template| template.name.to_string())
+            .chain(
+                self.user
+                    .iter()
+                    .map(|template| format!("{} (user)", template.name)),
+            )
+            .collect();
+        if self.selected >= names.len() {
+            self.select(0);
+        }
+        let mut selected = self.selected;
+        egui::ComboBox::from_label("Template")
+            .selected_text(&names[selected])
+            .show_ui(ui, |ui| {
+                for (k, name) in names.iter().enumerate() {
+                    ui.selectable_value(&mut selected, k, name);
+                }
+            });
+        let Some(template) = self.template().cloned() else {
+            return;
+        };
+        if selected != self.selected || self.values.len() != template.inputs.len() {
+            self.select(selected);
+        }
+        if !template.description.is_empty() {
+            ui.weak(&template.description);
+        }
+
+        egui::Grid::new("template_inputs").show(ui, |ui| {
+            for (input, value) in template.inputs.iter().zip(self.values.iter_mut()) {
+                ui.label(&input.name);
+                ui.add(
+                    egui::DragValue::new(value)
+                        .speed(0.1)
+                        .suffix(format!(" {}", input.units)),
+                );
+                ui.end_row();
+            }
+            ui.label("origin");
+            ui.horizontal(|ui| {
+                for value in self.origin.iter_mut() {
+                    ui.add(egui::DragValue::new(value).speed(0.1));
+                }
+            });
+            ui.end_row();
+            ui.label("azimuth");
+            ui.add(egui::DragValue::new(&mut self.azimuth).suffix("°"));
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Add to model").clicked() {
+                self.status = match template.generate(&self.values, self.origin, self.azimuth) {
+                    Ok(bodies) => self.place(model, &template.name, bodies),
+                    Err(err) => err,
+                };
+            }
+            if self.selected >= self.builtin.len() && ui.button("Remove").clicked() {
+                self.user.remove(self.selected - self.builtin.len());
+                self.select(0);
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Save JSON…").clicked() {
+                let mut template = template.clone();
+                for (input, value) in template.inputs.iter_mut().zip(&self.values) {
+                    input.value = *value;
+                }
+                let json = serde_json::to_string_pretty(&template).unwrap();
+                let file_name = format!("{}.json", template.name);
+                self.status = match save_text(&file_name, &json) {
+                    Ok(true) => "Saved".to_string(),
+                    Ok(false) => String::new(),
+                    Err(err) => format!("Save failed: {err}"),
+                };
+            }
+            if ui.button("Load JSON…").clicked() {
+                pick_file(ui.ctx(), self.picked.clone(), "Template", &["json"]);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_name).desired_width(100.));
+            ui.add_enabled_ui(model.number_objects_selected() > 0, |ui| {
+                if ui
+                    .button("Template from selection")
+                    .on_hover_text("Save the selected bodies as a user template without inputs")
+                    .clicked()
+                {
+                    let name = match self.new_name.trim() {
+                        "" => "Selection",
+                        name => name,
+                    };
+                    self.user.push(Template::from_selection(model, name));
+                    self.select(self.builtin.len() + self.user.len() - 1);
+                    self.new_name.clear();
+                }
+            });
+        });
+
+        let picked = self.picked.lock().unwrap().take();
+        if let Some((file_name, bytes)) = picked {
+            let template = bytes.and_then(|bytes| {
+                serde_json::from_slice::<Template>(&bytes).map_err(|err| err.to_string())
+            });
+            self.status = match template {
+                Ok(template) => {
+                    self.user.push(template);
+                    self.select(self.builtin.len() + self.user.len() - 1);
+                    format!("Loaded {file_name}")
+                }
+                Err(err) => format!("Could not read {file_name}: {err}"),
+            };
+        }
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+    }
+
+    /// Add the bodies to the model as a new group.
+    fn place(
+        &mut self,
+        model: &mut Model,
+        name: &str,
+        bodies: Vec<(String, Color32, GravityObject)>,
+    ) -> String {
+        self.n_placed += 1;
+        let group = format!("{name} {}", self.n_placed);
+        model.add_group(&group);
+        model.deselect_all();
+        let n_bodies = bodies.len();
+        let mut n_added = 0;
+        for (body_name, colour, object) in bodies {
+            let id = model.object_counter;
+            model.add_object(GravityModelObject {
+                object,
+                name: body_name,
+                id,
+                colour,
+                is_selected: true,
+                is_hidden: false,
+                is_locked: false,
+            });
+            if model.objects.contains_key(&id.to_string()) {
+                model.set_group(&id.to_string(), Some(&group));
+                n_added += 1;
+            }
+        }
+        if n_added < n_bodies {
+            format!("Added {n_added} of {n_bodies} bodies, the model is full")
+        } else {
+            format!("Added {group}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> f64 {
+        let variables = BTreeMap::from([("depth".to_string(), 4.), ("pi".to_string(), PI)]);
+        evaluate(expression, &variables).unwrap()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7.);
+        assert_eq!(eval("(1 + 2) * 3"), 9.);
+        assert_eq!(eval("8 / 4 / 2"), 1.);
+        assert_eq!(eval("10 - 4 - 3"), 3.);
+        assert_eq!(eval("-(depth + 2) / 2"), -3.);
+        assert_eq!(eval("2 * sqrt(depth) ^ 2"), 8.);
+    }
+
+    #[test]
+    fn unary_minus_and_powers() {
+        assert_eq!(eval("-2 ^ 2"), -4.);
+        assert_eq!(eval("(-2) ^ 2"), 4.);
+        assert_eq!(eval("2 ^ -1"), 0.5);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.);
+        assert_eq!(eval("--3"), 3.);
+    }
+
+    #[test]
+    fn numbers_with_exponents() {
+        assert_eq!(eval("1.5e3"), 1500.);
+        assert_eq!(eval("2e-1 + 1"), 1.2);
+        assert_eq!(eval("1E+2 - 1"), 99.);
+        assert_eq!(eval(".5"), 0.5);
+        assert!((eval("cos(pi)") + 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn errors() {
+        let variables = BTreeMap::new();
+        for expression in ["", "1 +", "(1", "1)", "depth", "log(2)", "1e", "2(3)"] {
+            assert!(evaluate(expression, &variables).is_err(), "{expression}");
+        }
+    }
+
+    #[test]
+    fn count_limit() {
+        let mut template = Template::builtin()
+            .into_iter()
+            .find(|template| template.name == "Sinkhole")
+            .unwrap();
+        let bodies = template.generate(&[10., 8., 4., 2000., 1600.], [0.; 3], 0.);
+        assert_eq!(bodies.unwrap().len(), 4);
+        assert!(template
+            .generate(&[10., 8., 1e9, 2000., 1600.], [0.; 3], 0.)
+            .is_err());
+        template.bodies[0].count = "1 / 0".to_string();
+        assert!(template
+            .generate(&[10., 8., 4., 2000., 1600.], [0.; 3], 0.)
+            .is_err());
+    }
+}
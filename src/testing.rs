@@ -0,0 +1,45 @@
+//! Fixtures shared by the unit tests.
+
+use crate::gravity_objects::{DataType, GravityCalc, GravityModelObject, GravityObject, Sphere};
+use crate::map::GridData;
+use crate::model::Model;
+use egui::Color32;
+use ndarray::{Array1, Array2};
+
+/// Model of the `spheres`, with ids counting from 0.
+pub fn sphere_model(spheres: &[Sphere]) -> Model {
+    let mut model = Model::default();
+    for (id, sphere) in spheres.iter().enumerate() {
+        model.add_object(GravityModelObject {
+            object: GravityObject::Sphere(sphere.clone()),
+            name: format!("Sphere {id}"),
+            id: id as u128,
+            colour: Color32::WHITE,
+            is_selected: false,
+            is_hidden: false,
+            is_locked: false,
+        });
+    }
+    model
+}
+
+/// Stations and gz of `sphere` on a square grid of `n` by `n` nodes at height `z`,
+/// centred on the origin.
+pub fn sphere_grid(sphere: &Sphere, n: usize, spacing: f64, z: f64) -> (Array2<f64>, GridData) {
+    let x = Array1::from_shape_fn(n, |i| (i as f64 - (n / 2) as f64) * spacing);
+    let points = Array2::from_shape_fn((n * n, 3), |(k, c)| match c {
+        0 => x[k / n],
+        1 => x[k % n],
+        _ => z,
+    });
+    let values = sphere
+        .calculate(&DataType::Gz, &points)
+        .into_shape((n, n))
+        .unwrap();
+    let grid = GridData {
+        x: x.clone(),
+        y: x,
+        values,
+    };
+    (points, grid)
+}
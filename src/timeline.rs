@@ -0,0 +1,329 @@
+use crate::gravity_objects::Parameter;
+use crate::model::Model;
+use egui::{Context, Ui};
+
+/// Keyframed value of one object parameter. Values between keys are interpolated
+/// linearly and held before the first and after the last key.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Track {
+    pub object: String,
+    pub parameter: Parameter,
+    /// Time and value pairs sorted by time.
+    pub keys: Vec<[f64; 2]>,
+}
+
+impl Track {
+    pub fn value(&self, time: f64) -> Option<f64> {
+        let (first, last) = (self.keys.first()?, self.keys.last()?);
+        if time <= first[0] {
+            return Some(first[1]);
+        }
+        if time >= last[0] {
+            return Some(last[1]);
+        }
+        let k = self.keys.iter().position(|key| key[0] > time)?;
+        let [[t0, v0], [t1, v1]] = [self.keys[k - 1], self.keys[k]];
+        Some(v0 + (v1 - v0) * (time - t0) / (t1 - t0))
+    }
+
+    /// Add a key, replacing any key at the same time.
+    fn set_key(&mut self, time: f64, value: f64) {
+        match self.keys.iter().position(|key| key[0] >= time) {
+            Some(k) if self.keys[k][0] == time => self.keys[k][1] = value,
+            Some(k) => self.keys.insert(k, [time, value]),
+            None => self.keys.push([time, value]),
+        }
+    }
+}
+
+/// Named survey time, e.g. the baseline and each repeat survey.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Epoch {
+    pub name: String,
+    /// Time in years.
+    pub time: f64,
+}
+
+/// Changes of the model over time for time-lapse (4D) surveys. Object parameters are
+/// keyframed and the objects take their values at the current time.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Timeline {
+    pub epochs: Vec<Epoch>,
+    pub tracks: Vec<Track>,
+    /// Current time in years.
+    pub time: f64,
+}
+
+impl Timeline {
+    /// Span of the epochs and keys.
+    pub fn range(&self) -> [f64; 2] {
+        let times = self.epochs.iter().map(|epoch| epoch.time).chain(
+            self.tracks
+                .iter()
+                .flat_map(|track| track.keys.iter().map(|key| key[0])),
+        );
+        let range = times.fold([f64::INFINITY, f64::NEG_INFINITY], |[min, max], t| {
+            [min.min(t), max.max(t)]
+        });
+        if range[0] < range[1] {
+            range
+        } else if range[0].is_finite() {
+            [range[0], range[0] + 1.]
+        } else {
+            [0., 1.]
+        }
+    }
+
+    pub fn set_key(&mut self, object: &str, parameter: Parameter, time: f64, value: f64) {
+        let track = match self
+            .tracks
+            .iter_mut()
+            .position(|track| track.object == object && track.parameter == parameter)
+        {
+            Some(k) => &mut self.tracks[k],
+            None => {
+                self.tracks.push(Track {
+                    object: object.to_string(),
+                    parameter,
+                    keys: vec![],
+                });
+                self.tracks.last_mut().unwrap()
+            }
+        };
+        track.set_key(time, value);
+    }
+
+    /// Drop the tracks of a deleted object.
+    pub fn remove_object(&mut self, object: &str) {
+        self.tracks.retain(|track| track.object != object);
+    }
+
+    pub fn add_epoch(&mut self, name: String, time: f64) {
+        let k = self.epochs.partition_point(|epoch| epoch.time <= time);
+        self.epochs.insert(k, Epoch { name, time });
+    }
+}
+
+impl Model {
+    /// Set the keyframed parameters of the objects to their values at `time`.
+    pub fn apply_time(&mut self, time: f64) {
+        self.timeline.time = time;
+        for track in &self.timeline.tracks {
+            if let (Some(Some(obj)), Some(value)) =
+                (self.objects.get_mut(&track.object), track.value(time))
+            {
+                track.parameter.set(&mut obj.object, value);
+            }
+        }
+    }
+
+    /// Copy of the model at `time`.
+    pub fn at_time(&self, time: f64) -> Model {
+        let mut model = self.clone();
+        model.apply_time(time);
+        model
+    }
+
+    /// Key the current values of `parameters` of the selected objects at the current
+    /// time. Parameters an object doesn't have are skipped.
+    pub fn key_selected(&mut self, parameters: &[Parameter]) {
+        let time = self.timeline.time;
+        for obj in self
+            .objects
+            .values()
+            .flatten()
+            .filter(|obj| obj.is_selected)
+        {
+            for parameter in parameters {
+                if let Some(value) = parameter.get(&obj.object) {
+                    self.timeline
+                        .set_key(&obj.id.to_string(), *parameter, time, value);
+                }
+            }
+        }
+    }
+}
+
+/// Playback and keyframing of the model timeline.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct TimelineDialog {
+    pub open: bool,
+    #[serde(skip)]
+    pub playing: bool,
+    /// Playback speed in years per second.
+    pub speed: f64,
+    pub loop_playback: bool,
+    pub key_position: bool,
+    pub key_size: bool,
+    pub key_density: bool,
+    /// Epochs compared by the difference anomaly, monitor minus base.
+    pub base: usize,
+    pub monitor: usize,
+    new_epoch: String,
+}
+
+impl Default for TimelineDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            playing: false,
+            speed: 1.,
+            loop_playback: true,
+            key_position: true,
+            key_size: false,
+            key_density: true,
+            base: 0,
+            monitor: 1,
+            new_epoch: String::new(),
+        }
+    }
+}
+
+impl TimelineDialog {
+    fn key_parameters(&self) -> Vec<Parameter> {
+        let mut parameters = vec![];
+        if self.key_position {
+            parameters.extend([
+                Parameter::XCentroid,
+                Parameter::YCentroid,
+                Parameter::ZCentroid,
+            ]);
+        }
+        if self.key_size {
+            parameters.extend([
+                Parameter::XLength,
+                Parameter::YLength,
+                Parameter::ZLength,
+                Parameter::Radius,
+            ]);
+        }
+        if self.key_density {
+            parameters.push(Parameter::Density);
+        }
+        parameters
+    }
+
+    /// Advance the current time while playing.
+    pub fn update(&mut self, ctx: &Context, model: &mut Model) {
+        if !self.playing {
+            return;
+        }
+        let [start, end] = model.timeline.range();
+        let mut time = model.timeline.time + self.speed * ctx.input().stable_dt as f64;
+        if time > end {
+            if self.loop_playback {
+                time = start;
+            } else {
+                time = end;
+                self.playing = false;
+            }
+        }
+        model.apply_time(time);
+        ctx.request_repaint();
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, model: &mut Model) {
+        let [start, end] = model.timeline.range();
+        let mut time = model.timeline.time;
+        ui.horizontal(|ui| {
+            let label = if self.playing { "⏸" } else { "▶" };
+            if ui.button(label).clicked() {
+                self.playing = !self.playing;
+            }
+            ui.add(
+                egui::Slider::new(&mut time, start..=end)
+                    .text("years")
+                    .clamp_to_range(false),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.speed)
+                    .speed(0.1)
+                    .suffix(" years/s"),
+            );
+            ui.checkbox(&mut self.loop_playback, "Loop");
+        });
+        if time != model.timeline.time {
+            model.apply_time(time);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.key_position, "Position");
+            ui.checkbox(&mut self.key_size, "Size");
+            ui.checkbox(&mut self.key_density, "Density");
+        });
+        ui.add_enabled_ui(model.number_objects_selected() > 0, |ui| {
+            if ui
+                .button("Key selected")
+                .on_hover_text("Record the checked parameters of the selected objects now")
+                .clicked()
+            {
+                model.key_selected(&self.key_parameters());
+            }
+        });
+        let mut remove_track = None;
+        for (k, track) in model.timeline.tracks.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let name = model
+                    .objects
+                    .get(&track.object)
+                    .and_then(|obj| obj.as_ref())
+                    .map_or(track.object.as_str(), |obj| obj.name.as_str());
+                ui.label(format!(
+                    "{name} {}: {} keys",
+                    track.parameter.name(),
+                    track.keys.len()
+                ));
+                if ui.small_button("🗑").clicked() {
+                    remove_track = Some(k);
+                }
+            });
+        }
+        if let Some(k) = remove_track {
+            model.timeline.tracks.remove(k);
+        }
+
+        ui.separator();
+        ui.label("Epochs");
+        let mut remove_epoch = None;
+        let mut go_to = None;
+        for (k, epoch) in model.timeline.epochs.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut epoch.name).desired_width(80.));
+                ui.add(
+                    egui::DragValue::new(&mut epoch.time)
+                        .speed(0.1)
+                        .suffix(" yr"),
+                );
+                if ui.small_button("Go").clicked() {
+                    go_to = Some(epoch.time);
+                }
+                if ui.small_button("🗑").clicked() {
+                    remove_epoch = Some(k);
+                }
+            });
+        }
+        if let Some(k) = remove_epoch {
+            model.timeline.epochs.remove(k);
+        }
+        if let Some(time) = go_to {
+            model.apply_time(time);
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_epoch).desired_width(80.));
+            if ui.button("Add epoch now").clicked() {
+                let name = if self.new_epoch.trim().is_empty() {
+                    format!("Epoch {}", model.timeline.epochs.len() + 1)
+                } else {
+                    self.new_epoch.trim().to_string()
+                };
+                model.timeline.add_epoch(name, model.timeline.time);
+                self.new_epoch.clear();
+            }
+        });
+    }
+}
@@ -0,0 +1,216 @@
+use crate::gravity_objects::{
+    rotation_matrix_x, rotation_matrix_y, rotation_matrix_z, GravityObject,
+};
+use crate::model::Model;
+use egui::Ui;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TransformMode {
+    /// Move, rotate and scale by the given amounts.
+    Relative,
+    /// Set the centre, rotation angles and size to the given values.
+    Absolute,
+}
+
+/// Numeric transform of the selected objects.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct TransformDialog {
+    pub open: bool,
+    pub mode: TransformMode,
+    /// Offset (relative) or centre (absolute) in metres.
+    pub translate: [f64; 3],
+    /// Rotation about the x, y and z axes in degrees.
+    pub rotate: [f64; 3],
+    /// Scale factors (relative) or dimensions in metres (absolute).
+    pub scale: [f64; 3],
+}
+
+impl Default for TransformDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            mode: TransformMode::Relative,
+            translate: [0.; 3],
+            rotate: [0.; 3],
+            scale: [1.; 3],
+        }
+    }
+}
+
+impl TransformDialog {
+    /// Reset the fields to the identity transform (relative) or to the first selected
+    /// object (absolute).
+    fn reset(&mut self, model: &Model) {
+        let selected = model.objects.values().flatten().find(|obj| obj.is_selected);
+        match (self.mode, selected) {
+            (TransformMode::Absolute, Some(obj)) => {
+                let centre = obj.object.as_calc().centre();
+                self.translate = [centre[0], centre[1], centre[2]];
+                match &obj.object {
+                    GravityObject::Cuboid(cuboid) => {
+                        self.rotate = [cuboid.x_rotation, cuboid.y_rotation, cuboid.z_rotation]
+                            .map(f64::to_degrees);
+                        self.scale = [cuboid.x_length, cuboid.y_length, cuboid.z_length];
+                    }
+                    GravityObject::Sphere(sphere) => {
+                        self.rotate = [0.; 3];
+                        self.scale = [2. * sphere.radius; 3];
+                    }
+                    GravityObject::Polyhedron(polyhedron) => {
+                        self.rotate = [0.; 3];
+                        self.scale = polyhedron.size();
+                    }
+                }
+            }
+            _ => {
+                self.translate = [0.; 3];
+                self.rotate = [0.; 3];
+                self.scale = [1.; 3];
+            }
+        }
+    }
+
+    fn apply_translate(&self, model: &mut Model) {
+        for obj in model.objects.values_mut().flatten() {
+            if obj.is_selected && !obj.is_locked {
+                let centre = obj.object.as_calc().centre();
+                let centre = match self.mode {
+                    TransformMode::Relative => [
+                        centre[0] + self.translate[0],
+                        centre[1] + self.translate[1],
+                        centre[2] + self.translate[2],
+                    ],
+                    TransformMode::Absolute => self.translate,
+                };
+                obj.object.set_centre(centre);
+            }
+        }
+    }
+
+    fn apply_rotate(&self, model: &mut Model) {
+        let [x, y, z] = self.rotate.map(f64::to_radians);
+        for obj in model.objects.values_mut().flatten() {
+            if obj.is_selected && !obj.is_locked {
+                match (&mut obj.object, self.mode) {
+                    (GravityObject::Cuboid(cuboid), TransformMode::Absolute) => {
+                        [cuboid.x_rotation, cuboid.y_rotation, cuboid.z_rotation] = [x, y, z];
+                    }
+                    (object, _) => object.rotate(
+                        &rotation_matrix_x(x)
+                            .dot(&rotation_matrix_y(y))
+                            .dot(&rotation_matrix_z(z)),
+                    ),
+                }
+            }
+        }
+    }
+
+    fn apply_scale(&self, model: &mut Model) {
+        if self.scale.iter().any(|s| *s <= 0.) {
+            return;
+        }
+        for obj in model.objects.values_mut().flatten() {
+            if !obj.is_selected || obj.is_locked {
+                continue;
+            }
+            match (&mut obj.object, self.mode) {
+                (GravityObject::Cuboid(cuboid), TransformMode::Relative) => {
+                    cuboid.x_length *= self.scale[0];
+                    cuboid.y_length *= self.scale[1];
+                    cuboid.z_length *= self.scale[2];
+                }
+                (GravityObject::Cuboid(cuboid), TransformMode::Absolute) => {
+                    [cuboid.x_length, cuboid.y_length, cuboid.z_length] = self.scale;
+                }
+                // Spheres scale by the geometric mean so that the volume changes by the
+                // product of the factors.
+                (GravityObject::Sphere(sphere), TransformMode::Relative) => {
+                    sphere.radius *= self.scale.iter().product::<f64>().cbrt();
+                }
+                (GravityObject::Sphere(sphere), TransformMode::Absolute) => {
+                    sphere.radius = self.scale[0] / 2.;
+                }
+                (GravityObject::Polyhedron(polyhedron), TransformMode::Relative) => {
+                    polyhedron.scale(self.scale);
+                }
+                (GravityObject::Polyhedron(polyhedron), TransformMode::Absolute) => {
+                    let size = polyhedron.size();
+                    if size.iter().all(|s| *s > 0.) {
+                        polyhedron.scale([0, 1, 2].map(|k| self.scale[k] / size[k]));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, model: &mut Model) {
+        let n_selected = model.number_objects_selected();
+        ui.horizontal(|ui| {
+            let mode = self.mode;
+            ui.radio_value(&mut self.mode, TransformMode::Relative, "Relative");
+            ui.radio_value(&mut self.mode, TransformMode::Absolute, "Absolute");
+            if self.mode != mode {
+                self.reset(model);
+            }
+            if ui.button("Reset").clicked() {
+                self.reset(model);
+            }
+        });
+        ui.label(format!("{n_selected} object(s) selected"));
+
+        let (translate_label, scale_label) = match self.mode {
+            TransformMode::Relative => ("Move (m)", "Scale (x)"),
+            TransformMode::Absolute => ("Centre (m)", "Size (m)"),
+        };
+        ui.add_enabled_ui(n_selected > 0, |ui| {
+            egui::Grid::new("transform_dialog").show(ui, |ui| {
+                ui.label("");
+                ui.label("x");
+                ui.label("y");
+                ui.label("z");
+                ui.end_row();
+
+                ui.label(translate_label);
+                for value in self.translate.iter_mut() {
+                    ui.add(egui::DragValue::new(value).speed(0.05));
+                }
+                if ui.button("Apply").clicked() {
+                    self.apply_translate(model);
+                }
+                ui.end_row();
+
+                ui.label("Rotate (deg)");
+                for value in self.rotate.iter_mut() {
+                    ui.add(egui::DragValue::new(value).speed(1.));
+                }
+                if ui.button("Apply").clicked() {
+                    self.apply_rotate(model);
+                }
+                ui.end_row();
+
+                ui.label(scale_label);
+                for value in self.scale.iter_mut() {
+                    ui.add(
+                        egui::DragValue::new(value)
+                            .speed(0.01)
+                            .clamp_range(0.001..=1000.),
+                    );
+                }
+                if ui.button("Apply").clicked() {
+                    self.apply_scale(model);
+                }
+                ui.end_row();
+            });
+            if ui.button("Apply all").clicked() {
+                self.apply_translate(model);
+                self.apply_rotate(model);
+                self.apply_scale(model);
+            }
+        });
+        if self.mode == TransformMode::Absolute {
+            ui.label("Absolute sizes of spheres use x as the diameter.");
+            ui.label("Imported meshes have no stored rotation, rotations apply relatively.");
+        }
+    }
+}
@@ -0,0 +1,846 @@
+use crate::gravity_objects::{DataType, Parameter};
+use crate::model::Model;
+use crate::noise::Rng;
+use egui::plot::{Bar, BarChart, Legend, Line, LineStyle, Plot, Points};
+use egui::{Color32, Context, Ui};
+use ndarray::{Array1, Array2, Axis};
+
+const ENVELOPE_COLOUR: Color32 = Color32::from_rgb(214, 39, 40);
+/// Posterior draws forward modelled for the credible envelopes.
+const ENVELOPE_DRAWS: usize = 200;
+
+/// Prior probability of a sampled parameter, in its display units.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Prior {
+    Uniform { min: f64, max: f64 },
+    Normal { mean: f64, sigma: f64 },
+}
+
+impl Prior {
+    /// Log density up to a constant.
+    fn log_density(&self, value: f64) -> f64 {
+        match *self {
+            Prior::Uniform { min, max } => {
+                if (min..=max).contains(&value) {
+                    0.
+                } else {
+                    f64::NEG_INFINITY
+                }
+            }
+            Prior::Normal { mean, sigma } => -0.5 * ((value - mean) / sigma).powi(2),
+        }
+    }
+
+    fn width(&self) -> f64 {
+        match *self {
+            Prior::Uniform { min, max } => max - min,
+            Prior::Normal { sigma, .. } => 2. * sigma,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Prior::Uniform { .. } => "Uniform",
+            Prior::Normal { .. } => "Normal",
+        }
+    }
+}
+
+/// Parameter of one object sampled by the chain.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub id: String,
+    pub parameter: Parameter,
+    pub prior: Prior,
+}
+
+/// Observed data along a profile, `axis` is the column of `points` it runs along.
+pub struct ObservedProfile<'a> {
+    pub name: &'a str,
+    pub points: Array2<f64>,
+    pub axis: usize,
+    pub observed: Array1<f64>,
+}
+
+/// Percentiles of the predicted data at the stations of a profile.
+struct Envelope {
+    name: String,
+    positions: Vec<f64>,
+    observed: Vec<f64>,
+    /// 2.5, 16, 50, 84 and 97.5 percentiles at each station.
+    bands: [Vec<f64>; 5],
+}
+
+/// Affine-invariant ensemble sampler (Goodman & Weare stretch move) over the variables,
+/// and the noise level if it is fitted.
+struct Chain {
+    /// Working copy of the model the variables are set on.
+    model: Model,
+    variables: Vec<Variable>,
+    data_type: DataType,
+    profiles: Vec<(String, usize, Array2<f64>, Array1<f64>)>,
+    points: Array2<f64>,
+    observed: Array1<f64>,
+    sigma: f64,
+    fit_sigma: bool,
+    rng: Rng,
+    walkers: Vec<Vec<f64>>,
+    log_posterior: Vec<f64>,
+    step: usize,
+    steps: usize,
+    burn_in: usize,
+    thin: usize,
+    accepted: usize,
+    proposed: usize,
+    samples: Vec<Vec<f64>>,
+    envelopes: Vec<Envelope>,
+}
+
+impl Chain {
+    fn is_running(&self) -> bool {
+        self.step < self.steps
+    }
+
+    fn n_dimensions(&self) -> usize {
+        self.variables.len() + self.fit_sigma as usize
+    }
+
+    fn set(&mut self, theta: &[f64]) {
+        for (variable, value) in self.variables.iter().zip(theta) {
+            if let Some(Some(obj)) = self.model.objects.get_mut(&variable.id) {
+                variable.parameter.set(&mut obj.object, *value);
+            }
+        }
+    }
+
+    fn predict(&mut self, theta: &[f64], points: &Array2<f64>) -> Array1<f64> {
+        self.set(theta);
+        self.model.calculate(&self.data_type, points)
+    }
+
+    fn log_posterior(&mut self, theta: &[f64]) -> f64 {
+        let mut log_prior = 0.;
+        for (variable, value) in self.variables.iter().zip(theta) {
+            let positive = matches!(
+                variable.parameter,
+                Parameter::XLength | Parameter::YLength | Parameter::ZLength | Parameter::Radius
+            );
+            if positive && *value <= 0. {
+                return f64::NEG_INFINITY;
+            }
+            log_prior += variable.prior.log_density(*value);
+        }
+        let sigma = if self.fit_sigma {
+            let sigma = theta[self.variables.len()];
+            if sigma <= 0. {
+                return f64::NEG_INFINITY;
+            }
+            // Jeffreys prior on the noise level.
+            log_prior -= sigma.ln();
+            sigma
+        } else {
+            self.sigma
+        };
+        if !log_prior.is_finite() {
+            return f64::NEG_INFINITY;
+        }
+        let points = std::mem::take(&mut self.points);
+        let predicted = self.predict(theta, &points);
+        let residuals = &self.observed - &predicted;
+        self.points = points;
+        let n = residuals.len() as f64;
+        log_prior - 0.5 * residuals.dot(&residuals) / sigma.powi(2) - n * sigma.ln()
+    }
+
+    /// One update of every walker.
+    fn advance(&mut self) {
+        const STRETCH: f64 = 2.;
+        let n_walkers = self.walkers.len();
+        let d = self.n_dimensions() as f64;
+        for k in 0..n_walkers {
+            let mut j = (self.rng.uniform() * (n_walkers - 1) as f64) as usize;
+            if j >= k {
+                j += 1;
+            }
+            let z = ((STRETCH - 1.) * self.rng.uniform() + 1.).powi(2) / STRETCH;
+            let proposal: Vec<f64> = self.walkers[j]
+                .iter()
+                .zip(&self.walkers[k])
+                .map(|(xj, xk)| xj + z * (xk - xj))
+                .collect();
+            let log_posterior = self.log_posterior(&proposal);
+            let log_ratio = (d - 1.) * z.ln() + log_posterior - self.log_posterior[k];
+            self.proposed += 1;
+            if self.rng.uniform().ln() < log_ratio {
+                self.walkers[k] = proposal;
+                self.log_posterior[k] = log_posterior;
+                self.accepted += 1;
+            }
+        }
+        self.step += 1;
+        if self.step > self.burn_in && (self.step - self.burn_in) % self.thin == 0 {
+            self.samples.extend(self.walkers.iter().cloned());
+        }
+    }
+
+    /// Forward model posterior draws along each profile for the credible envelopes.
+    fn envelopes(&mut self) {
+        let n = self.samples.len();
+        if n == 0 {
+            return;
+        }
+        let draws: Vec<Vec<f64>> = (0..ENVELOPE_DRAWS.min(n))
+            .map(|i| self.samples[i * n / ENVELOPE_DRAWS.min(n)].clone())
+            .collect();
+        let profiles = std::mem::take(&mut self.profiles);
+        self.envelopes = profiles
+            .iter()
+            .map(|(name, axis, points, observed)| {
+                let predicted: Vec<Array1<f64>> = draws
+                    .iter()
+                    .map(|theta| self.predict(theta, points))
+                    .collect();
+                let bands = [2.5, 16., 50., 84., 97.5].map(|p| {
+                    (0..points.len_of(Axis(0)))
+                        .map(|i| percentile(&predicted.iter().map(|v| v[i]).collect::<Vec<_>>(), p))
+                        .collect()
+                });
+                Envelope {
+                    name: name.to_string(),
+                    positions: points.column(*axis).to_vec(),
+                    observed: observed.to_vec(),
+                    bands,
+                }
+            })
+            .collect();
+        self.profiles = profiles;
+    }
+}
+
+/// Linearly interpolated percentile `p` (0-100) of `values`.
+fn percentile(values: &[f64], p: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let position = p / 100. * (sorted.len() - 1) as f64;
+    let (i, t) = (position.floor() as usize, position.fract());
+    match sorted.get(i + 1) {
+        Some(next) => sorted[i] * (1. - t) + next * t,
+        None => sorted[i],
+    }
+}
+
+/// Bayesian uncertainty of object parameters: an MCMC sampler with priors on the chosen
+/// parameters and a Gaussian noise model, advanced a few steps each frame so the app stays
+/// responsive, including on the web.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Uncertainty {
+    pub open: bool,
+    pub variables: Vec<Variable>,
+    /// Standard deviation of the data errors, the starting value if it is fitted.
+    pub sigma: f64,
+    pub fit_sigma: bool,
+    pub seed: u64,
+    /// Number of walkers, at least twice the number of dimensions plus two.
+    pub walkers: usize,
+    pub steps: usize,
+    pub burn_in: usize,
+    /// Keep every `thin`th step after the burn-in.
+    pub thin: usize,
+    pub steps_per_frame: usize,
+    #[serde(skip)]
+    new_object: Option<String>,
+    #[serde(skip)]
+    new_parameter: Option<Parameter>,
+    #[serde(skip)]
+    chain: Option<Chain>,
+    #[serde(skip)]
+    status: String,
+}
+
+impl Default for Uncertainty {
+    fn default() -> Self {
+        Self {
+            open: false,
+            variables: vec![],
+            sigma: 1.,
+            fit_sigma: false,
+            seed: 0,
+            walkers: 32,
+            steps: 2000,
+            burn_in: 500,
+            thin: 10,
+            steps_per_frame: 5,
+            new_object: None,
+            new_parameter: None,
+            chain: None,
+            status: String::new(),
+        }
+    }
+}
+
+impl Uncertainty {
+    pub fn is_running(&self) -> bool {
+        self.chain.as_ref().map_or(false, Chain::is_running)
+    }
+
+    /// Start a chain from the current values of the variables in `model`.
+    pub fn start(
+        &mut self,
+        model: &Model,
+        data_type: &DataType,
+        profiles: Vec<ObservedProfile<'_>>,
+    ) {
+        self.chain = None;
+        self.variables
+            .retain(|variable| matches!(model.objects.get(&variable.id), Some(Some(_))));
+        if self.variables.is_empty() {
+            self.status = "Add parameters to sample.".to_string();
+            return;
+        }
+        let mut start: Vec<f64> = self
+            .variables
+            .iter()
+            .map(|variable| {
+                let obj = model.objects[&variable.id].as_ref().unwrap();
+                variable.parameter.get(&obj.object).unwrap_or_default()
+            })
+            .collect();
+        let mut scales: Vec<f64> = self
+            .variables
+            .iter()
+            .map(|variable| 1e-2 * variable.prior.width())
+            .collect();
+        if self.fit_sigma {
+            start.push(self.sigma);
+            scales.push(0.1 * self.sigma);
+        }
+        let views: Vec<_> = profiles.iter().map(|p| p.points.view()).collect();
+        let observed: Vec<_> = profiles.iter().map(|p| p.observed.view()).collect();
+        let mut chain = Chain {
+            model: model.clone(),
+            variables: self.variables.clone(),
+            data_type: *data_type,
+            points: ndarray::concatenate(Axis(0), &views).unwrap(),
+            observed: ndarray::concatenate(Axis(0), &observed).unwrap(),
+            profiles: profiles
+                .into_iter()
+                .map(|p| (p.name.to_string(), p.axis, p.points, p.observed))
+                .collect(),
+            sigma: self.sigma,
+            fit_sigma: self.fit_sigma,
+            rng: Rng::new(self.seed),
+            walkers: vec![],
+            log_posterior: vec![],
+            step: 0,
+            steps: self.steps.max(self.burn_in + 1),
+            burn_in: self.burn_in,
+            thin: self.thin.max(1),
+            accepted: 0,
+            proposed: 0,
+            samples: vec![],
+            envelopes: vec![],
+        };
+        if !chain.log_posterior(&start).is_finite() {
+            self.status = "The current model lies outside the priors.".to_string();
+            return;
+        }
+        // Scatter the walkers in a small ball about the start, inside the priors.
+        let n_walkers = self.walkers.max(2 * start.len() + 2);
+        let mut failures = 0;
+        while chain.walkers.len() < n_walkers {
+            let mut theta = start.clone();
+            for (value, scale) in theta.iter_mut().zip(&scales) {
+                *value += scale * chain.rng.normal();
+            }
+            let log_posterior = chain.log_posterior(&theta);
+            if log_posterior.is_finite() {
+                chain.walkers.push(theta);
+                chain.log_posterior.push(log_posterior);
+            } else {
+                // Shrink the ball for a start near the edge of a prior.
+                failures += 1;
+                if failures % 100 == 0 {
+                    scales.iter_mut().for_each(|scale| *scale /= 2.);
+                }
+            }
+        }
+        self.status.clear();
+        self.chain = Some(chain);
+    }
+
+    /// Advance a running chain, called every frame.
+    pub fn update(&mut self, ctx: &Context) {
+        let Some(chain) = &mut self.chain else {
+            return;
+        };
+        if !chain.is_running() {
+            return;
+        }
+        for _ in 0..self.steps_per_frame.max(1) {
+            chain.advance();
+            if !chain.is_running() {
+                chain.envelopes();
+                break;
+            }
+        }
+        ctx.request_repaint();
+    }
+
+    fn labels(chain: &Chain, model: &Model) -> Vec<String> {
+        let mut labels: Vec<String> = chain
+            .variables
+            .iter()
+            .map(|variable| {
+                let name = match model.objects.get(&variable.id) {
+                    Some(Some(obj)) => obj.name.to_string(),
+                    _ => variable.id.clone(),
+                };
+                format!("{name} {}", variable.parameter.name())
+            })
+            .collect();
+        if chain.fit_sigma {
+            labels.push("noise σ".to_string());
+        }
+        labels
+    }
+
+    /// Variables, priors and sampler settings, returning whether to start a chain.
+    pub fn ui(&mut self, ui: &mut Ui, model: &Model) -> bool {
+        let running = self.is_running();
+        let mut remove = None;
+        ui.add_enabled_ui(!running, |ui| {
+            egui::Grid::new("mcmc_variables").show(ui, |ui| {
+                for heading in ["Object", "Parameter", "Prior", "", ""] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+                for (i, variable) in self.variables.iter_mut().enumerate() {
+                    let name = match model.objects.get(&variable.id) {
+                        Some(Some(obj)) => obj.name.to_string(),
+                        _ => format!("{} (missing)", variable.id),
+                    };
+                    ui.label(name);
+                    ui.label(format!(
+                        "{} ({})",
+                        variable.parameter.name(),
+                        variable.parameter.units()
+                    ));
+                    egui::ComboBox::from_id_source(("mcmc_prior", i))
+                        .selected_text(variable.prior.label())
+                        .show_ui(ui, |ui| {
+                            let (low, high) = match variable.prior {
+                                Prior::Uniform { min, max } => (min, max),
+                                Prior::Normal { mean, sigma } => (mean - sigma, mean + sigma),
+                            };
+                            let uniform = Prior::Uniform {
+                                min: low,
+                                max: high,
+                            };
+                            let normal = Prior::Normal {
+                                mean: (low + high) / 2.,
+                                sigma: (high - low) / 2.,
+                            };
+                            if ui
+                                .selectable_label(variable.prior.label() == "Uniform", "Uniform")
+                                .clicked()
+                            {
+                                variable.prior = uniform;
+                            }
+                            if ui
+                                .selectable_label(variable.prior.label() == "Normal", "Normal")
+                                .clicked()
+                            {
+                                variable.prior = normal;
+                            }
+                        });
+                    let speed = (variable.prior.width().abs() / 100.).max(1e-3);
+                    match &mut variable.prior {
+                        Prior::Uniform { min, max } => {
+                            ui.add(egui::DragValue::new(min).speed(speed).prefix("min "));
+                            ui.add(egui::DragValue::new(max).speed(speed).prefix("max "));
+                            if *max < *min {
+                                std::mem::swap(min, max);
+                            }
+                        }
+                        Prior::Normal { mean, sigma } => {
+                            ui.add(egui::DragValue::new(mean).speed(speed).prefix("mean "));
+                            ui.add(
+                                egui::DragValue::new(sigma)
+                                    .speed(speed)
+                                    .clamp_range(1e-6..=f64::INFINITY)
+                                    .prefix("σ "),
+                            );
+                        }
+                    }
+                    if ui.button("🗑").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = remove {
+                self.variables.remove(i);
+            }
+
+            let name = |id: &String| match model.objects.get(id) {
+                Some(Some(obj)) => format!("{}: {}", obj.id, obj.name),
+                _ => "None".to_string(),
+            };
+            let object = self
+                .new_object
+                .clone()
+                .filter(|id| matches!(model.objects.get(id), Some(Some(_))))
+                .or_else(|| model.selected_object_ids().into_iter().next())
+                .or_else(|| model.ordered_ids().into_iter().next());
+            self.new_object = object.clone();
+            let parameters: Vec<Parameter> =
+                match object.as_ref().and_then(|id| model.objects.get(id)) {
+                    Some(Some(obj)) => Parameter::ALL
+                        .into_iter()
+                        .filter(|parameter| parameter.get(&obj.object).is_some())
+                        .collect(),
+                    _ => vec![],
+                };
+            if !self
+                .new_parameter
+                .map_or(false, |parameter| parameters.contains(&parameter))
+            {
+                self.new_parameter = parameters.first().copied();
+            }
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("mcmc_new_object")
+                    .selected_text(object.as_ref().map_or("None".to_string(), name))
+                    .show_ui(ui, |ui| {
+                        for id in model.ordered_ids() {
+                            let label = name(&id);
+                            ui.selectable_value(&mut self.new_object, Some(id), label);
+                        }
+                    });
+                egui::ComboBox::from_id_source("mcmc_new_parameter")
+                    .selected_text(self.new_parameter.map_or("None".to_string(), |p| p.name()))
+                    .show_ui(ui, |ui| {
+                        for parameter in &parameters {
+                            ui.selectable_value(
+                                &mut self.new_parameter,
+                                Some(*parameter),
+                                parameter.name(),
+                            );
+                        }
+                    });
+                if ui.button("Add parameter").clicked() {
+                    if let (Some(id), Some(parameter)) = (object, self.new_parameter) {
+                        let exists = self
+                            .variables
+                            .iter()
+                            .any(|v| v.id == id && v.parameter == parameter);
+                        if !exists {
+                            let [min, max] = model.ranges.range(&parameter);
+                            self.variables.push(Variable {
+                                id,
+                                parameter,
+                                prior: Prior::Uniform { min, max },
+                            });
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+            egui::Grid::new("mcmc_settings").show(ui, |ui| {
+                ui.label("Data error σ");
+                ui.add(
+                    egui::DragValue::new(&mut self.sigma)
+                        .speed(0.01)
+                        .clamp_range(1e-6..=f64::INFINITY),
+                );
+                ui.checkbox(&mut self.fit_sigma, "Fit noise level");
+                ui.end_row();
+                ui.label("Walkers");
+                ui.add(egui::DragValue::new(&mut self.walkers).clamp_range(4..=256));
+                ui.label("Seed");
+                ui.add(egui::DragValue::new(&mut self.seed));
+                ui.end_row();
+                ui.label("Steps");
+                ui.add(egui::DragValue::new(&mut self.steps).clamp_range(10..=100000));
+                ui.label("Burn-in");
+                ui.add(egui::DragValue::new(&mut self.burn_in).clamp_range(0..=100000));
+                ui.end_row();
+                ui.label("Thin");
+                ui.add(egui::DragValue::new(&mut self.thin).clamp_range(1..=1000));
+                ui.label("Steps per frame");
+                ui.add(egui::DragValue::new(&mut self.steps_per_frame).clamp_range(1..=100));
+                ui.end_row();
+            });
+        });
+
+        let mut start = false;
+        ui.horizontal(|ui| {
+            if running {
+                if ui.button("Stop").clicked() {
+                    if let Some(chain) = &mut self.chain {
+                        chain.steps = chain.step;
+                        chain.envelopes();
+                    }
+                }
+            } else {
+                start = ui.button("Run").clicked();
+            }
+            if let Some(chain) = &self.chain {
+                ui.add(
+                    egui::ProgressBar::new(chain.step as f32 / chain.steps as f32)
+                        .desired_width(200.)
+                        .show_percentage(),
+                );
+                if chain.proposed > 0 {
+                    ui.label(format!(
+                        "acceptance {:.0}%",
+                        100. * chain.accepted as f64 / chain.proposed as f64
+                    ));
+                }
+            }
+        });
+        if !self.status.is_empty() {
+            ui.label(&self.status);
+        }
+        start
+    }
+
+    /// Posterior summary, marginals, pair plots and the credible envelopes of the data.
+    pub fn show(&self, ui: &mut Ui, model: &Model, width: f32) {
+        let Some(chain) = &self.chain else {
+            return;
+        };
+        if chain.samples.is_empty() {
+            ui.label("Sampling the burn-in...");
+            return;
+        }
+        let labels = Self::labels(chain, model);
+        let n = labels.len();
+        let columns: Vec<Vec<f64>> = (0..n)
+            .map(|k| chain.samples.iter().map(|theta| theta[k]).collect())
+            .collect();
+
+        ui.label(format!("{} posterior samples", chain.samples.len()));
+        egui::Grid::new("mcmc_summary")
+            .striped(true)
+            .show(ui, |ui| {
+                for heading in ["Parameter", "Mean", "Std", "2.5%", "Median", "97.5%"] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+                for (label, values) in labels.iter().zip(&columns) {
+                    let mean = values.iter().sum::<f64>() / values.len() as f64;
+                    let std = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>()
+                        / values.len() as f64)
+                        .sqrt();
+                    ui.label(label);
+                    for value in [
+                        mean,
+                        std,
+                        percentile(values, 2.5),
+                        percentile(values, 50.),
+                        percentile(values, 97.5),
+                    ] {
+                        ui.label(format!("{value:.3}"));
+                    }
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+        ui.label("Marginals (diagonal) and pairs");
+        let size = (width / n as f32).clamp(80., 200.);
+        egui::Grid::new("mcmc_pairs")
+            .spacing([2., 2.])
+            .show(ui, |ui| {
+                for i in 0..n {
+                    for j in 0..=i {
+                        let plot = Plot::new(("mcmc_pair", i, j))
+                            .width(size)
+                            .height(size)
+                            .show_axes([i == n - 1, j == 0])
+                            .allow_drag(false)
+                            .allow_zoom(false)
+                            .allow_scroll(false);
+                        if i == j {
+                            plot.show(ui, |plot_ui| {
+                                plot_ui.bar_chart(histogram(&columns[i], 30));
+                            });
+                        } else {
+                            let points: Vec<[f64; 2]> = columns[j]
+                                .iter()
+                                .zip(&columns[i])
+                                .map(|(x, y)| [*x, *y])
+                                .collect();
+                            plot.show(ui, |plot_ui| {
+                                plot_ui.points(Points::new(points).radius(1.));
+                            });
+                        }
+                    }
+                    ui.label(&labels[i]);
+                    ui.end_row();
+                }
+            });
+
+        for envelope in &chain.envelopes {
+            ui.separator();
+            ui.label(format!("Predicted data along the {}", envelope.name));
+            let trace = |values: &Vec<f64>| -> Vec<[f64; 2]> {
+                envelope
+                    .positions
+                    .iter()
+                    .zip(values)
+                    .map(|(x, v)| [*x, *v])
+                    .collect()
+            };
+            Plot::new(("mcmc_envelope", &envelope.name))
+                .view_aspect(2.5)
+                .width(width)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    plot_ui.points(
+                        Points::new(trace(&envelope.observed))
+                            .name("Observed")
+                            .radius(1.5),
+                    );
+                    for (k, name, style) in [
+                        (0, "95% credible", LineStyle::dotted_dense()),
+                        (4, "95% credible", LineStyle::dotted_dense()),
+                        (1, "68% credible", LineStyle::Dashed { length: 5. }),
+                        (3, "68% credible", LineStyle::Dashed { length: 5. }),
+                        (2, "Median", LineStyle::Solid),
+                    ] {
+                        plot_ui.line(
+                            Line::new(trace(&envelope.bands[k]))
+                                .name(name)
+                                .color(ENVELOPE_COLOUR)
+                                .style(style),
+                        );
+                    }
+                });
+        }
+    }
+}
+
+fn histogram(values: &[f64], n_bins: usize) -> BarChart {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let width = ((max - min) / n_bins as f64).max(1e-12);
+    let mut counts = vec![0usize; n_bins];
+    for value in values {
+        counts[(((value - min) / width) as usize).min(n_bins - 1)] += 1;
+    }
+    BarChart::new(
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| Bar::new(min + (i as f64 + 0.5) * width, count as f64).width(width))
+            .collect(),
+    )
+    .color(ENVELOPE_COLOUR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gravity_objects::Sphere;
+    use crate::testing::sphere_model;
+
+    #[test]
+    fn percentiles() {
+        let values = [3., 1., 5., 2., 4.];
+        assert_eq!(percentile(&values, 0.), 1.);
+        assert_eq!(percentile(&values, 25.), 2.);
+        assert_eq!(percentile(&values, 50.), 3.);
+        assert_eq!(percentile(&values, 62.5), 3.5);
+        assert_eq!(percentile(&values, 100.), 5.);
+        assert_eq!(percentile(&[7.], 97.5), 7.);
+        assert!(percentile(&[], 50.).is_nan());
+    }
+
+    /// Two spheres whose densities the data depend on linearly, so with a wide uniform
+    /// prior the posterior is the Gaussian of the least-squares fit.
+    #[test]
+    fn stretch_move_samples_linear_posterior() {
+        let model = sphere_model(&[-4., 3.].map(|x_centroid| Sphere {
+            x_centroid,
+            y_centroid: 0.,
+            z_centroid: -4.,
+            radius: 1.5,
+            density: 1000.,
+        }));
+        let points = Array2::from_shape_fn((41, 3), |(i, c)| match c {
+            0 => i as f64 * 0.5 - 10.,
+            _ => 0.,
+        });
+        let observed = model.calculate(&DataType::Gz, &points);
+        let sigma = 0.1 * observed.iter().copied().fold(0., f64::max);
+
+        // Posterior covariance σ² (JᵀJ)⁻¹ from the response per unit density.
+        let columns: Vec<Array1<f64>> = ["0", "1"]
+            .iter()
+            .map(|id| {
+                let mut single = model.clone();
+                single.objects.retain(|key, _| key == id);
+                single.calculate(&DataType::Gz, &points) / 1000.
+            })
+            .collect();
+        let [a, b, c] = [
+            columns[0].dot(&columns[0]),
+            columns[0].dot(&columns[1]),
+            columns[1].dot(&columns[1]),
+        ];
+        let determinant = a * c - b * b;
+        let deviations = [
+            sigma * (c / determinant).sqrt(),
+            sigma * (a / determinant).sqrt(),
+        ];
+
+        let mut uncertainty = Uncertainty {
+            variables: ["0", "1"]
+                .map(|id| Variable {
+                    id: id.to_string(),
+                    parameter: Parameter::Density,
+                    prior: Prior::Uniform {
+                        min: -5000.,
+                        max: 5000.,
+                    },
+                })
+                .to_vec(),
+            sigma,
+            seed: 7,
+            walkers: 16,
+            steps: 1500,
+            burn_in: 500,
+            thin: 5,
+            ..Uncertainty::default()
+        };
+        let profile = ObservedProfile {
+            name: "x profile",
+            points,
+            axis: 0,
+            observed,
+        };
+        uncertainty.start(&model, &DataType::Gz, vec![profile]);
+        let chain = uncertainty.chain.as_mut().unwrap();
+        while chain.is_running() {
+            chain.advance();
+        }
+        let acceptance = chain.accepted as f64 / chain.proposed as f64;
+        assert!((0.2..0.9).contains(&acceptance), "{acceptance}");
+        for (k, deviation) in deviations.iter().enumerate() {
+            let values: Vec<f64> = chain.samples.iter().map(|theta| theta[k]).collect();
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let sd = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+            assert!((mean - 1000.).abs() < 0.2 * deviation, "{k}: {mean}");
+            assert!((sd / deviation - 1.).abs() < 0.15, "{k}: {sd} {deviation}");
+            let [low, high] = [percentile(&values, 2.5), percentile(&values, 97.5)];
+            assert!(low < 1000. && 1000. < high, "{k}: {low} {high}");
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use crate::gravity_objects::GravityObject;
+use crate::map::{Colormap, GridData};
+use crate::model::Model;
+use egui::epaint::Mesh;
+use egui::{
+    Align2, Color32, Context, FontId, PointerButton, Pos2, Rect, Sense, Shape, Stroke, Ui, Vec2,
+};
+use ndarray::{Array2, Axis};
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+/// Near clipping distance in metres, triangles with a vertex closer than this are dropped.
+const NEAR: f64 = 0.05;
+
+/// Cuboid edges, indexed into `Cuboid::vertices_rotated`.
+const CUBOID_EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [1, 2],
+    [2, 3],
+    [3, 0],
+    [4, 5],
+    [5, 6],
+    [6, 7],
+    [7, 4],
+    [3, 5],
+    [4, 0],
+    [6, 2],
+    [7, 1],
+];
+
+/// Survey data shown alongside the bodies in the 3D view.
+pub struct Scene {
+    /// Survey stations, one array of (n, 3) points per line or grid.
+    pub points: Vec<Array2<f64>>,
+    pub surface: Option<Surface>,
+}
+
+/// Gridded data draped over the survey surface.
+pub struct Surface {
+    /// Grid points in the x-major order used by `GridData::from_points`.
+    pub points: Array2<f64>,
+    pub grid: GridData,
+    pub range: [f64; 2],
+    pub colormap: Colormap,
+}
+
+/// Orbit camera and display options for the 3D view. Everything is drawn on the CPU with
+/// the egui painter: triangles are depth sorted back to front and flat shaded.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct View3D {
+    pub open: bool,
+    /// Azimuth of the camera about the target, anticlockwise from +x in radians.
+    pub yaw: f64,
+    /// Elevation of the camera above the target in radians.
+    pub pitch: f64,
+    pub distance: f64,
+    pub target: [f64; 3],
+    /// Vertical field of view in radians.
+    pub fov: f64,
+    pub show_points: bool,
+    pub show_surface: bool,
+    /// Height in metres of the draped surface relief across the colour range.
+    pub relief: f64,
+    pub show_axes: bool,
+}
+
+impl Default for View3D {
+    fn default() -> Self {
+        Self {
+            open: false,
+            yaw: -FRAC_PI_2 - 0.6,
+            pitch: 0.5,
+            distance: 30.,
+            target: [0., 0., -2.],
+            fov: 45f64.to_radians(),
+            show_points: true,
+            show_surface: false,
+            relief: 0.,
+            show_axes: true,
+        }
+    }
+}
+
+/// Perspective camera for a single frame.
+struct Camera {
+    eye: [f64; 3],
+    forward: [f64; 3],
+    right: [f64; 3],
+    up: [f64; 3],
+    focal: f64,
+    centre: Pos2,
+}
+
+impl Camera {
+    fn new(view: &View3D, rect: Rect) -> Self {
+        let (sin_yaw, cos_yaw) = view.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = view.pitch.sin_cos();
+        let offset = [cos_pitch * cos_yaw, cos_pitch * sin_yaw, sin_pitch];
+        let eye = add(view.target, scale(offset, view.distance));
+        let forward = scale(offset, -1.);
+        let right = normalise(cross(forward, [0., 0., 1.]));
+        let up = cross(right, forward);
+        Self {
+            eye,
+            forward,
+            right,
+            up,
+            focal: rect.height() as f64 / 2. / (view.fov / 2.).tan(),
+            centre: rect.center(),
+        }
+    }
+
+    /// Screen position and depth along the view direction.
+    fn project(&self, point: [f64; 3]) -> Option<(Pos2, f64)> {
+        let v = sub(point, self.eye);
+        let depth = dot(v, self.forward);
+        if depth < NEAR {
+            return None;
+        }
+        let x = dot(v, self.right) / depth * self.focal;
+        let y = dot(v, self.up) / depth * self.focal;
+        Some((self.centre + Vec2::new(x as f32, -y as f32), depth))
+    }
+
+    /// World distance covered by one screen pixel at the target.
+    fn pixel_size(&self, distance: f64) -> f64 {
+        distance / self.focal
+    }
+}
+
+struct Face {
+    points: [Pos2; 3],
+    depth: f64,
+    colour: Color32,
+    /// Model key of the body the face belongs to, None for the data surface.
+    key: Option<String>,
+}
+
+impl View3D {
+    pub fn show(&mut self, ctx: &Context, ui: &mut Ui, model: &mut Model, scene: &Scene) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_points, "Survey points");
+            ui.checkbox(&mut self.show_surface, "Data surface");
+            ui.checkbox(&mut self.show_axes, "Axes");
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Reset").clicked() {
+                *self = Self {
+                    open: self.open,
+                    show_points: self.show_points,
+                    show_surface: self.show_surface,
+                    relief: self.relief,
+                    show_axes: self.show_axes,
+                    ..Default::default()
+                };
+            }
+            if ui.button("Top").clicked() {
+                self.yaw = -FRAC_PI_2;
+                self.pitch = FRAC_PI_2 - 1e-3;
+            }
+            if ui.button("Front (XZ)").clicked() {
+                self.yaw = -FRAC_PI_2;
+                self.pitch = 0.;
+            }
+            if ui.button("Side (YZ)").clicked() {
+                self.yaw = 0.;
+                self.pitch = 0.;
+            }
+            if self.show_surface {
+                ui.add(egui::Slider::new(&mut self.relief, -20.0..=20.).text("relief m"));
+            }
+        });
+
+        let width = ui.available_width().max(300.);
+        let (response, painter) =
+            ui.allocate_painter(Vec2::new(width, width * 0.75), Sense::click_and_drag());
+        let rect = response.rect;
+
+        // Orbit with the primary button, pan with the secondary or middle button (or shift
+        // and primary), zoom with the scroll wheel.
+        let shift = ctx.input().modifiers.shift;
+        let delta = response.drag_delta();
+        let pan = response.dragged_by(PointerButton::Secondary)
+            || response.dragged_by(PointerButton::Middle)
+            || (response.dragged_by(PointerButton::Primary) && shift);
+        if pan {
+            let camera = Camera::new(self, rect);
+            let pixel = camera.pixel_size(self.distance);
+            let shift = add(
+                scale(camera.right, -delta.x as f64 * pixel),
+                scale(camera.up, delta.y as f64 * pixel),
+            );
+            self.target = add(self.target, shift);
+        } else if response.dragged_by(PointerButton::Primary) {
+            self.yaw = (self.yaw - delta.x as f64 * 0.01) % TAU;
+            self.pitch =
+                (self.pitch + delta.y as f64 * 0.01).clamp(-FRAC_PI_2 + 1e-3, FRAC_PI_2 - 1e-3);
+        }
+        if response.hovered() {
+            let scroll = ctx.input().scroll_delta.y as f64;
+            self.distance = (self.distance * (-scroll * 0.002).exp()).clamp(0.5, 1000.);
+        }
+
+        let camera = Camera::new(self, rect);
+        painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+
+        let light = normalise(add(
+            scale(camera.forward, -1.),
+            add(scale(camera.up, 0.6), scale(camera.right, -0.3)),
+        ));
+        let mut faces: Vec<Face> = vec![];
+        for (key, object) in model.objects.iter() {
+            if let Some(obj) = object {
+                if obj.is_hidden {
+                    continue;
+                }
+                let mesh = obj.object.mesh();
+                for triangle in &mesh.triangles {
+                    let normal = mesh.normal(triangle);
+                    let vertices = triangle.map(|i| mesh.vertices[i]);
+                    if dot(normal, sub(camera.eye, vertices[0])) <= 0. {
+                        continue;
+                    }
+                    let mut colour = shade(obj.colour, 0.35 + 0.65 * dot(normal, light).max(0.));
+                    if obj.is_selected {
+                        colour = lerp_colour(colour, Color32::WHITE, 0.3);
+                    }
+                    if let Some(face) = project_triangle(&camera, vertices, colour, Some(key)) {
+                        faces.push(face);
+                    }
+                }
+            }
+        }
+        if let (true, Some(surface)) = (self.show_surface, &scene.surface) {
+            faces.extend(self.surface_faces(&camera, surface));
+        }
+        faces.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+
+        let mut mesh = Mesh::default();
+        for face in &faces {
+            let index = mesh.vertices.len() as u32;
+            for point in face.points {
+                mesh.colored_vertex(point, face.colour);
+            }
+            mesh.add_triangle(index, index + 1, index + 2);
+        }
+        painter.add(Shape::mesh(mesh));
+
+        let outline = Stroke::new(2., ui.visuals().strong_text_color());
+        for obj in model.objects.values().flatten() {
+            if !obj.is_selected || obj.is_hidden {
+                continue;
+            }
+            match &obj.object {
+                GravityObject::Cuboid(cuboid) => {
+                    let verts = cuboid.vertices_rotated();
+                    let verts: Vec<[f64; 3]> = verts
+                        .axis_iter(Axis(0))
+                        .map(|v| [v[0], v[1], v[2]])
+                        .collect();
+                    for [i, j] in CUBOID_EDGES {
+                        if let (Some((a, _)), Some((b, _))) =
+                            (camera.project(verts[i]), camera.project(verts[j]))
+                        {
+                            painter.line_segment([a, b], outline);
+                        }
+                    }
+                }
+                GravityObject::Polyhedron(polyhedron) => {
+                    let verts = &polyhedron.mesh.vertices;
+                    for [i, j] in polyhedron.mesh.feature_edges(30f64.to_radians()) {
+                        if let (Some((a, _)), Some((b, _))) =
+                            (camera.project(verts[i]), camera.project(verts[j]))
+                        {
+                            painter.line_segment([a, b], outline);
+                        }
+                    }
+                }
+                GravityObject::Sphere(sphere) => {
+                    // Silhouette: the circle through the centre facing the camera.
+                    let centre = [sphere.x_centroid, sphere.y_centroid, sphere.z_centroid];
+                    let silhouette: Option<Vec<Pos2>> = (0..64)
+                        .map(|i| {
+                            let t = TAU * i as f64 / 64.;
+                            let offset = add(
+                                scale(camera.right, sphere.radius * t.cos()),
+                                scale(camera.up, sphere.radius * t.sin()),
+                            );
+                            camera.project(add(centre, offset)).map(|(p, _)| p)
+                        })
+                        .collect();
+                    if let Some(silhouette) = silhouette {
+                        painter.add(Shape::closed_line(silhouette, outline));
+                    }
+                }
+            }
+        }
+
+        if self.show_points {
+            let colour = ui.visuals().text_color();
+            for points in &scene.points {
+                for point in points.axis_iter(Axis(0)) {
+                    if let Some((p, _)) = camera.project([point[0], point[1], point[2]]) {
+                        if rect.contains(p) {
+                            painter.circle_filled(p, 1.5, colour);
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.show_axes {
+            let length = (self.distance / 8.).max(0.5);
+            let origin = [0., 0., 0.];
+            for (axis, label, colour) in [
+                ([length, 0., 0.], "x", Color32::from_rgb(220, 60, 60)),
+                ([0., length, 0.], "y", Color32::from_rgb(60, 180, 60)),
+                ([0., 0., length], "z", Color32::from_rgb(60, 100, 230)),
+            ] {
+                if let (Some((a, _)), Some((b, _))) = (camera.project(origin), camera.project(axis))
+                {
+                    painter.line_segment([a, b], Stroke::new(2., colour));
+                    painter.text(
+                        b,
+                        Align2::CENTER_CENTER,
+                        label,
+                        FontId::proportional(14.),
+                        colour,
+                    );
+                }
+            }
+        }
+
+        // Click selects the frontmost body under the pointer, shift adds to the selection.
+        if response.clicked() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let hit = faces
+                    .iter()
+                    .rev()
+                    .find(|face| face.key.is_some() && in_triangle(pointer, face.points))
+                    .and_then(|face| face.key.clone());
+                if !shift {
+                    model.deselect_all();
+                }
+                if let Some(Some(obj)) = hit.and_then(|key| model.objects.get_mut(&key)) {
+                    obj.is_selected = !obj.is_selected || !shift;
+                }
+            }
+        }
+        ui.label("drag: orbit, right drag / shift drag: pan, scroll: zoom, click: select");
+    }
+
+    fn surface_faces(&self, camera: &Camera, surface: &Surface) -> Vec<Face> {
+        let (nx, ny) = surface.grid.values.dim();
+        let span = surface.range[1] - surface.range[0];
+        let vertex = |i: usize, j: usize| {
+            let idx = i * ny + j;
+            let value = surface.grid.values[[i, j]];
+            let t = (value - surface.range[0]) / span;
+            let z = surface.points[[idx, 2]] + self.relief * t;
+            ([surface.grid.x[i], surface.grid.y[j], z], t)
+        };
+        let mut faces = vec![];
+        for i in 0..nx.saturating_sub(1) {
+            for j in 0..ny.saturating_sub(1) {
+                let corners = [
+                    vertex(i, j),
+                    vertex(i + 1, j),
+                    vertex(i + 1, j + 1),
+                    vertex(i, j + 1),
+                ];
+                for [a, b, c] in [[0, 1, 2], [0, 2, 3]] {
+                    let t = (corners[a].1 + corners[b].1 + corners[c].1) / 3.;
+                    let colour = surface.colormap.colour(t);
+                    let vertices = [corners[a].0, corners[b].0, corners[c].0];
+                    if let Some(face) = project_triangle(camera, vertices, colour, None) {
+                        faces.push(face);
+                    }
+                }
+            }
+        }
+        faces
+    }
+}
+
+fn project_triangle(
+    camera: &Camera,
+    vertices: [[f64; 3]; 3],
+    colour: Color32,
+    key: Option<&String>,
+) -> Option<Face> {
+    let mut points = [Pos2::ZERO; 3];
+    let mut depth = 0.;
+    for (point, vertex) in points.iter_mut().zip(vertices) {
+        let (p, d) = camera.project(vertex)?;
+        *point = p;
+        depth += d / 3.;
+    }
+    Some(Face {
+        points,
+        depth,
+        colour,
+        key: key.cloned(),
+    })
+}
+
+fn in_triangle(p: Pos2, [a, b, c]: [Pos2; 3]) -> bool {
+    let side = |a: Pos2, b: Pos2| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    let (d1, d2, d3) = (side(a, b), side(b, c), side(c, a));
+    let negative = d1 < 0. || d2 < 0. || d3 < 0.;
+    let positive = d1 > 0. || d2 > 0. || d3 > 0.;
+    !(negative && positive)
+}
+
+fn shade(colour: Color32, intensity: f64) -> Color32 {
+    let k = intensity.clamp(0., 1.);
+    Color32::from_rgb(
+        (colour.r() as f64 * k) as u8,
+        (colour.g() as f64 * k) as u8,
+        (colour.b() as f64 * k) as u8,
+    )
+}
+
+fn lerp_colour(a: Color32, b: Color32, t: f64) -> Color32 {
+    let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t) as u8;
+    Color32::from_rgb(lerp(a.r(), b.r()), lerp(a.g(), b.g()), lerp(a.b(), b.b()))
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f64; 3], k: f64) -> [f64; 3] {
+    [a[0] * k, a[1] * k, a[2] * k]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalise(a: [f64; 3]) -> [f64; 3] {
+    let length = dot(a, a).sqrt();
+    if length > 0. {
+        scale(a, 1. / length)
+    } else {
+        a
+    }
+}
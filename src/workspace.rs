@@ -0,0 +1,282 @@
+use crate::gravity_objects::DataType;
+use crate::model::Model;
+use egui::plot::{Line, LineStyle, PlotUi};
+use egui::{Color32, Ui};
+use ndarray::{Array1, Array2};
+
+const COLOURS: [Color32; 6] = [
+    Color32::from_rgb(31, 119, 180),
+    Color32::from_rgb(255, 127, 14),
+    Color32::from_rgb(44, 160, 44),
+    Color32::from_rgb(214, 39, 40),
+    Color32::from_rgb(148, 103, 189),
+    Color32::from_rgb(23, 190, 207),
+];
+const DIFFERENCE_COLOUR: Color32 = Color32::from_rgb(227, 119, 194);
+
+/// Model open in the workspace. The active model lives in the app and its entry holds a
+/// placeholder until another model is activated.
+#[derive(serde::Deserialize, serde::Serialize, Clone)]
+pub struct Entry {
+    pub model: Model,
+    pub colour: Color32,
+    /// Overlay the combined response in the data plots while another model is active.
+    pub overlay: bool,
+}
+
+/// Residual statistics of a model's response against observed data.
+pub struct Misfit {
+    pub rms: f64,
+    pub mean: f64,
+    pub max: f64,
+    /// Chi-squared per datum for the noise level, if there is one.
+    pub chi2: Option<f64>,
+}
+
+impl Misfit {
+    pub fn new(observed: &Array1<f64>, predicted: &Array1<f64>, sigma: Option<f64>) -> Self {
+        let residuals = observed - predicted;
+        let n = residuals.len().max(1) as f64;
+        let sum_squares = residuals.dot(&residuals);
+        Self {
+            rms: (sum_squares / n).sqrt(),
+            mean: residuals.sum() / n,
+            max: residuals.iter().fold(0., |max: f64, r| max.max(r.abs())),
+            chi2: sigma
+                .filter(|sigma| *sigma > 0.)
+                .map(|sigma| sum_squares / sigma.powi(2) / n),
+        }
+    }
+}
+
+/// Alternative models open side by side, e.g. a void and a low-density fill, with their
+/// responses overlaid and differenced in the data plots.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub open: bool,
+    pub entries: Vec<Entry>,
+    pub active: usize,
+    pub show_difference: bool,
+    /// Models differenced in the data plots, first minus second.
+    pub difference: [usize; 2],
+    /// Model whose data, with noise if enabled, is the observed data for misfits.
+    pub reference: usize,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self {
+            open: false,
+            entries: vec![Entry {
+                model: Model::default(),
+                colour: COLOURS[0],
+                overlay: true,
+            }],
+            active: 0,
+            show_difference: false,
+            difference: [0, 0],
+            reference: 0,
+        }
+    }
+}
+
+impl Workspace {
+    /// All models in order, with the live `active` model in place of its placeholder.
+    pub fn models<'a>(&'a self, active: &'a Model) -> Vec<(&'a Entry, &'a Model)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let model = if i == self.active {
+                    active
+                } else {
+                    &entry.model
+                };
+                (entry, model)
+            })
+            .collect()
+    }
+
+    pub fn colour(&self) -> Color32 {
+        self.entries[self.active].colour
+    }
+
+    pub fn is_comparing(&self) -> bool {
+        self.entries.len() > 1
+    }
+
+    /// Make model `index` the one being edited.
+    pub fn activate(&mut self, model: &mut Model, index: usize) {
+        if index == self.active || index >= self.entries.len() {
+            return;
+        }
+        std::mem::swap(model, &mut self.entries[self.active].model);
+        std::mem::swap(model, &mut self.entries[index].model);
+        self.active = index;
+    }
+
+    /// Add a model and make it active.
+    pub fn add(&mut self, model: &mut Model, new: Model) {
+        let colour = COLOURS[self.entries.len() % COLOURS.len()];
+        self.entries.push(Entry {
+            model: new,
+            colour,
+            overlay: true,
+        });
+        self.activate(model, self.entries.len() - 1);
+    }
+
+    fn remove(&mut self, model: &mut Model, index: usize) {
+        if self.entries.len() < 2 {
+            return;
+        }
+        if index == self.active {
+            self.activate(model, if index == 0 { 1 } else { 0 });
+        }
+        self.entries.remove(index);
+        let shift = |i: &mut usize| {
+            if *i > index {
+                *i -= 1;
+            } else if *i == index {
+                *i = 0;
+            }
+        };
+        if self.active > index {
+            self.active -= 1;
+        }
+        shift(&mut self.reference);
+        self.difference.iter_mut().for_each(shift);
+    }
+
+    /// Name and colour of the active model's combined response, tagged with the model
+    /// while comparing.
+    pub fn combined_style(&self, model: &Model, colour: Color32) -> (String, Color32) {
+        if self.is_comparing() {
+            (format!("Combined ({})", model.name), self.colour())
+        } else {
+            ("Combined".to_string(), colour)
+        }
+    }
+
+    /// Combined responses of the other overlaid models and the difference trace.
+    pub fn show_overlays(
+        &self,
+        plot_ui: &mut PlotUi,
+        model: &Model,
+        data_type: &DataType,
+        points: &Array2<f64>,
+        trace: impl Fn(&Array1<f64>) -> Vec<[f64; 2]>,
+    ) {
+        if !self.is_comparing() {
+            return;
+        }
+        let models = self.models(model);
+        for (i, (entry, other)) in models.iter().enumerate() {
+            if i != self.active && entry.overlay {
+                plot_ui.line(
+                    Line::new(trace(&other.calculate(data_type, points)))
+                        .name(&other.name)
+                        .color(entry.colour)
+                        .style(LineStyle::dashed_loose()),
+                );
+            }
+        }
+        let [a, b] = self.difference;
+        if self.show_difference && a != b && b < models.len() && a < models.len() {
+            let difference =
+                models[a].1.calculate(data_type, points) - models[b].1.calculate(data_type, points);
+            plot_ui.line(
+                Line::new(trace(&difference))
+                    .name(format!("{} − {}", models[a].1.name, models[b].1.name))
+                    .color(DIFFERENCE_COLOUR)
+                    .width(2.),
+            );
+        }
+    }
+
+    /// Model list with activation, colours, overlays and the difference choice.
+    pub fn ui(&mut self, ui: &mut Ui, model: &mut Model) {
+        let mut activate = None;
+        let mut remove = None;
+        let n = self.entries.len();
+        egui::Grid::new("workspace_models").show(ui, |ui| {
+            for heading in ["Active", "Name", "Colour", "Overlay", ""] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for i in 0..n {
+                if ui.radio(i == self.active, "").clicked() {
+                    activate = Some(i);
+                }
+                let entry = &mut self.entries[i];
+                let name = if i == self.active {
+                    &mut model.name
+                } else {
+                    &mut entry.model.name
+                };
+                ui.add(egui::TextEdit::singleline(name).desired_width(120.));
+                ui.color_edit_button_srgba(&mut entry.colour);
+                ui.add_enabled(
+                    i != self.active,
+                    egui::Checkbox::new(&mut entry.overlay, ""),
+                );
+                if ui.add_enabled(n > 1, egui::Button::new("🗑")).clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = activate {
+            self.activate(model, i);
+        }
+        if let Some(i) = remove {
+            self.remove(model, i);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("New model").clicked() {
+                let new = Model {
+                    name: format!("Model {}", self.entries.len() + 1),
+                    ..Model::default()
+                };
+                self.add(model, new);
+            }
+            if ui.button("Duplicate active").clicked() {
+                let new = Model {
+                    name: format!("{} copy", model.name),
+                    ..model.clone()
+                };
+                self.add(model, new);
+            }
+        });
+
+        ui.separator();
+        let names: Vec<String> = self
+            .models(model)
+            .iter()
+            .map(|(_, model)| model.name.clone())
+            .collect();
+        let combo = |ui: &mut Ui, id: &str, index: &mut usize| {
+            *index = (*index).min(names.len() - 1);
+            egui::ComboBox::from_id_source(id)
+                .selected_text(&names[*index])
+                .show_ui(ui, |ui| {
+                    for (i, name) in names.iter().enumerate() {
+                        ui.selectable_value(index, i, name);
+                    }
+                });
+        };
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_difference, "Difference");
+            let [mut a, mut b] = self.difference;
+            combo(ui, "workspace_difference_a", &mut a);
+            ui.label("−");
+            combo(ui, "workspace_difference_b", &mut b);
+            self.difference = [a, b];
+        });
+        ui.horizontal(|ui| {
+            ui.label("Observed data from");
+            combo(ui, "workspace_reference", &mut self.reference);
+        });
+    }
+}